@@ -0,0 +1,112 @@
+use std::{hint::black_box, path::Path};
+
+use chrono::{Duration, NaiveDateTime};
+use criterion::{Criterion, criterion_group, criterion_main};
+use geo::MultiPolygon;
+use hrdf_parser::{Hrdf, Version};
+use hrdf_routing_engine::{
+    IsochroneArgs, IsochroneConfig, compute_isochrones, hrdf_from_directory, plan_journey,
+};
+
+/// Origin-destination pairs of the fixture, departing at 08:00, with their expected arrival time:
+/// Alpha - Echo, Alpha - Delta, Charlie - Alpha and Alpha - Hotel.
+const OD_PAIRS: [(i32, i32, &str); 4] = [
+    (8500001, 8500005, "2025-06-02 08:32:00"),
+    (8500001, 8500004, "2025-06-02 08:25:00"),
+    (8500003, 8500001, "2025-06-02 08:42:00"),
+    (8500001, 8500008, "2025-06-02 09:05:00"),
+];
+
+fn parse_date_time(value: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap()
+}
+
+fn load_hrdf() -> Hrdf {
+    // The small timetable of the tests, the benchmarks don't need the network.
+    hrdf_from_directory(
+        Version::V_5_40_41_2_0_7,
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hrdf"),
+    )
+    .unwrap()
+}
+
+fn bench_plan_journey(c: &mut Criterion) {
+    let hrdf = load_hrdf();
+    let departure_at = parse_date_time("2025-06-02 08:00:00");
+    let cases: Vec<(i32, i32, NaiveDateTime)> = OD_PAIRS
+        .iter()
+        .map(|&(from_id, to_id, arrival_at)| (from_id, to_id, parse_date_time(arrival_at)))
+        .collect();
+
+    // Every iteration checks the arrival times, the benchmark then fails if they change.
+    c.bench_function("plan_journey", |b| {
+        b.iter(|| {
+            for &(from_id, to_id, arrival_at) in &cases {
+                let route = plan_journey(
+                    &hrdf,
+                    black_box(from_id),
+                    black_box(to_id),
+                    departure_at,
+                    10,
+                    false,
+                )
+                .unwrap();
+                assert_eq!(route.arrival_at(), arrival_at, "{from_id} - {to_id}");
+            }
+        })
+    });
+}
+
+/// The circles are united in parallel, in any order: the areas are equal up to the rounding.
+fn assert_same_areas(areas: &[f64], expected: &[f64]) {
+    assert_eq!(areas.len(), expected.len());
+    for (area, expected) in areas.iter().zip(expected) {
+        assert!(
+            (area - expected).abs() <= expected * 1e-6,
+            "{area} != {expected}"
+        );
+    }
+}
+
+fn bench_compute_isochrones(c: &mut Criterion) {
+    let hrdf = load_hrdf();
+    // No excluded polygons, they would have to be downloaded.
+    let excluded_polygons = MultiPolygon::new(vec![]);
+    let config = IsochroneConfig::new(&excluded_polygons);
+    // Alpha
+    let isochrone_args = IsochroneArgs {
+        latitude: 46.951081,
+        longitude: 7.438637,
+        departure_at: parse_date_time("2025-06-02 08:00:00"),
+        time_limit: Duration::minutes(60),
+        interval: Duration::minutes(10),
+        num_bands: None,
+        max_num_explorable_connections: 10,
+        num_starting_points: 5,
+        countries: Vec::new(),
+        snap_to_interval: false,
+        apply_excluded_polygons: true,
+        verbose: false,
+    };
+
+    let areas = compute_isochrones(&hrdf, isochrone_args.clone(), &config)
+        .unwrap()
+        .areas()
+        .to_vec();
+    assert_eq!(areas.len(), 6);
+
+    c.bench_function("compute_isochrones", |b| {
+        b.iter(|| {
+            let isochrone_map =
+                compute_isochrones(&hrdf, black_box(isochrone_args.clone()), &config).unwrap();
+            assert_same_areas(isochrone_map.areas(), &areas);
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_plan_journey, bench_compute_isochrones
+}
+criterion_main!(benches);
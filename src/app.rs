@@ -1,86 +1,84 @@
 use orx_parallel::*;
 use std::time::Instant;
+#[cfg(feature = "hectare")]
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+};
 
-use crate::isochrone::{self, IsochroneDisplayMode, compute_isochrones};
-use crate::{IsochroneArgs, RResult};
-use chrono::Duration;
-use geo::MultiPolygon;
+use crate::isochrone::{self, IsochroneConfig, IsochroneOutputFormat, compute_isochrones};
+use crate::{IsochroneArgs, OutputArgs, RResult};
 use hrdf_parser::{Coordinates, Hrdf};
 use isochrone::compute_optimal_isochrones;
 
 #[cfg(feature = "hectare")]
 use crate::{
-    IsochroneHectareArgs,
-    isochrone::externals::{HectareData, HectareRecord},
+    IsochroneHectareArgs, RError, StopIndex, WALKING_SPEED_IN_KILOMETERS_PER_HOUR,
+    isochrone::{
+        compute_walking_isochrone,
+        externals::{HectareData, HectareRecord},
+    },
 };
 
 use self::isochrone::compute_average_isochrones;
 use self::isochrone::compute_worst_isochrones;
 use self::isochrone::utils::wgs84_to_lv95;
 
-#[allow(clippy::too_many_arguments)]
 pub fn run_simple(
     hrdf: Hrdf,
-    excluded_polygons: MultiPolygon,
     isochrone_args: IsochroneArgs,
-    display_mode: IsochroneDisplayMode,
-    num_threads: usize,
+    config: &IsochroneConfig,
+    format: IsochroneOutputFormat,
+    output_args: &OutputArgs,
 ) -> RResult<()> {
     let time_limit = isochrone_args.time_limit.num_minutes();
-    let isochrone_interval = isochrone_args.interval.num_minutes();
+    let isochrone_interval = isochrone_args.effective_interval().num_minutes();
 
     let (x, y) = wgs84_to_lv95(isochrone_args.latitude, isochrone_args.longitude);
     let coord = Coordinates::new(hrdf_parser::CoordinateSystem::LV95, x, y);
 
-    #[cfg(feature = "svg")]
-    let iso = compute_isochrones(
-        &hrdf,
-        &excluded_polygons,
-        isochrone_args,
-        display_mode,
-        num_threads,
-    );
+    let iso = compute_isochrones(&hrdf, isochrone_args, config)?;
 
-    #[cfg(feature = "svg")]
-    iso.write_svg(
-        &format!("isochrones_{}_{}.svg", time_limit, isochrone_interval),
+    iso.write(
+        &output_args.file_path(
+            &format!("isochrones_{}_{}", time_limit, isochrone_interval),
+            format.extension(),
+        ),
+        format,
         1.0 / 100.0,
         Some(coord),
     )?;
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
 pub fn run_average(
     hrdf: Hrdf,
-    excluded_polygons: MultiPolygon,
     isochrone_args: IsochroneArgs,
-    delta_time: Duration,
-    num_threads: usize,
+    config: &IsochroneConfig,
+    format: IsochroneOutputFormat,
+    output_args: &OutputArgs,
 ) -> RResult<()> {
     let time_limit = isochrone_args.time_limit.num_minutes();
-    let isochrone_interval = isochrone_args.interval.num_minutes();
+    let isochrone_interval = isochrone_args.effective_interval().num_minutes();
 
     let (x, y) = wgs84_to_lv95(isochrone_args.latitude, isochrone_args.longitude);
     let coord = Coordinates::new(hrdf_parser::CoordinateSystem::LV95, x, y);
 
-    #[cfg(feature = "svg")]
-    let iso = compute_average_isochrones(
-        &hrdf,
-        &excluded_polygons,
-        isochrone_args,
-        delta_time,
-        num_threads,
-    );
-
-    #[cfg(feature = "svg")]
-    iso.write_svg(
-        &format!(
-            "average_isochrones_{}_{}_{}.svg",
-            time_limit,
-            isochrone_interval,
-            delta_time.num_minutes()
+    let iso = compute_average_isochrones(&hrdf, isochrone_args, config)?;
+
+    iso.write(
+        &output_args.file_path(
+            &format!(
+                "average_isochrones_{}_{}_{}",
+                time_limit,
+                isochrone_interval,
+                config.delta_time().num_minutes()
+            ),
+            format.extension(),
         ),
+        format,
         1.0 / 100.0,
         Some(coord),
     )?;
@@ -88,26 +86,99 @@ pub fn run_average(
     Ok(())
 }
 
+/// Computes the optimal isochrone surface of each hectare which doesn't have one yet.
+///
+/// The hectares are computed in parallel and share the same immutable state: the HRDF data with
+/// its indexes and the excluded polygons are borrowed by every computation, never rebuilt or
+/// copied per hectare. Keep it that way, rebuilding any of them per record makes a country-wide
+/// run unfeasible.
 #[cfg(feature = "hectare")]
-#[allow(clippy::too_many_arguments)]
 pub fn run_surface_per_ha(
     hrdf: Hrdf,
-    excluded_polygons: MultiPolygon,
     hectare: HectareData,
     isochrone_args: IsochroneHectareArgs,
-    delta_time: Duration,
-    display_mode: IsochroneDisplayMode,
-    num_threads: usize,
+    config: &IsochroneConfig,
 ) -> RResult<Vec<HectareRecord>> {
+    surfaces_per_ha(&hrdf, hectare, isochrone_args, config, |record| record)
+}
+
+/// Same as run_surface_per_ha, but each record is written to path as soon as it is computed,
+/// one GeoJSON point feature per line (newline-delimited GeoJSON). The records are therefore
+/// not kept in memory and the file contains the finished ones if the run is interrupted. The
+/// lines are in the order of completion.
+#[cfg(feature = "hectare")]
+pub fn run_surface_per_ha_to_geojsonl(
+    hrdf: Hrdf,
+    hectare: HectareData,
+    isochrone_args: IsochroneHectareArgs,
+    config: &IsochroneConfig,
+    path: &Path,
+) -> RResult<()> {
+    let writer = Mutex::new(BufWriter::new(File::create(path)?));
+    // The first error stops the writing, the computation itself cannot be interrupted.
+    let error = Mutex::new(None);
+
+    surfaces_per_ha(&hrdf, hectare, isochrone_args, config, |record| {
+        let mut error = error.lock().unwrap();
+        if error.is_none()
+            && let Err(e) = write_geojsonl_record(&mut *writer.lock().unwrap(), &record)
+        {
+            *error = Some(e);
+        }
+    })?;
+
+    match error.into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(writer.into_inner().unwrap().flush()?),
+    }
+}
+
+#[cfg(feature = "hectare")]
+fn write_geojsonl_record(writer: &mut impl Write, record: &HectareRecord) -> RResult<()> {
+    let feature = serde_json::json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "Point",
+            "coordinates": [record.longitude, record.latitude],
+        },
+        "properties": {
+            "reli": record.reli,
+            "population": record.population,
+            "area": record.area,
+        },
+    });
+    serde_json::to_writer(&mut *writer, &feature)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Computes the missing surfaces and passes each record to on_record once done, from the worker
+/// threads. A hectare without any stop in range is only reached by walking, any other error of
+/// the isochrones is returned once all the hectares are done.
+#[cfg(feature = "hectare")]
+fn surfaces_per_ha<T, F>(
+    hrdf: &Hrdf,
+    hectare: HectareData,
+    isochrone_args: IsochroneHectareArgs,
+    config: &IsochroneConfig,
+    on_record: F,
+) -> RResult<Vec<T>>
+where
+    T: Send + Sync,
+    F: Fn(HectareRecord) -> T + Sync,
+{
+    use chrono::Duration;
     use std::sync::RwLock;
 
+    // Built once for all the hectares.
+    let stop_index = StopIndex::new(hrdf.data_storage());
+    let config = &config.with_stop_index(&stop_index);
     let total_time = RwLock::new(Instant::now());
     let locked_counter = RwLock::new(0);
     let data = hectare.data();
     let total = data.len();
-    let id_pos_surf = data
-        .into_par()
-        .num_threads(num_threads)
+    data.into_par()
+        .num_threads(config.num_threads())
         .map(|record| {
             let start = Instant::now();
             let HectareRecord {
@@ -126,8 +197,6 @@ pub fn run_surface_per_ha(
             let he_re = if area.is_some() {
                 record
             } else {
-                use crate::utils::inner_threads;
-
                 let IsochroneHectareArgs {
                     departure_at,
                     time_limit,
@@ -141,20 +210,29 @@ pub fn run_surface_per_ha(
                     departure_at,
                     time_limit,
                     interval: time_limit,
+                    num_bands: None,
                     max_num_explorable_connections,
                     num_starting_points,
+                    countries: Vec::new(),
+                    snap_to_interval: false,
+                    apply_excluded_polygons: true,
                     verbose: !verbose,
                 };
-                let opt_iso = compute_optimal_isochrones(
-                    &hrdf,
-                    &excluded_polygons,
-                    isochrone_args,
-                    delta_time,
-                    display_mode,
-                    inner_threads(num_threads, true)
-                );
-
-                let area = opt_iso.compute_max_area();
+                let area = match compute_optimal_isochrones(hrdf, isochrone_args, &config.nested()) {
+                    Ok(opt_iso) => opt_iso.compute_max_area(),
+                    Err(e @ RError::NoStopWithinAccessRange(..)) => {
+                        // Without any stop in range, the hectare is only reached by walking.
+                        log::warn!("{e}, {reli} is only reached by walking");
+                        compute_walking_isochrone(
+                            latitude,
+                            longitude,
+                            time_limit,
+                            WALKING_SPEED_IN_KILOMETERS_PER_HOUR,
+                        )
+                        .compute_max_area()
+                    }
+                    Err(e) => return Err(e),
+                };
                 HectareRecord {
                     reli,
                     longitude,
@@ -186,43 +264,34 @@ pub fn run_surface_per_ha(
                     }
                 }
             }
-            he_re
+            Ok(on_record(he_re))
         })
-        .collect();
-
-    Ok(id_pos_surf)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .collect()
 }
 
-#[allow(clippy::too_many_arguments)]
 pub fn run_optimal(
     hrdf: Hrdf,
-    excluded_polygons: MultiPolygon,
     isochrone_args: IsochroneArgs,
-    delta_time: Duration,
-    display_mode: IsochroneDisplayMode,
-    num_threads: usize,
+    config: &IsochroneConfig,
+    format: IsochroneOutputFormat,
+    output_args: &OutputArgs,
 ) -> RResult<()> {
     let time_limit = isochrone_args.time_limit.num_minutes();
-    let isochrone_interval = isochrone_args.interval.num_minutes();
+    let isochrone_interval = isochrone_args.effective_interval().num_minutes();
 
     let (x, y) = wgs84_to_lv95(isochrone_args.latitude, isochrone_args.longitude);
     let coord = Coordinates::new(hrdf_parser::CoordinateSystem::LV95, x, y);
 
-    let opt_iso = compute_optimal_isochrones(
-        &hrdf,
-        &excluded_polygons,
-        isochrone_args,
-        delta_time,
-        display_mode,
-        num_threads,
-    );
+    let opt_iso = compute_optimal_isochrones(&hrdf, isochrone_args, config)?;
 
-    #[cfg(feature = "svg")]
-    opt_iso.write_svg(
-        &format!(
-            "optimal_isochrones_{}_{}.svg",
-            time_limit, isochrone_interval
+    opt_iso.write(
+        &output_args.file_path(
+            &format!("optimal_isochrones_{}_{}", time_limit, isochrone_interval),
+            format.extension(),
         ),
+        format,
         1.0 / 100.0,
         Some(coord),
     )?;
@@ -232,30 +301,25 @@ pub fn run_optimal(
 
 pub fn run_worst(
     hrdf: Hrdf,
-    excluded_polygons: MultiPolygon,
     isochrone_args: IsochroneArgs,
-    delta_time: Duration,
-    display_mode: IsochroneDisplayMode,
-    num_threads: usize,
+    config: &IsochroneConfig,
+    format: IsochroneOutputFormat,
+    output_args: &OutputArgs,
 ) -> RResult<()> {
     let time_limit = isochrone_args.time_limit.num_minutes();
-    let isochrone_interval = isochrone_args.interval.num_minutes();
+    let isochrone_interval = isochrone_args.effective_interval().num_minutes();
 
     let (x, y) = wgs84_to_lv95(isochrone_args.latitude, isochrone_args.longitude);
     let coord = Coordinates::new(hrdf_parser::CoordinateSystem::LV95, x, y);
 
-    let opt_iso = compute_worst_isochrones(
-        &hrdf,
-        &excluded_polygons,
-        isochrone_args,
-        delta_time,
-        display_mode,
-        num_threads,
-    );
+    let opt_iso = compute_worst_isochrones(&hrdf, isochrone_args, config)?;
 
-    #[cfg(feature = "svg")]
-    opt_iso.write_svg(
-        &format!("worst_isochrones_{}_{}.svg", time_limit, isochrone_interval),
+    opt_iso.write(
+        &output_args.file_path(
+            &format!("worst_isochrones_{}_{}", time_limit, isochrone_interval),
+            format.extension(),
+        ),
+        format,
         1.0 / 100.0,
         Some(coord),
     )?;
@@ -263,34 +327,29 @@ pub fn run_worst(
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
 pub fn run_comparison(
     hrdf_2025: Hrdf,
     hrdf_2026: Hrdf,
-    excluded_polygons: MultiPolygon,
     isochrone_args_2025: IsochroneArgs,
     isochrone_args_2026: IsochroneArgs,
-    delta_time: Duration,
-    display_mode: IsochroneDisplayMode,
-    num_threads: usize,
+    config: &IsochroneConfig,
+    format: IsochroneOutputFormat,
+    output_args: &OutputArgs,
 ) -> RResult<()> {
     let time_limit = isochrone_args_2025.time_limit.num_minutes();
-    let isochrone_interval = isochrone_args_2025.interval.num_minutes();
+    let isochrone_interval = isochrone_args_2025.effective_interval().num_minutes();
 
     let (x, y) = wgs84_to_lv95(isochrone_args_2025.latitude, isochrone_args_2025.longitude);
     let coord = Coordinates::new(hrdf_parser::CoordinateSystem::LV95, x, y);
 
-    let isochrones_2025 = compute_optimal_isochrones(
-        &hrdf_2025,
-        &excluded_polygons,
-        isochrone_args_2025,
-        delta_time,
-        display_mode,
-        num_threads,
-    );
-    #[cfg(feature = "svg")]
-    isochrones_2025.write_svg(
-        &format!("isochrones_2025_{}_{}.svg", time_limit, isochrone_interval),
+    let isochrones_2025 = compute_optimal_isochrones(&hrdf_2025, isochrone_args_2025, config)?;
+    isochrones_2025.write(
+        &output_args.file_path_with_suffix(
+            &format!("isochrones_2025_{}_{}", time_limit, isochrone_interval),
+            "old",
+            format.extension(),
+        ),
+        format,
         1.0 / 100.0,
         Some(coord),
     )?;
@@ -301,17 +360,14 @@ pub fn run_comparison(
         isochrones_2025.compute_max_distance(coord).1
     );
 
-    let isochrones_2026 = compute_optimal_isochrones(
-        &hrdf_2026,
-        &excluded_polygons,
-        isochrone_args_2026,
-        delta_time,
-        display_mode,
-        num_threads,
-    );
-    #[cfg(feature = "svg")]
-    isochrones_2026.write_svg(
-        &format!("isochrones_2026_{}_{}.svg", time_limit, isochrone_interval),
+    let isochrones_2026 = compute_optimal_isochrones(&hrdf_2026, isochrone_args_2026, config)?;
+    isochrones_2026.write(
+        &output_args.file_path_with_suffix(
+            &format!("isochrones_2026_{}_{}", time_limit, isochrone_interval),
+            "new",
+            format.extension(),
+        ),
+        format,
         1.0 / 100.0,
         Some(coord),
     )?;
@@ -324,3 +380,35 @@ pub fn run_comparison(
 
     Ok(())
 }
+
+#[cfg(all(test, feature = "hectare"))]
+mod tests {
+    use test_log::test;
+
+    use super::*;
+
+    #[test]
+    fn test_write_geojsonl_record() {
+        let mut buffer = Vec::new();
+        for reli in [1, 2] {
+            let record = HectareRecord {
+                reli,
+                longitude: 6.1,
+                latitude: 46.2,
+                population: 10,
+                area: Some(1e6),
+            };
+            write_geojsonl_record(&mut buffer, &record).unwrap();
+        }
+
+        let text = String::from_utf8(buffer).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let feature: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(feature["properties"]["reli"], 2);
+        assert_eq!(
+            feature["geometry"]["coordinates"],
+            serde_json::json!([6.1, 46.2])
+        );
+    }
+}
@@ -1,11 +1,92 @@
-use std::net::Ipv4Addr;
+use std::{net::Ipv4Addr, path::PathBuf};
 
-use chrono::{Duration, NaiveDateTime};
+use chrono::{DateTime, Duration, Local, NaiveDateTime, NaiveTime};
 use clap::{Parser, Subcommand};
 
 #[cfg(feature = "hectare")]
 use crate::IsochroneHectareArgs;
-use crate::{IsochroneArgs, IsochroneDisplayMode, JourneyArgs, RResult};
+use crate::{
+    DEFAULT_MAX_ACCESS_WALK_IN_METERS, GraphFormat, IsochroneArgs, IsochroneDisplayMode,
+    IsochroneOutputFormat, JourneyArgs, OutputArgs, RError, RResult, isochrone::check_num_bands,
+};
+
+/// Formats accepted for an absolute departure time, ISO 8601 with a T or a space.
+const DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+];
+
+/// Parses a departure time: `now`, `now+30m` or `now-2h` (in m, h or d), `today 08:00` or a date
+/// and time such as `2025-04-10 15:36:00` or `2025-04-10T15:36:00+02:00` (the offset is ignored).
+fn parse_departure_at(text: &str, now: NaiveDateTime) -> RResult<NaiveDateTime> {
+    let text = text.trim();
+    let invalid = || RError::InvalidDepartureAt(String::from(text));
+
+    if let Some(offset) = text.strip_prefix("now") {
+        if offset.is_empty() {
+            return Ok(now);
+        }
+        return parse_offset(offset)
+            .map(|offset| now + offset)
+            .ok_or_else(invalid);
+    }
+    if let Some(time) = text.strip_prefix("today ") {
+        return ["%H:%M:%S", "%H:%M"]
+            .iter()
+            .find_map(|format| NaiveTime::parse_from_str(time.trim(), format).ok())
+            .map(|time| now.date().and_time(time))
+            .ok_or_else(invalid);
+    }
+    DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(text)
+                .ok()
+                .map(|date_time| date_time.naive_local())
+        })
+        .ok_or_else(invalid)
+}
+
+/// Parses a signed offset such as `+30m`, `-2h` or `+1d`.
+fn parse_offset(offset: &str) -> Option<Duration> {
+    let (sign, offset) = if let Some(offset) = offset.strip_prefix('+') {
+        (1, offset)
+    } else {
+        (-1, offset.strip_prefix('-')?)
+    };
+    let unit = offset.chars().last()?;
+    let value = i64::from(
+        offset[..offset.len() - unit.len_utf8()]
+            .parse::<u32>()
+            .ok()?,
+    );
+    let duration = match unit {
+        'm' => Duration::minutes(value),
+        'h' => Duration::hours(value),
+        'd' => Duration::days(value),
+        _ => return None,
+    };
+    Some(duration * sign)
+}
+
+/// Parses the departure time relative to the current local time.
+fn parse_departure_at_from_now(text: &str) -> RResult<NaiveDateTime> {
+    parse_departure_at(text, Local::now().naive_local())
+}
+
+/// Parses a distance in meters, which can't be negative.
+fn parse_distance(text: &str) -> Result<f64, String> {
+    let distance: f64 = text.parse().map_err(|e| format!("{e}"))?;
+    if distance.is_nan() || distance < 0.0 {
+        return Err(String::from(
+            "the distance must be a positive number of meters",
+        ));
+    }
+    Ok(distance)
+}
 
 #[derive(Parser, Debug, Clone)]
 pub struct IsochroneArgsBuilder {
@@ -15,8 +96,9 @@ pub struct IsochroneArgsBuilder {
     /// Departure longitude
     #[arg(long, default_value_t = 6.13536000)]
     longitude: f64,
-    /// Departure date and time
-    #[arg(short, long, default_value_t = String::from("2025-04-10 15:36:00"))]
+    /// Departure date and time: now, now+30m, today 08:00 or 2025-04-10 15:36:00. Moved into the
+    /// timetable period if outside of it
+    #[arg(short, long, default_value_t = String::from("now"))]
     departure_at: String,
     /// Maximum time of the isochrone in minutes
     #[arg(short, long, default_value_t = 60)]
@@ -24,12 +106,29 @@ pub struct IsochroneArgsBuilder {
     /// Time interval between two isochrone in minutes
     #[arg(short, long, default_value_t = 10)]
     interval: i64,
-    /// Maximum number of connections
+    /// Time interval between two isochrone in seconds, replaces --interval when given
+    #[arg(long)]
+    interval_seconds: Option<i64>,
+    /// Number of isochrones, replaces --interval: the interval is then the time limit divided by
+    /// the number of isochrones
+    #[arg(long, conflicts_with_all = ["interval", "interval_seconds"])]
+    num_bands: Option<usize>,
+    /// Maximum number of connections (low values are faster but give smaller isochrones)
     #[arg(short, long, default_value_t = 10)]
     max_num_explorable_connections: i32,
     /// Number of starting points
     #[arg(short, long, default_value_t = 5)]
     num_starting_points: usize,
+    /// Comma separated stop id prefixes of the countries to include, e.g. 85 (Switzerland) or
+    /// 80 (Germany). All the countries by default
+    #[arg(long, value_delimiter = ',')]
+    countries: Vec<String>,
+    /// Rounds the time to reach each stop up to the next isochrone, for crisp bands
+    #[arg(long, default_value_t = false)]
+    snap_to_interval: bool,
+    /// Keeps the lakes in the isochrones, e.g. the areas across a lake reachable by boat
+    #[arg(long, default_value_t = false)]
+    keep_excluded_polygons: bool,
     /// Verbose on or off
     #[arg(short, long, default_value_t = false)]
     verbose: bool,
@@ -48,19 +147,30 @@ impl IsochroneArgsBuilder {
             departure_at,
             time_limit,
             interval,
+            interval_seconds,
+            num_bands,
             max_num_explorable_connections,
             num_starting_points,
+            countries,
+            snap_to_interval,
+            keep_excluded_polygons,
             verbose,
         } = self;
 
+        check_num_bands(Duration::minutes(time_limit), num_bands)?;
+
         Ok(IsochroneArgs {
             latitude,
             longitude,
-            departure_at: NaiveDateTime::parse_from_str(&departure_at, "%Y-%m-%d %H:%M:%S")?,
+            departure_at: parse_departure_at_from_now(&departure_at)?,
             time_limit: Duration::minutes(time_limit),
-            interval: Duration::minutes(interval),
+            interval: interval_seconds.map_or(Duration::minutes(interval), Duration::seconds),
+            num_bands,
             max_num_explorable_connections,
             num_starting_points,
+            countries,
+            snap_to_interval,
+            apply_excluded_polygons: !keep_excluded_polygons,
             verbose,
         })
     }
@@ -68,18 +178,31 @@ impl IsochroneArgsBuilder {
 
 #[derive(Parser, Debug, Clone)]
 pub struct JourneyArgsBuilder {
-    /// Departure stop id
-    #[arg(long, default_value_t = 8587418)]
-    departure_stop_id: i32,
-    /// Departure longitude
-    #[arg(long, default_value_t = 8595120)]
-    arrival_stop_id: i32,
-    /// Departure date and time
-    #[arg(short, long, default_value_t = String::from("2025-09-17 17:05:59"))]
+    /// Departure stop id, 8587418 if neither the id nor the name is given
+    #[arg(long)]
+    departure_stop_id: Option<i32>,
+    /// Arrival stop id, 8595120 if neither the id nor the name is given
+    #[arg(long)]
+    arrival_stop_id: Option<i32>,
+    /// Departure stop name, resolved to the best matching stop. Ignored if the id is given
+    #[arg(long, visible_alias = "from-name")]
+    departure_stop_name: Option<String>,
+    /// Arrival stop name, resolved to the best matching stop. Ignored if the id is given
+    #[arg(long, visible_alias = "to-name")]
+    arrival_stop_name: Option<String>,
+    /// Stop id the journey must go through
+    #[arg(long)]
+    via_stop_id: Option<i32>,
+    /// Departure date and time: now, now+30m, today 08:00 or 2025-04-10 15:36:00. Moved into the
+    /// timetable period if outside of it
+    #[arg(short, long, default_value_t = String::from("now"))]
     departure_at: String,
     /// Maximum number of connections
     #[arg(short, long, default_value_t = 10)]
     max_num_explorable_connections: i32,
+    /// Prints the routes which lost against the journey and why
+    #[arg(long, default_value_t = false)]
+    explain: bool,
     /// Verbose on or off
     #[arg(short, long, default_value_t = false)]
     verbose: bool,
@@ -90,26 +213,52 @@ impl JourneyArgsBuilder {
         let Self {
             departure_stop_id,
             arrival_stop_id,
+            departure_stop_name,
+            arrival_stop_name,
+            via_stop_id,
             departure_at,
             max_num_explorable_connections,
+            explain,
             verbose,
         } = self;
 
+        // The ids win over the names.
         Ok(JourneyArgs {
-            departure_stop_id,
-            arrival_stop_id,
-            departure_at: NaiveDateTime::parse_from_str(&departure_at, "%Y-%m-%d %H:%M:%S")?,
+            departure_stop_name: departure_stop_name.filter(|_| departure_stop_id.is_none()),
+            arrival_stop_name: arrival_stop_name.filter(|_| arrival_stop_id.is_none()),
+            departure_stop_id: departure_stop_id.unwrap_or(8587418),
+            arrival_stop_id: arrival_stop_id.unwrap_or(8595120),
+            via_stop_id,
+            departure_at: parse_departure_at_from_now(&departure_at)?,
             max_num_explorable_connections,
+            explain,
             verbose,
         })
     }
 }
 
+#[derive(Parser, Debug, Clone)]
+pub struct OutputArgsBuilder {
+    /// Directory where the output files are written, created if it doesn't exist
+    #[arg(long, default_value = ".")]
+    out_dir: PathBuf,
+    /// Name of the output files (without extension), defaults to a name derived from the mode
+    #[arg(long)]
+    out_name: Option<String>,
+}
+
+impl OutputArgsBuilder {
+    pub fn finalize(self) -> RResult<OutputArgs> {
+        OutputArgs::try_new(self.out_dir, self.out_name)
+    }
+}
+
 #[cfg(feature = "hectare")]
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 pub struct IsochroneHectareArgsBuilder {
-    /// Departure date and time
-    #[arg(short, long, default_value_t = String::from("2025-04-10 07:30:00"))]
+    /// Departure date and time: now, now+30m, today 08:00 or 2025-04-10 15:36:00. Moved into the
+    /// timetable period if outside of it
+    #[arg(short, long, default_value_t = String::from("now"))]
     departure_at: String,
     /// Maximum time of the isochrone in minutes
     #[arg(short, long, default_value_t = 60)]
@@ -137,7 +286,7 @@ impl IsochroneHectareArgsBuilder {
         } = self;
 
         Ok(IsochroneHectareArgs {
-            departure_at: NaiveDateTime::parse_from_str(&departure_at, "%Y-%m-%d %H:%M:%S")?,
+            departure_at: parse_departure_at_from_now(&departure_at)?,
             time_limit: Duration::minutes(time_limit),
             max_num_explorable_connections,
             num_starting_points,
@@ -146,7 +295,7 @@ impl IsochroneHectareArgsBuilder {
     }
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Clone)]
 pub enum Mode {
     /// Serve mode to a given port
     Serve {
@@ -157,9 +306,26 @@ pub enum Mode {
         /// Port exposed on the server
         #[arg(short, long, value_parser = clap::value_parser!(u16), num_args = 1.., default_values_t = [8100u16])]
         ports: Vec<u16>,
+
+        /// Computes a throwaway isochrone before serving, so that the first request is fast
+        #[arg(long, default_value_t = false)]
+        warmup: bool,
     },
     /// Debug mode used to check if the examples still run
     Debug,
+    /// Validate mode running one journey and one small isochrone, for smoke tests
+    Validate,
+    /// Exports the stops, the transfers and the transit edges explored by the routing
+    ExportGraph {
+        #[command(flatten)]
+        output_args: OutputArgsBuilder,
+        /// Output format of the graph: geojson or csv
+        #[arg(long, default_value_t = GraphFormat::GeoJson)]
+        format: GraphFormat,
+        /// Year of the timetable
+        #[arg(long, default_value_t = 2025)]
+        year: i32,
+    },
     /// Journey mode to find a journey between two stop ids departing at a given time
     Journey {
         #[command(flatten)]
@@ -169,6 +335,11 @@ pub enum Mode {
     Compare {
         #[command(flatten)]
         isochrone_args: IsochroneArgsBuilder,
+        #[command(flatten)]
+        output_args: OutputArgsBuilder,
+        /// Output format of the isochrones: svg, geojson, png or json
+        #[arg(long, default_value_t = IsochroneOutputFormat::Svg)]
+        format: IsochroneOutputFormat,
         /// Second departure date and time
         #[arg(short, long, default_value_t = String::from("2025-04-11 15:36:00"))]
         old_departure_at: String,
@@ -183,6 +354,11 @@ pub enum Mode {
     Optimal {
         #[command(flatten)]
         isochrone_args: IsochroneArgsBuilder,
+        #[command(flatten)]
+        output_args: OutputArgsBuilder,
+        /// Output format of the isochrones: svg, geojson, png or json
+        #[arg(long, default_value_t = IsochroneOutputFormat::Svg)]
+        format: IsochroneOutputFormat,
         /// The +/- duration on which to compute the average (in minutes)
         #[arg(long, default_value_t = 30)]
         delta_time: i64,
@@ -194,6 +370,11 @@ pub enum Mode {
     Worst {
         #[command(flatten)]
         isochrone_args: IsochroneArgsBuilder,
+        #[command(flatten)]
+        output_args: OutputArgsBuilder,
+        /// Output format of the isochrones: svg, geojson, png or json
+        #[arg(long, default_value_t = IsochroneOutputFormat::Svg)]
+        format: IsochroneOutputFormat,
         /// The +/- duration on which to compute the average (in minutes)
         #[arg(long, default_value_t = 30)]
         delta_time: i64,
@@ -205,6 +386,11 @@ pub enum Mode {
     Simple {
         #[command(flatten)]
         isochrone_args: IsochroneArgsBuilder,
+        #[command(flatten)]
+        output_args: OutputArgsBuilder,
+        /// Output format of the isochrones: svg, geojson, png or json
+        #[arg(long, default_value_t = IsochroneOutputFormat::Svg)]
+        format: IsochroneOutputFormat,
         /// Display mode of the isochrones: circles or contour_line
         #[arg(long, default_value_t = IsochroneDisplayMode::Circles)]
         mode: IsochroneDisplayMode,
@@ -213,6 +399,11 @@ pub enum Mode {
     Average {
         #[command(flatten)]
         isochrone_args: IsochroneArgsBuilder,
+        #[command(flatten)]
+        output_args: OutputArgsBuilder,
+        /// Output format of the isochrones: svg, geojson, png or json
+        #[arg(long, default_value_t = IsochroneOutputFormat::Svg)]
+        format: IsochroneOutputFormat,
         /// The +/- duration on which to compute the average (in minutes)
         #[arg(long, default_value_t = 30)]
         delta_time: i64,
@@ -222,12 +413,18 @@ pub enum Mode {
     Hectare {
         #[command(flatten)]
         isochrone_args: IsochroneHectareArgsBuilder,
+        #[command(flatten)]
+        output_args: OutputArgsBuilder,
         /// The +/- duration on which to compute the average (in minutes)
         #[arg(long, default_value_t = 30)]
         delta_time: i64,
         /// The URL from where to download the necessary data for the Hectare computations
         #[arg(short, long, default_value_t = String::from("https://dam-api.bfs.admin.ch/hub/api/dam/assets/32686751/master"))]
         url: String,
+        /// Write each record as soon as it is computed to a newline-delimited GeoJSON file
+        /// instead of a JSON file at the end
+        #[arg(long, default_value_t = false)]
+        geojsonl: bool,
     },
 }
 
@@ -243,7 +440,176 @@ pub struct Cli {
     // Maximum number of cores used. If 0 is given all cores are automatically assigned
     #[arg(long, default_value_t = 4)]
     pub num_threads: usize,
+    /// Maximum walking time of a footpath between two stops in minutes, no limit if absent
+    /// (15 is a sensible value). Also used by journey
+    #[arg(long, value_parser = clap::value_parser!(i64).range(0..))]
+    pub max_transfer_walk: Option<i64>,
+    /// Maximum walking distance in meters between the departure point and its departure stops
+    #[arg(long, default_value_t = DEFAULT_MAX_ACCESS_WALK_IN_METERS, value_parser = parse_distance)]
+    pub max_access_walk: f64,
+    /// Step in seconds between two departure times tried by the optimal, worst and average
+    /// isochrones, at least 1
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(i64).range(1..))]
+    pub search_step: i64,
+    /// Simplifies the polygons of the isochrones, no vertex moving by more than the tolerance in
+    /// meters. All the vertices are kept if absent
+    #[arg(long, value_parser = parse_distance)]
+    pub simplify_tolerance: Option<f64>,
     /// What mode is used
     #[command(subcommand)]
     pub mode: Mode,
 }
+
+#[cfg(test)]
+mod tests {
+    use test_log::test;
+
+    use crate::utils::create_date_time;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_departure_at() {
+        let now = create_date_time(2025, 4, 10, 15, 36);
+
+        assert_eq!(parse_departure_at("now", now).unwrap(), now);
+        assert_eq!(
+            parse_departure_at("now+30m", now).unwrap(),
+            create_date_time(2025, 4, 10, 16, 6)
+        );
+        assert_eq!(
+            parse_departure_at("now-2h", now).unwrap(),
+            create_date_time(2025, 4, 10, 13, 36)
+        );
+        assert_eq!(
+            parse_departure_at("now+1d", now).unwrap(),
+            create_date_time(2025, 4, 11, 15, 36)
+        );
+        assert_eq!(
+            parse_departure_at("today 08:00", now).unwrap(),
+            create_date_time(2025, 4, 10, 8, 0)
+        );
+        assert_eq!(
+            parse_departure_at("2025-06-02 07:30:00", now).unwrap(),
+            create_date_time(2025, 6, 2, 7, 30)
+        );
+        assert_eq!(
+            parse_departure_at("2025-06-02T07:30", now).unwrap(),
+            create_date_time(2025, 6, 2, 7, 30)
+        );
+        assert_eq!(
+            parse_departure_at("2025-06-02T07:30:00+02:00", now).unwrap(),
+            create_date_time(2025, 6, 2, 7, 30)
+        );
+
+        for text in [
+            "now+30",
+            "now*2h",
+            "now+m",
+            "today",
+            "yesterday 08:00",
+            "2025-06-02",
+        ] {
+            assert!(
+                matches!(
+                    parse_departure_at(text, now),
+                    Err(RError::InvalidDepartureAt(_))
+                ),
+                "{text}"
+            );
+        }
+    }
+    #[test]
+    fn test_isochrone_num_bands() {
+        let isochrone_args =
+            IsochroneArgsBuilder::try_parse_from(["isochrone", "-t", "60", "--num-bands", "4"])
+                .unwrap()
+                .finalize()
+                .unwrap();
+        assert_eq!(isochrone_args.effective_interval(), Duration::minutes(15));
+
+        assert!(
+            IsochroneArgsBuilder::try_parse_from(["isochrone", "-i", "10", "--num-bands", "4"])
+                .is_err()
+        );
+        assert!(matches!(
+            IsochroneArgsBuilder::try_parse_from(["isochrone", "--num-bands", "0"])
+                .unwrap()
+                .finalize(),
+            Err(RError::InvalidNumBands)
+        ));
+        assert!(matches!(
+            IsochroneArgsBuilder::try_parse_from(["isochrone", "-t", "60", "--num-bands", "7"])
+                .unwrap()
+                .finalize(),
+            Err(RError::InvalidNumBands)
+        ));
+    }
+
+    #[test]
+    fn test_search_step_must_be_positive() {
+        let cli =
+            Cli::try_parse_from(["hrdf-routing-engine", "--search-step", "30", "serve"]).unwrap();
+        assert_eq!(cli.search_step, 30);
+        assert!(
+            Cli::try_parse_from(["hrdf-routing-engine", "--search-step", "0", "serve"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_max_access_walk_must_be_positive() {
+        let cli = Cli::try_parse_from(["hrdf-routing-engine", "--max-access-walk", "800", "serve"])
+            .unwrap();
+        assert_eq!(cli.max_access_walk, 800.0);
+        for max_access_walk in ["-1", "NaN", "far"] {
+            assert!(
+                Cli::try_parse_from([
+                    "hrdf-routing-engine",
+                    "--max-access-walk",
+                    max_access_walk,
+                    "serve"
+                ])
+                .is_err(),
+                "{max_access_walk}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_simplify_tolerance_must_be_positive() {
+        let cli =
+            Cli::try_parse_from(["hrdf-routing-engine", "--simplify-tolerance", "5", "serve"])
+                .unwrap();
+        assert_eq!(cli.simplify_tolerance, Some(5.0));
+        for simplify_tolerance in ["-1", "NaN"] {
+            assert!(
+                Cli::try_parse_from([
+                    "hrdf-routing-engine",
+                    "--simplify-tolerance",
+                    simplify_tolerance,
+                    "serve"
+                ])
+                .is_err(),
+                "{simplify_tolerance}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_export_graph_mode() {
+        let cli = Cli::try_parse_from(["hrdf-routing-engine", "export-graph", "--format", "csv"])
+            .unwrap();
+        assert!(matches!(
+            cli.mode,
+            Mode::ExportGraph {
+                format: GraphFormat::Csv,
+                year: 2025,
+                ..
+            }
+        ));
+        assert!(
+            Cli::try_parse_from(["hrdf-routing-engine", "export-graph", "--format", "svg"])
+                .is_err()
+        );
+    }
+}
@@ -1,8 +1,122 @@
 use std::time::Instant;
 
-use hrdf_parser::Hrdf;
+use chrono::{Duration, NaiveDateTime};
+use geo::MultiPolygon;
+use hrdf_parser::{Hrdf, timetable_start_date};
 
-use crate::{routing::plan_journey, utils::create_date_time};
+use crate::{
+    IsochroneArgs, IsochroneConfig, RError, RResult,
+    isochrone::compute_isochrones,
+    routing::plan_journey,
+    timetable,
+    utils::{create_date_time, create_time},
+};
+
+/// Computes a throwaway isochrone, so that the first request to the service is as fast as the
+/// next ones.
+pub fn warmup(hrdf: &Hrdf, excluded_polygons: &MultiPolygon) {
+    let start_time = Instant::now();
+
+    if let Ok(start_date) = timetable_start_date(hrdf.data_storage().timetable_metadata()) {
+        // Bern
+        let isochrone_args = IsochroneArgs {
+            latitude: 46.94899,
+            longitude: 7.43913,
+            departure_at: NaiveDateTime::new(start_date, create_time(8, 0)),
+            time_limit: Duration::minutes(10),
+            interval: Duration::minutes(10),
+            num_bands: None,
+            max_num_explorable_connections: 2,
+            num_starting_points: 1,
+            countries: Vec::new(),
+            snap_to_interval: false,
+            apply_excluded_polygons: true,
+            verbose: false,
+        };
+        if let Err(e) = compute_isochrones(
+            hrdf,
+            isochrone_args,
+            &IsochroneConfig::new(excluded_polygons),
+        ) {
+            log::warn!("Warmup failed: {e}");
+        }
+    }
+    log::info!("Warmup done in {:.2?}", start_time.elapsed());
+}
+
+/// Checks that the pipeline runs: loads the HRDF, plans a journey and computes a small isochrone.
+/// The duration of each stage is printed, and the stage which failed if any.
+pub async fn run_validate(
+    excluded_polygons: &MultiPolygon,
+    force_rebuild: bool,
+    cache_prefix: Option<String>,
+) -> RResult<()> {
+    println!("excluded polygons: {} polygons", excluded_polygons.0.len());
+
+    let start_time = Instant::now();
+    let hrdf = Hrdf::try_from_year(2025, force_rebuild, cache_prefix)
+        .await
+        .map_err(RError::from);
+    let hrdf = report_stage("load hrdf", start_time, hrdf)?;
+    println!(
+        "dangling stop references: {}",
+        timetable::dangling_stop_ids(&hrdf).len()
+    );
+    timetable::warn_dangling_stop_ids(&hrdf);
+
+    let start_time = Instant::now();
+    // Zürich HB => Bern
+    let route = plan_journey(
+        &hrdf,
+        8503000,
+        8507000,
+        create_date_time(2025, 6, 2, 8, 0),
+        3,
+        false,
+    )
+    .ok_or_else(|| RError::NoRouteFound(String::from("8503000 => 8507000")));
+    report_stage("plan_journey", start_time, route)?;
+
+    let start_time = Instant::now();
+    // Genève, gare Cornavin
+    let isochrone_args = IsochroneArgs {
+        latitude: 46.20956654,
+        longitude: 6.13536000,
+        departure_at: create_date_time(2025, 6, 2, 8, 0),
+        time_limit: Duration::minutes(10),
+        interval: Duration::minutes(10),
+        num_bands: None,
+        max_num_explorable_connections: 2,
+        num_starting_points: 1,
+        countries: Vec::new(),
+        snap_to_interval: false,
+        apply_excluded_polygons: true,
+        verbose: false,
+    };
+    let isochrone_map = compute_isochrones(
+        &hrdf,
+        isochrone_args,
+        &IsochroneConfig::new(excluded_polygons),
+    )
+    .and_then(|isochrone_map| {
+        if isochrone_map.areas().iter().any(|area| *area > 0.0) {
+            Ok(isochrone_map)
+        } else {
+            Err(RError::EmptyMultiPolygon)
+        }
+    });
+    report_stage("compute_isochrones", start_time, isochrone_map)?;
+
+    Ok(())
+}
+
+fn report_stage<T>(stage: &str, start_time: Instant, result: RResult<T>) -> RResult<T> {
+    match &result {
+        Ok(_) => println!("{stage}: ok in {:.2?}", start_time.elapsed()),
+        Err(e) => println!("{stage}: failed after {:.2?}: {e}", start_time.elapsed()),
+    }
+    result
+}
 
 pub fn run_debug(hrdf: Hrdf) {
     println!();
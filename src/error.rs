@@ -1,4 +1,7 @@
-use std::num::{ParseFloatError, ParseIntError};
+use std::{
+    num::{ParseFloatError, ParseIntError},
+    path::PathBuf,
+};
 
 use thiserror::Error;
 #[cfg(feature = "hectare")]
@@ -10,10 +13,36 @@ pub enum RError {
     HrdfError(#[from] hrdf_parser::Error),
     #[error("Failed to parse date {0}")]
     ParseDate(#[from] chrono::ParseError),
+    #[error(
+        "Invalid departure time {0}, expected now, now+30m, today 08:00 or 2025-04-10 15:36:00"
+    )]
+    InvalidDepartureAt(String),
+    #[error("The number of isochrones must be at least 1 and divide the time limit")]
+    InvalidNumBands,
+    #[error("The search step must be positive")]
+    InvalidSearchStep,
     #[error("Empty MultiPolygon")]
     EmptyMultiPolygon,
     #[error("No bounding rectangle exists")]
     NoBoundingRect,
+    #[error("The GeoJSON is not a Point")]
+    NotAPoint,
+    #[error("No stop matches {0}")]
+    StopNotFound(String),
+    #[error("No route found for {0}")]
+    NoRouteFound(String),
+    #[error("No stop within access range ({2} m) of {0}, {1}")]
+    NoStopWithinAccessRange(f64, f64, f64),
+    #[error("The {0} output format requires the {0} feature")]
+    FormatNotEnabled(String),
+    #[cfg(feature = "png")]
+    #[error("Failed to render png: {0}")]
+    PngError(String),
+    #[cfg(feature = "png")]
+    #[error("Invalid zoom level {0}, the tiles go up to zoom 22")]
+    InvalidZoom(u8),
+    #[error("Output directory {0} is not writable: {1}")]
+    OutputDirNotWritable(PathBuf, std::io::Error),
     #[error("Io Error: {0}")]
     IoError(#[from] std::io::Error),
     #[error("SerdeJsonError: {0}")]
@@ -0,0 +1,250 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use hrdf_parser::{DataStorage, Model, Stop};
+use rustc_hash::FxHashSet;
+use serde_json::json;
+use strum_macros::EnumString;
+
+use crate::error::RResult;
+
+#[derive(Debug, EnumString, PartialEq, Clone, Copy)]
+pub enum GraphFormat {
+    /// The stops as points and the edges as lines.
+    #[strum(serialize = "geojson")]
+    GeoJson,
+    /// One edge per line: from_stop_id,to_stop_id,kind,duration.
+    #[strum(serialize = "csv")]
+    Csv,
+}
+
+impl GraphFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::GeoJson => "geojson",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+impl Display for GraphFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum EdgeKind {
+    /// A footpath between two stops.
+    Transfer,
+    /// Two consecutive stops of at least one journey.
+    Transit,
+}
+
+impl EdgeKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Transfer => "transfer",
+            Self::Transit => "transit",
+        }
+    }
+}
+
+/// An edge of the graph explored by the routing. The duration (in minutes) is only known for the
+/// transfers, it depends on the journey for the transit edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct GraphEdge {
+    kind: EdgeKind,
+    from_stop_id: i32,
+    to_stop_id: i32,
+    duration: Option<i32>,
+}
+
+/// The transfers, then the transit edges, each sorted by stop ids.
+fn graph_edges(data_storage: &DataStorage) -> Vec<GraphEdge> {
+    let transfers = data_storage
+        .stop_connections()
+        .entries()
+        .into_iter()
+        .map(|stop_connection| GraphEdge {
+            kind: EdgeKind::Transfer,
+            from_stop_id: stop_connection.stop_id_1(),
+            to_stop_id: stop_connection.stop_id_2(),
+            duration: Some(i32::from(stop_connection.duration())),
+        });
+    let transits = data_storage
+        .journeys()
+        .entries()
+        .into_iter()
+        .flat_map(|journey| {
+            journey
+                .route()
+                .windows(2)
+                .map(|pair| GraphEdge {
+                    kind: EdgeKind::Transit,
+                    from_stop_id: pair[0].stop_id(),
+                    to_stop_id: pair[1].stop_id(),
+                    duration: None,
+                })
+                .collect::<Vec<_>>()
+        });
+
+    let mut edges: Vec<_> = transfers
+        .chain(transits)
+        .collect::<FxHashSet<_>>()
+        .into_iter()
+        .collect();
+    edges.sort_unstable();
+    edges
+}
+
+/// (longitude, latitude) as mandated by GeoJSON, None if the stop has no WGS84 coordinates.
+fn geojson_position(stop: &Stop) -> Option<[f64; 2]> {
+    let coordinates = stop.wgs84_coordinates()?;
+    Some([coordinates.longitude()?, coordinates.latitude()?])
+}
+
+/// Converts the routing graph to a GeoJSON FeatureCollection: one Point feature per stop, then
+/// one LineString feature per edge. The stops without coordinates and the edges touching them are
+/// left out.
+pub fn graph_to_geojson(data_storage: &DataStorage) -> serde_json::Value {
+    let mut stops = data_storage.stops().entries();
+    stops.sort_unstable_by_key(|stop| stop.id());
+    let stop_features = stops.into_iter().filter_map(|stop| {
+        Some(json!({
+            "type": "Feature",
+            "properties": {
+                "kind": "stop",
+                "stop_id": stop.id(),
+                "name": stop.name(),
+                "exchange_point": stop.can_be_used_as_exchange_point(),
+            },
+            "geometry": {
+                "type": "Point",
+                "coordinates": geojson_position(stop)?,
+            }
+        }))
+    });
+
+    let position = |stop_id| geojson_position(data_storage.stops().find(stop_id)?);
+    let edge_features = graph_edges(data_storage).into_iter().filter_map(|edge| {
+        Some(json!({
+            "type": "Feature",
+            "properties": {
+                "kind": edge.kind.as_str(),
+                "from_stop_id": edge.from_stop_id,
+                "to_stop_id": edge.to_stop_id,
+                "duration": edge.duration,
+            },
+            "geometry": {
+                "type": "LineString",
+                "coordinates": [position(edge.from_stop_id)?, position(edge.to_stop_id)?],
+            }
+        }))
+    });
+
+    json!({
+        "type": "FeatureCollection",
+        "features": stop_features.chain(edge_features).collect::<Vec<_>>(),
+    })
+}
+
+/// Writes the edges as CSV with a header line, the duration being empty when unknown.
+fn write_edges_csv(edges: &[GraphEdge], writer: &mut impl Write) -> RResult<()> {
+    writeln!(writer, "from_stop_id,to_stop_id,kind,duration")?;
+    for edge in edges {
+        writeln!(
+            writer,
+            "{},{},{},{}",
+            edge.from_stop_id,
+            edge.to_stop_id,
+            edge.kind.as_str(),
+            edge.duration.map(|d| d.to_string()).unwrap_or_default()
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the graph explored by the routing (the stops, the transfers between them and the
+/// consecutive stops of the journeys) to a file, e.g. to inspect the network in QGIS.
+pub fn write_graph(data_storage: &DataStorage, path: &Path, format: GraphFormat) -> RResult<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    match format {
+        GraphFormat::GeoJson => {
+            serde_json::to_writer(&mut writer, &graph_to_geojson(data_storage))?
+        }
+        GraphFormat::Csv => write_edges_csv(&graph_edges(data_storage), &mut writer)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use hrdf_parser::Hrdf;
+    use test_log::test;
+
+    use super::*;
+
+    #[test]
+    fn test_write_edges_csv() {
+        assert_eq!(GraphFormat::from_str("csv").unwrap(), GraphFormat::Csv);
+        assert!(GraphFormat::from_str("svg").is_err());
+
+        let edges = [
+            GraphEdge {
+                kind: EdgeKind::Transfer,
+                from_stop_id: 8503000,
+                to_stop_id: 8503088,
+                duration: Some(4),
+            },
+            GraphEdge {
+                kind: EdgeKind::Transit,
+                from_stop_id: 8503000,
+                to_stop_id: 8507000,
+                duration: None,
+            },
+        ];
+        let mut csv = Vec::new();
+        write_edges_csv(&edges, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "from_stop_id,to_stop_id,kind,duration\n\
+             8503000,8503088,transfer,4\n\
+             8503000,8507000,transit,\n"
+        );
+    }
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_graph_to_geojson() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let geojson = graph_to_geojson(hrdf.data_storage());
+        let features = geojson["features"].as_array().unwrap();
+
+        let kind_count = |kind: &str| {
+            features
+                .iter()
+                .filter(|feature| feature["properties"]["kind"] == kind)
+                .count()
+        };
+        assert!(kind_count("stop") > 0);
+        assert!(kind_count("transfer") > 0);
+        assert!(kind_count("transit") > 0);
+        // Zürich HB is directly linked to Bern.
+        assert!(features.iter().any(|feature| {
+            feature["properties"]["kind"] == "transit"
+                && feature["properties"]["from_stop_id"] == 8503000
+                && feature["properties"]["to_stop_id"] == 8507000
+        }));
+    }
+}
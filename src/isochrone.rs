@@ -1,32 +1,45 @@
 mod circles;
-mod constants;
+pub(crate) mod constants;
 mod contour_line;
 pub(crate) mod externals;
 mod models;
 pub(crate) mod utils;
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::time::Instant;
 
-use crate::isochrone::utils::haversine_distance;
+use crate::error::{RError, RResult};
 use crate::routing::Route;
-use crate::routing::compute_routes_from_origin;
-use crate::utils::inner_threads;
-use constants::WALKING_SPEED_IN_KILOMETERS_PER_HOUR;
+use crate::routing::{compute_routes_from_origin, get_operating_journeys};
+use crate::stop_index::StopIndex;
+use crate::timetable::{is_stop_served_on, parent_station};
+use crate::utils::{available_inner_threads, inner_threads, log_prefix};
+use constants::{DEFAULT_MAX_ACCESS_WALK_IN_METERS, WALKING_SPEED_IN_KILOMETERS_PER_HOUR};
 use geo::BooleanOps;
+use geo::CoordsIter;
 use geo::MultiPolygon;
-use hrdf_parser::{CoordinateSystem, Coordinates, DataStorage, Hrdf, Stop};
+use hrdf_parser::{CoordinateSystem, Coordinates, DataStorage, Hrdf, Model, Stop};
+pub use models::Diagnostics as IsochroneDiagnostics;
 pub use models::DisplayMode as IsochroneDisplayMode;
-pub use models::IsochroneMap;
+pub use models::OutputFormat as IsochroneOutputFormat;
+pub use models::Projection as IsochroneProjection;
+pub use models::ValidationReport as IsochroneValidationReport;
+pub use models::{ISOCHRONE_MAP_FORMAT_VERSION, IsochroneMap};
+pub use models::{StopDiagnostic, UnreachedReason};
+pub use utils::{origin_from_geojson, wgs84_to_web_mercator};
 
-use chrono::{Duration, NaiveDateTime};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
 
 use models::Isochrone;
 use orx_parallel::*;
 use utils::lv95_to_wgs84;
+use utils::simplify_multi_polygon;
 use utils::time_to_distance;
 
+#[cfg(feature = "hectare")]
+use self::externals::HectareData;
 use self::utils::NaiveDateTimeRange;
 use self::utils::wgs84_to_lv95;
 
@@ -47,22 +60,249 @@ pub struct IsochroneHectareArgs {
 
 #[derive(Debug, Clone)]
 pub struct IsochroneArgs {
+    /// Departure latitude (WGS84)
     pub latitude: f64,
+    /// Departure longitude (WGS84)
     pub longitude: f64,
+    /// Departure date and time
     pub departure_at: NaiveDateTime,
+    /// Maximum time of the isochrone
     pub time_limit: Duration,
+    /// Time interval between two isochrones. When it does not divide time_limit, the last
+    /// isochrone is still computed at time_limit
     pub interval: Duration,
+    /// Number of isochrones, replaces interval when given: the interval is then time_limit divided
+    /// by num_bands
+    pub num_bands: Option<usize>,
+    /// Maximum number of connections. Low values (2-3) are much faster to compute and give
+    /// slightly smaller isochrones
     pub max_num_explorable_connections: i32,
+    /// Number of starting points: the N stops closest (in walking time) to the departure point
+    /// from which the routing is started
     pub num_starting_points: usize,
+    /// Countries whose stops are part of the isochrones, all of them when empty. A country is
+    /// given by the prefix of the stop ids, its UIC code: 85 for Switzerland, 80 for Germany,
+    /// 81 for Austria, 83 for Italy and 87 for France. Only the reached stops are filtered: a
+    /// stop of the countries reached through a foreign stop stays part of the isochrones
+    pub countries: Vec<String>,
+    /// Rounds the duration to reach each stop up to the next isochrone, which gives crisp
+    /// concentric bands instead of smooth ones. Off by default
+    pub snap_to_interval: bool,
+    /// Subtracts the excluded polygons (the lakes) from the isochrones. On by default, turning it
+    /// off keeps e.g. the areas across a lake reachable by boat
+    pub apply_excluded_polygons: bool,
+    /// Verbose on or off
     pub verbose: bool,
 }
 
+/// Parameters of the isochrone computations that don't depend on the departure point.
+/// The excluded polygons are borrowed so that one config can be shared by many (parallel)
+/// computations without copying them.
+#[derive(Debug, Clone, Copy)]
+pub struct IsochroneConfig<'a> {
+    excluded_polygons: &'a MultiPolygon,
+    display_mode: IsochroneDisplayMode,
+    delta_time: Duration,
+    num_threads: usize,
+    max_transfer_walk: Option<Duration>,
+    max_access_walk: f64,
+    search_step: Duration,
+    group_stations: bool,
+    simplify_tolerance: Option<f64>,
+    label: Option<&'a str>,
+    stop_index: Option<&'a StopIndex>,
+}
+
+impl<'a> IsochroneConfig<'a> {
+    /// Circles display mode, +/- 30 minutes around the departure time searched every minute, a
+    /// single thread, no limit on the footpaths and DEFAULT_MAX_ACCESS_WALK_IN_METERS.
+    pub fn new(excluded_polygons: &'a MultiPolygon) -> Self {
+        Self {
+            excluded_polygons,
+            display_mode: IsochroneDisplayMode::Circles,
+            delta_time: Duration::minutes(30),
+            num_threads: 1,
+            max_transfer_walk: None,
+            max_access_walk: DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+            search_step: Duration::minutes(1),
+            group_stations: false,
+            simplify_tolerance: None,
+            label: None,
+            stop_index: None,
+        }
+    }
+
+    pub fn with_display_mode(mut self, display_mode: IsochroneDisplayMode) -> Self {
+        self.display_mode = display_mode;
+        self
+    }
+
+    /// The +/- duration around the departure time used by the optimal, worst and average
+    /// isochrones.
+    pub fn with_delta_time(mut self, delta_time: Duration) -> Self {
+        self.delta_time = delta_time;
+        self
+    }
+
+    /// Maximum number of threads, 0 uses all the cores.
+    pub fn with_num_threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = num_threads;
+        self
+    }
+
+    /// Footpaths between two stops longer than max_transfer_walk are not used, None (the
+    /// default) means no limit. 15 minutes is a sensible limit.
+    pub fn with_max_transfer_walk(mut self, max_transfer_walk: Option<Duration>) -> Self {
+        self.max_transfer_walk = max_transfer_walk;
+        self
+    }
+
+    /// The stops farther from the departure point than max_access_walk meters are not used as
+    /// departure stops.
+    pub fn with_max_access_walk(mut self, max_access_walk: f64) -> Self {
+        self.max_access_walk = max_access_walk;
+        self
+    }
+
+    /// The step between two departure times tried within delta_time, one minute by default.
+    /// It can be given in seconds, it must be positive.
+    pub fn with_search_step(mut self, search_step: Duration) -> RResult<Self> {
+        if search_step <= Duration::zero() {
+            return Err(RError::InvalidSearchStep);
+        }
+        self.search_step = search_step;
+        Ok(self)
+    }
+
+    /// Counts the stops of a station split into several stops (see parent_station) as the
+    /// station itself, at its position. Off by default.
+    pub fn with_group_stations(mut self, group_stations: bool) -> Self {
+        self.group_stations = group_stations;
+        self
+    }
+
+    /// Simplifies the polygons of the isochrones, no vertex moving by more than the tolerance in
+    /// meters. None (the default) keeps all the vertices.
+    pub fn with_simplify_tolerance(mut self, simplify_tolerance: Option<f64>) -> Self {
+        self.simplify_tolerance = simplify_tolerance;
+        self
+    }
+
+    /// Included in the log messages, e.g. to find the messages of one request of the service.
+    pub fn with_label(mut self, label: Option<&'a str>) -> Self {
+        self.label = label;
+        self
+    }
+
+    /// The index of the stops of the Hrdf given to the computations, e.g. the one kept by the
+    /// service. Without it, each computation builds its own.
+    pub fn with_stop_index(mut self, stop_index: &'a StopIndex) -> Self {
+        self.stop_index = Some(stop_index);
+        self
+    }
+
+    pub fn excluded_polygons(&self) -> &'a MultiPolygon {
+        self.excluded_polygons
+    }
+
+    pub fn display_mode(&self) -> IsochroneDisplayMode {
+        self.display_mode
+    }
+
+    pub fn delta_time(&self) -> Duration {
+        self.delta_time
+    }
+
+    pub fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    pub fn max_transfer_walk(&self) -> Option<Duration> {
+        self.max_transfer_walk
+    }
+
+    pub fn max_access_walk(&self) -> f64 {
+        self.max_access_walk
+    }
+
+    pub fn search_step(&self) -> Duration {
+        self.search_step
+    }
+
+    pub fn group_stations(&self) -> bool {
+        self.group_stations
+    }
+
+    pub fn simplify_tolerance(&self) -> Option<f64> {
+        self.simplify_tolerance
+    }
+
+    pub fn label(&self) -> Option<&'a str> {
+        self.label
+    }
+
+    /// The index given with with_stop_index, otherwise one built from the data storage.
+    pub(crate) fn stop_index(&self, data_storage: &DataStorage) -> Cow<'a, StopIndex> {
+        self.stop_index
+            .map_or_else(|| Cow::Owned(StopIndex::new(data_storage)), Cow::Borrowed)
+    }
+
+    /// The config to use in a computation nested in a parallel loop. The nested computation runs
+    /// on a single thread, also with the global pool, which the outer loop already fills.
+    pub(crate) fn nested(&self) -> Self {
+        self.with_num_threads(available_inner_threads(self.num_threads, true))
+    }
+
+    /// The config without excluded polygons unless the isochrone args apply them.
+    fn excluding_polygons(&self, apply_excluded_polygons: bool) -> Self {
+        if apply_excluded_polygons {
+            *self
+        } else {
+            Self {
+                excluded_polygons: &NO_EXCLUDED_POLYGONS,
+                ..*self
+            }
+        }
+    }
+}
+
+/// See IsochroneArgs::apply_excluded_polygons.
+static NO_EXCLUDED_POLYGONS: MultiPolygon = MultiPolygon(Vec::new());
+
+impl IsochroneArgs {
+    /// The interval between two isochrones, derived from num_bands when given.
+    pub fn effective_interval(&self) -> Duration {
+        self.interval_up_to(self.time_limit)
+    }
+
+    /// The interval between two isochrones up to time_limit instead of the one of the args.
+    fn interval_up_to(&self, time_limit: Duration) -> Duration {
+        self.num_bands.map_or(self.interval, |num_bands| {
+            time_limit / i32::try_from(num_bands.max(1)).unwrap_or(i32::MAX)
+        })
+    }
+}
+
+/// Checks that num_bands, when given, is at least 1 and divides time_limit to the second.
+pub(crate) fn check_num_bands(time_limit: Duration, num_bands: Option<usize>) -> RResult<()> {
+    match num_bands {
+        Some(num_bands) if num_bands == 0 || time_limit.num_seconds() % num_bands as i64 != 0 => {
+            Err(RError::InvalidNumBands)
+        }
+        _ => Ok(()),
+    }
+}
+
 impl Display for IsochroneArgs {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "longitude: {}, latitude: {}, departure_at: {}, time_limit: {}, interval: {}",
-            self.longitude, self.latitude, self.departure_at, self.time_limit, self.interval
+            self.longitude,
+            self.latitude,
+            self.departure_at,
+            self.time_limit,
+            self.effective_interval()
         )
     }
 }
@@ -71,20 +311,32 @@ impl Display for IsochroneArgs {
 /// Best is defined by the maximal surface covered by the largest isochrone
 pub fn compute_optimal_isochrones(
     hrdf: &Hrdf,
-    excluded_polygons: &MultiPolygon,
     isochrone_args: IsochroneArgs,
-    delta_time: Duration,
-    display_mode: models::DisplayMode,
-    num_threads: usize,
-) -> IsochroneMap {
+    config: &IsochroneConfig,
+) -> RResult<IsochroneMap> {
+    // Built once for all the departure times if not given.
+    let stop_index = config.stop_index(hrdf.data_storage());
+    let config = &config.with_stop_index(&stop_index);
+    let IsochroneConfig {
+        display_mode,
+        delta_time,
+        num_threads,
+        search_step,
+        ..
+    } = *config;
+    let isochrone_interval = isochrone_args.effective_interval();
     let IsochroneArgs {
         latitude,
         longitude,
         departure_at,
         time_limit,
-        interval: isochrone_interval,
+        interval: _,
+        num_bands: _,
         max_num_explorable_connections,
         num_starting_points,
+        countries,
+        snap_to_interval,
+        apply_excluded_polygons,
         verbose,
     } = isochrone_args;
 
@@ -100,7 +352,7 @@ pub fn compute_optimal_isochrones(
     let min_date_time = departure_at - delta_time;
     let max_date_time = departure_at + delta_time;
 
-    let isochrone_map = NaiveDateTimeRange::new(min_date_time, max_date_time, Duration::minutes(1))
+    let isochrone_map = NaiveDateTimeRange::new(min_date_time, max_date_time, search_step)
         .into_iter()
         .collect::<Vec<_>>();
 
@@ -110,32 +362,29 @@ pub fn compute_optimal_isochrones(
         .map(|dep| {
             compute_isochrones(
                 hrdf,
-                excluded_polygons,
                 IsochroneArgs {
                     latitude,
                     longitude,
                     departure_at: dep,
                     time_limit,
                     interval: isochrone_interval,
+                    num_bands: None,
                     max_num_explorable_connections,
                     num_starting_points,
+                    countries: countries.clone(),
+                    snap_to_interval,
+                    apply_excluded_polygons,
                     verbose,
                 },
-                display_mode,
-                inner_threads(num_threads, true),
+                &config.nested(),
             )
         })
-        .reduce(|lhs, rhs| {
-            if lhs.compute_max_area() > rhs.compute_max_area() {
-                lhs
-            } else {
-                rhs
-            }
-        });
+        .reduce(|lhs, rhs| Ok(larger_isochrone_map(lhs?, rhs?)));
 
     if verbose {
         log::info!(
-            "Time computing the optimal solution : {:.2?}",
+            "{}Time computing the optimal solution : {:.2?}",
+            log_prefix(config.label()),
             start_time.elapsed()
         );
     }
@@ -144,23 +393,34 @@ pub fn compute_optimal_isochrones(
 
 /// Computes the worst isochrone in [departure_at - delta_time; departure_at + delta_time)
 /// Best is defined by the maximal surface covered by the largest isochrone
-#[allow(clippy::too_many_arguments)]
 pub fn compute_worst_isochrones(
     hrdf: &Hrdf,
-    excluded_polygons: &MultiPolygon,
     isochrone_args: IsochroneArgs,
-    delta_time: Duration,
-    display_mode: models::DisplayMode,
-    num_threads: usize,
-) -> IsochroneMap {
+    config: &IsochroneConfig,
+) -> RResult<IsochroneMap> {
+    // Built once for all the departure times if not given.
+    let stop_index = config.stop_index(hrdf.data_storage());
+    let config = &config.with_stop_index(&stop_index);
+    let IsochroneConfig {
+        display_mode,
+        delta_time,
+        num_threads,
+        search_step,
+        ..
+    } = *config;
+    let isochrone_interval = isochrone_args.effective_interval();
     let IsochroneArgs {
         latitude,
         longitude,
         departure_at,
         time_limit,
-        interval: isochrone_interval,
+        interval: _,
+        num_bands: _,
         max_num_explorable_connections,
         num_starting_points,
+        countries,
+        snap_to_interval,
+        apply_excluded_polygons,
         verbose,
     } = isochrone_args;
 
@@ -176,7 +436,7 @@ pub fn compute_worst_isochrones(
     let min_date_time = departure_at - delta_time;
     let max_date_time = departure_at + delta_time;
 
-    let isochrone_map = NaiveDateTimeRange::new(min_date_time, max_date_time, Duration::minutes(1))
+    let isochrone_map = NaiveDateTimeRange::new(min_date_time, max_date_time, search_step)
         .into_iter()
         .collect::<Vec<_>>();
 
@@ -186,59 +446,176 @@ pub fn compute_worst_isochrones(
         .map(|dep| {
             compute_isochrones(
                 hrdf,
-                excluded_polygons,
                 IsochroneArgs {
                     latitude,
                     longitude,
                     departure_at: dep,
                     time_limit,
                     interval: isochrone_interval,
+                    num_bands: None,
                     max_num_explorable_connections,
                     num_starting_points,
+                    countries: countries.clone(),
+                    snap_to_interval,
+                    apply_excluded_polygons,
                     verbose,
                 },
-                display_mode,
-                inner_threads(num_threads, true),
+                &config.nested(),
             )
         })
-        .reduce(|lhs, rhs| {
-            if lhs.compute_max_area() < rhs.compute_max_area() {
-                lhs
-            } else {
-                rhs
-            }
-        });
+        .reduce(|lhs, rhs| Ok(smaller_isochrone_map(lhs?, rhs?)));
 
     if verbose {
         log::info!(
-            "Time computing the optimal solution : {:.2?}",
+            "{}Time computing the optimal solution : {:.2?}",
+            log_prefix(config.label()),
             start_time.elapsed()
         );
     }
     isochrone_map.expect("Could not find worst Isochrone Map")
 }
 
+/// The optimal and the worst isochrones of the same window, see compute_reliability_spread.
+#[derive(Debug)]
+pub struct ReliabilitySpread {
+    pub optimal: IsochroneMap,
+    pub worst: IsochroneMap,
+    /// Largest area of the worst isochrones over the one of the optimal isochrones, between 0
+    /// and 1. The closer to 1, the more consistent the service over the window.
+    pub area_ratio: f64,
+}
+
+/// Computes both the optimal and the worst isochrones in
+/// [departure_at - delta_time; departure_at + delta_time), each departure time being computed
+/// once instead of once for each of compute_optimal_isochrones and compute_worst_isochrones.
+pub fn compute_reliability_spread(
+    hrdf: &Hrdf,
+    isochrone_args: IsochroneArgs,
+    config: &IsochroneConfig,
+) -> RResult<ReliabilitySpread> {
+    // Built once for all the departure times if not given.
+    let stop_index = config.stop_index(hrdf.data_storage());
+    let config = &config.with_stop_index(&stop_index);
+    let IsochroneConfig {
+        delta_time,
+        num_threads,
+        search_step,
+        ..
+    } = *config;
+    let departure_at = isochrone_args.departure_at;
+    let verbose = isochrone_args.verbose;
+    // Same bands as compute_optimal_isochrones and compute_worst_isochrones.
+    let isochrone_args = IsochroneArgs {
+        interval: isochrone_args.effective_interval(),
+        num_bands: None,
+        ..isochrone_args
+    };
+
+    let start_time = Instant::now();
+    let departure_times = NaiveDateTimeRange::new(
+        departure_at - delta_time,
+        departure_at + delta_time,
+        search_step,
+    )
+    .into_iter()
+    .collect::<Vec<_>>();
+
+    let (optimal, worst) = departure_times
+        .into_par()
+        .num_threads(num_threads)
+        .map(|dep| -> RResult<_> {
+            let isochrone_map = compute_isochrones(
+                hrdf,
+                IsochroneArgs {
+                    departure_at: dep,
+                    ..isochrone_args.clone()
+                },
+                &config.nested(),
+            )?;
+            Ok((isochrone_map.clone(), isochrone_map))
+        })
+        .reduce(|lhs, rhs| {
+            let ((lhs_optimal, lhs_worst), (rhs_optimal, rhs_worst)) = (lhs?, rhs?);
+            Ok((
+                larger_isochrone_map(lhs_optimal, rhs_optimal),
+                smaller_isochrone_map(lhs_worst, rhs_worst),
+            ))
+        })
+        .expect("No isochrone_map found.")?;
+
+    if verbose {
+        log::info!(
+            "{}Time computing the reliability spread : {:.2?}",
+            log_prefix(config.label()),
+            start_time.elapsed()
+        );
+    }
+    let max_area = optimal.compute_max_area();
+    let area_ratio = if max_area > 0.0 {
+        worst.compute_max_area() / max_area
+    } else {
+        1.0
+    };
+    Ok(ReliabilitySpread {
+        optimal,
+        worst,
+        area_ratio,
+    })
+}
+
+/// Returns the isochrone map with the largest area. On equal areas the earliest departure is
+/// chosen, so that the result doesn't depend on the order of the parallel reduction.
+fn larger_isochrone_map(lhs: IsochroneMap, rhs: IsochroneMap) -> IsochroneMap {
+    let ordering = lhs
+        .compute_max_area()
+        .total_cmp(&rhs.compute_max_area())
+        .then(rhs.departure_at().cmp(&lhs.departure_at()));
+    if ordering.is_ge() { lhs } else { rhs }
+}
+
+/// Returns the isochrone map with the smallest area, the earliest departure on equal areas.
+fn smaller_isochrone_map(lhs: IsochroneMap, rhs: IsochroneMap) -> IsochroneMap {
+    let ordering = lhs
+        .compute_max_area()
+        .total_cmp(&rhs.compute_max_area())
+        .then(lhs.departure_at().cmp(&rhs.departure_at()));
+    if ordering.is_le() { lhs } else { rhs }
+}
+
 /// Computes the average isochrone.
 /// The point of origin is used to find the departure stop (the nearest stop).
 /// The departure date and time must be within the timetable period.
-#[allow(clippy::too_many_arguments)]
 pub fn compute_average_isochrones(
     hrdf: &Hrdf,
-    excluded_polygons: &MultiPolygon,
     isochrone_args: IsochroneArgs,
-    delta_time: Duration,
-    num_threads: usize,
-) -> IsochroneMap {
+    config: &IsochroneConfig,
+) -> RResult<IsochroneMap> {
+    let IsochroneConfig {
+        delta_time,
+        num_threads,
+        max_transfer_walk,
+        max_access_walk,
+        search_step,
+        ..
+    } = *config;
+    let isochrone_interval = isochrone_args.effective_interval();
     let IsochroneArgs {
         latitude,
         longitude,
         departure_at,
         time_limit,
-        interval: isochrone_interval,
+        interval: _,
+        num_bands: _,
         max_num_explorable_connections,
         num_starting_points,
+        countries,
+        snap_to_interval,
+        apply_excluded_polygons,
         verbose,
     } = isochrone_args;
+    let excluded_polygons = config
+        .excluding_polygons(apply_excluded_polygons)
+        .excluded_polygons();
 
     if verbose {
         log::info!(
@@ -249,25 +626,24 @@ pub fn compute_average_isochrones(
         );
     }
     // If there is no departue stop found we just use the default
-    let departure_coord = Coordinates::new(CoordinateSystem::WGS84, longitude, latitude);
-
-    let (easting, northing) = wgs84_to_lv95(latitude, longitude);
-    let departure_coord_lv95 = Coordinates::new(CoordinateSystem::LV95, easting, northing);
+    let (departure_coord, departure_coord_lv95) = departure_coordinates(latitude, longitude);
 
     let start_time = Instant::now();
     let min_date_time = departure_at - delta_time;
     let max_date_time = departure_at + delta_time;
 
-    let data = NaiveDateTimeRange::new(min_date_time, max_date_time, Duration::minutes(1))
+    let data = NaiveDateTimeRange::new(min_date_time, max_date_time, search_step)
         .into_iter()
         .collect::<Vec<_>>();
 
+    let stop_index = config.stop_index(hrdf.data_storage());
     let data = data
         .par()
         .num_threads(num_threads)
         .map(|dep| {
             let routes = compute_routes_from_origin(
                 hrdf,
+                &stop_index,
                 latitude,
                 longitude,
                 *dep,
@@ -275,12 +651,25 @@ pub fn compute_average_isochrones(
                 num_starting_points,
                 inner_threads(num_threads, true),
                 max_num_explorable_connections,
+                max_transfer_walk,
+                max_access_walk,
+                config.label(),
                 verbose,
-            );
-
-            unique_coordinates_from_routes(&routes, departure_at)
+            )?;
+            let routes = retain_countries(routes, &countries);
+
+            let mut data = stop_coordinates(hrdf, &routes, departure_at, config);
+            if snap_to_interval {
+                snap_to_time_limits(
+                    &mut data,
+                    &isochrone_time_limits(time_limit, isochrone_interval),
+                );
+            }
+            Ok(data)
         })
-        .collect::<Vec<_>>();
+        .collect::<Vec<_>>()
+        .into_iter()
+        .collect::<RResult<Vec<_>>>()?;
     let bounding_box = data.iter().fold(
         ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
         |cover_bb, d| {
@@ -315,11 +704,9 @@ pub fn compute_average_isochrones(
         .into_iter()
         .map(|(c, d)| (c, d / timesteps as i32))
         .collect::<Vec<_>>();
-    let isochrone_count = time_limit.num_minutes() / isochrone_interval.num_minutes();
-    let isochrones = (0..isochrone_count)
-        .map(|i| {
-            let current_time_limit = Duration::minutes(isochrone_interval.num_minutes() * (i + 1));
-
+    let isochrones = isochrone_time_limits(time_limit, isochrone_interval)
+        .into_iter()
+        .map(|current_time_limit| {
             let polygons = contour_line::get_polygons(
                 &avg_grid,
                 nx,
@@ -330,10 +717,13 @@ pub fn compute_average_isochrones(
             );
 
             let polygons = MultiPolygon(polygons.into_iter().collect());
+            // Simplified last, the polygons given to the difference are then valid ones.
             let polygons = polygons.difference(excluded_polygons);
-            Isochrone::new(polygons, current_time_limit.num_minutes() as u32)
+            let polygons = simplify_polygons(polygons, config.simplify_tolerance(), verbose);
+            Isochrone::from_time_limit(polygons, current_time_limit)
         })
         .collect::<Vec<_>>();
+    contour_line::recycle_grid(avg_grid);
 
     let areas = isochrones.iter().map(|i| i.compute_area()).collect();
     let max_distances = isochrones
@@ -347,11 +737,12 @@ pub fn compute_average_isochrones(
 
     if verbose {
         log::info!(
-            "Time for finding the isochrones : {:.2?}",
+            "{}Time for finding the isochrones : {:.2?}",
+            log_prefix(config.label()),
             start_time.elapsed()
         );
     }
-    IsochroneMap::new(
+    Ok(IsochroneMap::new(
         isochrones,
         areas,
         max_distances,
@@ -359,29 +750,149 @@ pub fn compute_average_isochrones(
         departure_at,
         convert_bounding_box_to_wgs84(bounding_box),
     )
+    .debug_validate())
 }
 
 /// Computes the isochrones.
-/// The point of origin is used to find the departure stop (the nearest stop).
+/// The point of origin is used to find the departure stop (the nearest stop), see
+/// compute_routes_from_origin for the error without any stop within max_access_walk.
 /// The departure date and time must be within the timetable period.
-#[allow(clippy::too_many_arguments)]
 pub fn compute_isochrones(
     hrdf: &Hrdf,
-    excluded_polygons: &MultiPolygon,
     isochrone_args: IsochroneArgs,
-    display_mode: IsochroneDisplayMode,
-    num_threads: usize,
-) -> IsochroneMap {
+    config: &IsochroneConfig,
+) -> RResult<IsochroneMap> {
+    Ok(compute_isochrones_and_routes(hrdf, isochrone_args, config)?.0)
+}
+
+/// Same as compute_isochrones, with the diagnostics of the stops within the bounding box of the
+/// isochrones: whether each one was reached and otherwise why, e.g. to explain an isochrone
+/// smaller than expected. The stops of the countries not included are left out. The number of
+/// stops not reached for each reason is logged when verbose.
+pub fn compute_isochrones_with_diagnostics(
+    hrdf: &Hrdf,
+    isochrone_args: IsochroneArgs,
+    config: &IsochroneConfig,
+) -> RResult<(IsochroneMap, IsochroneDiagnostics)> {
+    let departure_date = isochrone_args.departure_at.date();
+    let countries = isochrone_args.countries.clone();
+    let verbose = isochrone_args.verbose;
+    let (isochrone_map, routes) = compute_isochrones_and_routes(hrdf, isochrone_args, config)?;
+
+    let reached: HashSet<_> = routes.iter().filter_map(Route::arrival_stop_id).collect();
+    let ((min_latitude, min_longitude), (max_latitude, max_longitude)) =
+        isochrone_map.bounding_box();
+    let mut stops: Vec<_> = hrdf
+        .data_storage()
+        .stops()
+        .entries()
+        .into_iter()
+        .filter(|stop| countries.is_empty() || is_stop_in_countries(stop.id(), &countries))
+        .filter(|stop| {
+            stop.wgs84_coordinates()
+                .and_then(|coord| Some((coord.latitude()?, coord.longitude()?)))
+                .is_some_and(|(latitude, longitude)| {
+                    (min_latitude..=max_latitude).contains(&latitude)
+                        && (min_longitude..=max_longitude).contains(&longitude)
+                })
+        })
+        .map(|stop| {
+            let reason = unreached_reason(hrdf, stop, departure_date, &reached);
+            StopDiagnostic {
+                stop_id: stop.id(),
+                name: String::from(stop.name()),
+                reached: reason.is_none(),
+                reason,
+            }
+        })
+        .collect();
+    stops.sort_by_key(|stop| stop.stop_id);
+    let diagnostics = IsochroneDiagnostics { stops };
+
+    if verbose {
+        log::info!(
+            "{} stops within the bounding box, not reached: {} without service, {} not exchange points, {} beyond the time limit",
+            diagnostics.stops.len(),
+            diagnostics.count(UnreachedReason::NoService),
+            diagnostics.count(UnreachedReason::NotAnExchangePoint),
+            diagnostics.count(UnreachedReason::ExceededTimeLimit),
+        );
+    }
+    Ok((isochrone_map, diagnostics))
+}
+
+/// Why the stop was not reached, None if it was. A stop which is not an exchange point can still
+/// be reached by staying on a journey from a reached stop, it is then beyond the time limit.
+fn unreached_reason(
+    hrdf: &Hrdf,
+    stop: &Stop,
+    date: NaiveDate,
+    reached: &HashSet<i32>,
+) -> Option<UnreachedReason> {
+    if reached.contains(&stop.id()) {
+        None
+    } else if !is_stop_served_on(hrdf, stop.id(), date) {
+        Some(UnreachedReason::NoService)
+    } else if !stop.can_be_used_as_exchange_point()
+        && !is_served_from_reached_stop(hrdf, stop.id(), date, reached)
+    {
+        Some(UnreachedReason::NotAnExchangePoint)
+    } else {
+        Some(UnreachedReason::ExceededTimeLimit)
+    }
+}
+
+/// Whether a journey serving the stop on the date goes through a reached stop before it.
+fn is_served_from_reached_stop(
+    hrdf: &Hrdf,
+    stop_id: i32,
+    date: NaiveDate,
+    reached: &HashSet<i32>,
+) -> bool {
+    get_operating_journeys(hrdf.data_storage(), date, stop_id)
+        .into_iter()
+        .any(|journey| {
+            let route = journey.route();
+            route
+                .iter()
+                .rposition(|route_entry| route_entry.stop_id() == stop_id)
+                .is_some_and(|position| {
+                    route[..position]
+                        .iter()
+                        .any(|route_entry| reached.contains(&route_entry.stop_id()))
+                })
+        })
+}
+
+/// The isochrones and the routes they are computed from, the last route being the origin point.
+fn compute_isochrones_and_routes(
+    hrdf: &Hrdf,
+    isochrone_args: IsochroneArgs,
+    config: &IsochroneConfig,
+) -> RResult<(IsochroneMap, Vec<Route>)> {
+    let IsochroneConfig {
+        display_mode,
+        num_threads,
+        max_transfer_walk,
+        max_access_walk,
+        ..
+    } = *config;
+    let isochrone_interval = isochrone_args.effective_interval();
     let IsochroneArgs {
         latitude,
         longitude,
         departure_at,
         time_limit,
-        interval: isochrone_interval,
+        interval: _,
+        num_bands: _,
         max_num_explorable_connections,
         num_starting_points,
+        countries,
+        snap_to_interval,
+        apply_excluded_polygons,
         verbose,
     } = isochrone_args;
+    let config = &config.excluding_polygons(apply_excluded_polygons);
 
     if verbose {
         log::info!(
@@ -391,15 +902,13 @@ pub fn compute_isochrones(
         );
     }
     // If there is no departue stop found we just use the default
-    let departure_coord = Coordinates::new(CoordinateSystem::WGS84, longitude, latitude);
-
-    let (easting, northing) = wgs84_to_lv95(latitude, longitude);
-    let departure_coord_lv95 = Coordinates::new(CoordinateSystem::LV95, easting, northing);
+    let (departure_coord, departure_coord_lv95) = departure_coordinates(latitude, longitude);
 
     let start_time = Instant::now();
 
     let routes = compute_routes_from_origin(
         hrdf,
+        &config.stop_index(hrdf.data_storage()),
         latitude,
         longitude,
         departure_at,
@@ -407,24 +916,280 @@ pub fn compute_isochrones(
         num_starting_points,
         num_threads,
         max_num_explorable_connections,
+        max_transfer_walk,
+        max_access_walk,
+        config.label(),
         verbose,
-    );
+    )?;
+    let routes = retain_countries(routes, &countries);
 
     if verbose {
-        log::info!("Time for finding the routes : {:.2?}", start_time.elapsed());
+        log::info!(
+            "{}Time for finding the routes : {:.2?}",
+            log_prefix(config.label()),
+            start_time.elapsed()
+        );
     }
 
+    // We get only the stop coordinates
+    let mut data = stop_coordinates(hrdf, &routes, departure_at, config);
+    if snap_to_interval {
+        snap_to_time_limits(
+            &mut data,
+            &isochrone_time_limits(time_limit, isochrone_interval),
+        );
+    }
+    let (stops_reached, lines_reached) =
+        count_stops_and_lines(hrdf.data_storage(), &routes, departure_at, time_limit);
+
+    let isochrone_map = isochrone_map_from_data(
+        &data,
+        departure_at,
+        time_limit,
+        isochrone_interval,
+        (departure_coord, departure_coord_lv95),
+        config,
+        verbose,
+    )
+    .with_reached(stops_reached, lines_reached);
+    Ok((isochrone_map, routes))
+}
+
+/// Returns the stops reachable from the origin point within the time limit, without building the
+/// isochrones: the stop id, the arrival date and time and the duration from the departure, the
+/// closest stops first. The interval of isochrone_args is not used.
+/// The routing is done before the first item is returned.
+pub fn reachable_iter(
+    hrdf: &Hrdf,
+    isochrone_args: IsochroneArgs,
+    config: &IsochroneConfig,
+) -> RResult<impl Iterator<Item = (i32, NaiveDateTime, Duration)>> {
+    let IsochroneArgs {
+        latitude,
+        longitude,
+        departure_at,
+        time_limit,
+        max_num_explorable_connections,
+        num_starting_points,
+        countries,
+        verbose,
+        ..
+    } = isochrone_args;
+
+    let mut routes = compute_routes_from_origin(
+        hrdf,
+        &config.stop_index(hrdf.data_storage()),
+        latitude,
+        longitude,
+        departure_at,
+        time_limit,
+        num_starting_points,
+        config.num_threads,
+        max_num_explorable_connections,
+        config.max_transfer_walk,
+        config.max_access_walk,
+        config.label(),
+        verbose,
+    )?;
+    // The last route is the origin point.
+    routes.pop();
+    let mut reachable: Vec<_> = retain_countries(routes, &countries)
+        .into_iter()
+        .filter_map(|route| {
+            let arrival_at = route.arrival_at();
+            Some((
+                route.arrival_stop_id()?,
+                arrival_at,
+                arrival_at - departure_at,
+            ))
+        })
+        .collect();
+    reachable.sort_by_key(|&(stop_id, _, duration)| (duration, stop_id));
+    Ok(reachable.into_iter())
+}
+
+/// Computes the isochrone maps of several time limits from the same origin, e.g. for nested bands.
+/// The routes are only computed once, for the largest time limit, each map then only uses the
+/// stops reached within its time limit. The time_limit of isochrone_args is ignored, its interval
+/// still splits each map into isochrones, or its num_bands which must then divide each time limit.
+/// The maps are returned in the same order as time_limits.
+pub fn compute_isochrones_for_time_limits(
+    hrdf: &Hrdf,
+    isochrone_args: IsochroneArgs,
+    time_limits: &[Duration],
+    config: &IsochroneConfig,
+) -> RResult<Vec<IsochroneMap>> {
+    let Some(&max_time_limit) = time_limits.iter().max() else {
+        return Ok(Vec::new());
+    };
+    let isochrone_intervals = time_limits
+        .iter()
+        .map(|&time_limit| {
+            check_num_bands(time_limit, isochrone_args.num_bands)?;
+            Ok(isochrone_args.interval_up_to(time_limit))
+        })
+        .collect::<RResult<Vec<_>>>()?;
+    let IsochroneArgs {
+        latitude,
+        longitude,
+        departure_at,
+        interval: _,
+        num_bands: _,
+        max_num_explorable_connections,
+        num_starting_points,
+        countries,
+        snap_to_interval,
+        apply_excluded_polygons,
+        verbose,
+        ..
+    } = isochrone_args;
+    let config = &config.excluding_polygons(apply_excluded_polygons);
+    let departure = departure_coordinates(latitude, longitude);
+
     let start_time = Instant::now();
+    let routes = compute_routes_from_origin(
+        hrdf,
+        &config.stop_index(hrdf.data_storage()),
+        latitude,
+        longitude,
+        departure_at,
+        max_time_limit,
+        num_starting_points,
+        config.num_threads,
+        max_num_explorable_connections,
+        config.max_transfer_walk,
+        config.max_access_walk,
+        config.label(),
+        verbose,
+    )?;
+    let routes = retain_countries(routes, &countries);
+    if verbose {
+        log::info!(
+            "{}Time for finding the routes of {} time limits : {:.2?}",
+            log_prefix(config.label()),
+            time_limits.len(),
+            start_time.elapsed()
+        );
+    }
 
-    // We get only the stop coordinates
-    let data = unique_coordinates_from_routes(&routes, departure_at);
+    let data = stop_coordinates(hrdf, &routes, departure_at, config);
+    Ok(time_limits
+        .iter()
+        .zip(isochrone_intervals)
+        .map(|(&time_limit, isochrone_interval)| {
+            let mut data: Vec<_> = data
+                .iter()
+                .copied()
+                .filter(|&(_, duration)| duration <= time_limit)
+                .collect();
+            if snap_to_interval {
+                snap_to_time_limits(
+                    &mut data,
+                    &isochrone_time_limits(time_limit, isochrone_interval),
+                );
+            }
+            let (stops_reached, lines_reached) =
+                count_stops_and_lines(hrdf.data_storage(), &routes, departure_at, time_limit);
+            isochrone_map_from_data(
+                &data,
+                departure_at,
+                time_limit,
+                isochrone_interval,
+                departure,
+                config,
+                verbose,
+            )
+            .with_reached(stops_reached, lines_reached)
+        })
+        .collect())
+}
+
+/// Counts the distinct stops and lines reached by the routes within the time limit, the last
+/// route being the origin point. The journeys do not give their line, a line is identified by the
+/// administration, the transport type and the terminal stops of its journeys in either direction.
+fn count_stops_and_lines(
+    data_storage: &DataStorage,
+    routes: &[Route],
+    departure_at: NaiveDateTime,
+    time_limit: Duration,
+) -> (usize, usize) {
+    let routes = routes
+        .split_last()
+        .map_or(&[][..], |(_, routes)| routes)
+        .iter()
+        .filter(|route| route.arrival_at() - departure_at <= time_limit);
+
+    let mut stop_ids = HashSet::new();
+    let mut lines = HashSet::new();
+    for route in routes {
+        stop_ids.extend(route.arrival_stop_id());
+        for journey in route
+            .sections()
+            .iter()
+            .filter_map(|section| section.journey(data_storage))
+        {
+            let (Some(first), Some(last)) = (journey.route().first(), journey.route().last())
+            else {
+                continue;
+            };
+            let terminals = (
+                first.stop_id().min(last.stop_id()),
+                first.stop_id().max(last.stop_id()),
+            );
+            let designation = journey
+                .transport_type(data_storage)
+                .map(|transport_type| transport_type.designation())
+                .unwrap_or_default();
+            lines.insert((journey.administration(), designation, terminals));
+        }
+    }
+    (stop_ids.len(), lines.len())
+}
 
-    let bounding_box = get_bounding_box(&data, time_limit);
+/// Simplifies the polygons when a tolerance is given, see IsochroneConfig::with_simplify_tolerance.
+fn simplify_polygons(
+    polygons: MultiPolygon,
+    tolerance: Option<f64>,
+    verbose: bool,
+) -> MultiPolygon {
+    let Some(tolerance) = tolerance else {
+        return polygons;
+    };
+    let simplified = simplify_multi_polygon(&polygons, tolerance);
+    if verbose {
+        log::info!(
+            "Simplified the polygons from {} to {} vertices",
+            polygons.coords_count(),
+            simplified.coords_count()
+        );
+    }
+    simplified
+}
+
+/// Computes the isochrones from the stop coordinates and the durations to reach them.
+fn isochrone_map_from_data(
+    data: &[(Coordinates, Duration)],
+    departure_at: NaiveDateTime,
+    time_limit: Duration,
+    isochrone_interval: Duration,
+    (departure_coord, departure_coord_lv95): (Coordinates, Coordinates),
+    config: &IsochroneConfig,
+    verbose: bool,
+) -> IsochroneMap {
+    let IsochroneConfig {
+        excluded_polygons,
+        display_mode,
+        num_threads,
+        ..
+    } = *config;
+    let start_time = Instant::now();
+
+    let bounding_box = get_bounding_box(data, time_limit);
     let dx = 100.0;
 
     let grid = if display_mode == models::DisplayMode::ContourLine {
         Some(contour_line::create_grid(
-            &data,
+            data,
             bounding_box,
             time_limit,
             dx,
@@ -434,18 +1199,16 @@ pub fn compute_isochrones(
         None
     };
 
-    let isochrone_count = time_limit.num_minutes() / isochrone_interval.num_minutes();
-    let isochrones = (0..isochrone_count)
-        .map(|i| {
-            let current_time_limit = Duration::minutes(isochrone_interval.num_minutes() * (i + 1));
-            // let prev_time_limit = Duration::minutes(isochrone_interval.num_minutes() * i);
+    let isochrones = isochrone_time_limits(time_limit, isochrone_interval)
+        .into_iter()
+        .map(|current_time_limit| {
             let prev_time_limit = Duration::minutes(0);
 
             let polygons = match display_mode {
                 IsochroneDisplayMode::Circles => {
                     let num_points_circle = 6;
                     circles::get_polygons(
-                        &data,
+                        data,
                         current_time_limit,
                         prev_time_limit,
                         num_points_circle,
@@ -464,12 +1227,16 @@ pub fn compute_isochrones(
                     )
                 }
             };
-            // let polygons = MultiPolygon(polygons.into_iter().collect());
+            // Simplified last, the polygons given to the difference are then valid ones.
             let polygons = polygons.difference(excluded_polygons);
+            let polygons = simplify_polygons(polygons, config.simplify_tolerance(), verbose);
 
-            Isochrone::new(polygons, current_time_limit.num_minutes() as u32)
+            Isochrone::from_time_limit(polygons, current_time_limit)
         })
         .collect::<Vec<_>>();
+    if let Some((grid, ..)) = grid {
+        contour_line::recycle_grid(grid);
+    }
 
     let areas = isochrones.iter().map(|i| i.compute_area()).collect();
     let max_distances = isochrones
@@ -479,54 +1246,192 @@ pub fn compute_isochrones(
             let (w_x, w_y) = lv95_to_wgs84(x, y);
             ((w_x, w_y), max)
         })
-        .collect();
+        .collect();
+
+    if verbose {
+        log::info!(
+            "{}Time for finding the isochrones : {:.2?}",
+            log_prefix(config.label()),
+            start_time.elapsed()
+        );
+    }
+    IsochroneMap::new(
+        isochrones,
+        areas,
+        max_distances,
+        departure_coord,
+        departure_at,
+        convert_bounding_box_to_wgs84(bounding_box),
+    )
+    .debug_validate()
+}
+
+/// Computes the isochrone reachable by walking only from the origin point (WGS84), a single circle
+/// of radius time_to_distance(time_limit, walking_speed). It is the baseline showing what the
+/// public transport adds. The walk does not depend on the time, the departure_at of the map is
+/// left to its default.
+pub fn compute_walking_isochrone(
+    latitude: f64,
+    longitude: f64,
+    time_limit: Duration,
+    walking_speed_in_kilometers_per_hour: f64,
+) -> IsochroneMap {
+    // The circle is smooth enough to be compared with the area of the transit isochrones.
+    let num_circle_points = 64;
+    let (departure_coord, departure_coord_lv95) = departure_coordinates(latitude, longitude);
+    let radius = time_to_distance(time_limit, walking_speed_in_kilometers_per_hour);
+
+    let isochrone = Isochrone::from_time_limit(
+        circles::get_circle(departure_coord_lv95, radius, num_circle_points),
+        time_limit,
+    );
+    let areas = vec![isochrone.compute_area()];
+    let ((x, y), max) = isochrone.compute_max_distance(departure_coord_lv95);
+    let max_distances = vec![(lv95_to_wgs84(x, y), max)];
+
+    let (easting, northing) = (
+        departure_coord_lv95
+            .easting()
+            .expect("Wrong coordinate system"),
+        departure_coord_lv95
+            .northing()
+            .expect("Wrong coordinate system"),
+    );
+    let bounding_box = (
+        (easting - radius, northing - radius),
+        (easting + radius, northing + radius),
+    );
+
+    IsochroneMap::new(
+        vec![isochrone],
+        areas,
+        max_distances,
+        departure_coord,
+        NaiveDateTime::default(),
+        convert_bounding_box_to_wgs84(bounding_box),
+    )
+}
+
+/// Computes the isochrones from the same origin for each of the given departure times, e.g. to
+/// animate their evolution during the day. The departure_at of isochrone_args is ignored.
+/// The maps are returned in the same order as departure_times.
+pub fn compute_isochrones_series(
+    hrdf: &Hrdf,
+    isochrone_args: IsochroneArgs,
+    departure_times: &[NaiveDateTime],
+    config: &IsochroneConfig,
+) -> RResult<Vec<IsochroneMap>> {
+    // Built once for all the departure times if not given.
+    let stop_index = config.stop_index(hrdf.data_storage());
+    let config = &config.with_stop_index(&stop_index);
+    let start_time = Instant::now();
+
+    let isochrone_maps = departure_times
+        .par()
+        .num_threads(config.num_threads)
+        .map(|&departure_at| {
+            compute_isochrones(
+                hrdf,
+                IsochroneArgs {
+                    departure_at,
+                    ..isochrone_args.clone()
+                },
+                &config.nested(),
+            )
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .collect::<RResult<Vec<_>>>()?;
 
-    if verbose {
+    if isochrone_args.verbose {
         log::info!(
-            "Time for finding the isochrones : {:.2?}",
+            "{}Time computing the {} isochrones of the series : {:.2?}",
+            log_prefix(config.label()),
+            departure_times.len(),
             start_time.elapsed()
         );
     }
-    IsochroneMap::new(
-        isochrones,
-        areas,
-        max_distances,
-        departure_coord,
-        departure_at,
-        convert_bounding_box_to_wgs84(bounding_box),
+    Ok(isochrone_maps)
+}
+
+/// Computes the accessibility score of an isochrone map: the population reached in each band
+/// between two consecutive isochrones multiplied by the weight of the band, closest band first.
+/// The closer bands should weigh more. Bands without a weight are ignored.
+#[cfg(feature = "hectare")]
+pub fn accessibility_score(
+    isochrone_map: &IsochroneMap,
+    hectare: &HectareData,
+    weights: &[f64],
+) -> f64 {
+    weighted_bands_population(
+        &isochrone_map.compute_population(hectare.records()),
+        weights,
+    )
+}
+
+/// The isochrones are cumulative, the population of a band is the population reached by its
+/// isochrone minus the one reached by the previous isochrone.
+#[cfg(feature = "hectare")]
+fn weighted_bands_population(population_reached: &[u64], weights: &[f64]) -> f64 {
+    population_reached
+        .iter()
+        .scan(0, |previous, &population| {
+            let band = population.saturating_sub(*previous);
+            *previous = population;
+            Some(band)
+        })
+        .zip(weights)
+        .map(|(band, weight)| band as f64 * weight)
+        .sum()
+}
+
+/// Returns the departure point in WGS84 and LV95 coordinates.
+fn departure_coordinates(latitude: f64, longitude: f64) -> (Coordinates, Coordinates) {
+    let wgs84 = Coordinates::new(CoordinateSystem::WGS84, latitude, longitude);
+    let (easting, northing) = wgs84_to_lv95(latitude, longitude);
+    (
+        wgs84,
+        Coordinates::new(CoordinateSystem::LV95, easting, northing),
     )
 }
 
+/// Keeps the routes arriving at a stop of one of the countries, and the point of origin which is
+/// the last route. All the routes are kept when countries is empty.
+/// Only the arrival stop is checked, a route may pass through the stops of other countries, e.g.
+/// from Basel to Schaffhausen through Germany.
+fn retain_countries(mut routes: Vec<Route>, countries: &[String]) -> Vec<Route> {
+    if countries.is_empty() {
+        return routes;
+    }
+    let origin = routes.pop();
+    routes.retain(|route| {
+        route
+            .arrival_stop_id()
+            .is_some_and(|stop_id| is_stop_in_countries(stop_id, countries))
+    });
+    routes.extend(origin);
+    routes
+}
+
+/// The country of a stop is given by the prefix of its id.
+fn is_stop_in_countries(stop_id: i32, countries: &[String]) -> bool {
+    let stop_id = stop_id.to_string();
+    countries
+        .iter()
+        .any(|country| stop_id.starts_with(country.as_str()))
+}
+
 #[allow(dead_code)]
-fn find_nearest_stop(
-    data_storage: &DataStorage,
+fn find_nearest_stop<'a>(
+    data_storage: &'a DataStorage,
+    stop_index: &StopIndex,
     origin_point_latitude: f64,
     origin_point_longitude: f64,
-) -> &Stop {
-    data_storage
-        .stops()
-        .entries()
-        .into_iter()
-        .filter(|stop| stop.wgs84_coordinates().is_some())
-        .min_by(|a, b| {
-            let coord_1 = a.wgs84_coordinates().unwrap();
-            let distance_1 = haversine_distance(
-                origin_point_latitude,
-                origin_point_longitude,
-                coord_1.latitude().expect("Wrong coordinate system"),
-                coord_1.longitude().expect("Wrong coordinate system"),
-            );
-
-            let coord_2 = b.wgs84_coordinates().unwrap();
-            let distance_2 = haversine_distance(
-                origin_point_latitude,
-                origin_point_longitude,
-                coord_2.latitude().expect("Wrong coordinate system"),
-                coord_2.longitude().expect("Wrong coordinate system"),
-            );
-
-            distance_1.partial_cmp(&distance_2).unwrap()
-        })
+) -> &'a Stop {
+    stop_index
+        .nearest(origin_point_latitude, origin_point_longitude, 1)
+        .first()
+        .and_then(|&(stop_id, _)| data_storage.stops().find(stop_id))
         // The stop list cannot be empty.
         .unwrap()
 }
@@ -535,16 +1440,60 @@ fn find_nearest_stop(
 pub(crate) fn unique_coordinates_from_routes(
     routes: &[Route],
     departure_at: NaiveDateTime,
+) -> Vec<(Coordinates, Duration)> {
+    unique_coordinates(routes, departure_at, |stop_id, coordinates| {
+        (stop_id, coordinates)
+    })
+}
+
+/// Same as unique_coordinates_from_routes, the stops of a station split into several stops being
+/// replaced by the station.
+fn unique_coordinates_by_station(
+    hrdf: &Hrdf,
+    routes: &[Route],
+    departure_at: NaiveDateTime,
+) -> Vec<(Coordinates, Duration)> {
+    unique_coordinates(routes, departure_at, |stop_id, coordinates| {
+        parent_station(hrdf, stop_id)
+            .and_then(|station_id| {
+                let station = hrdf.data_storage().stops().find(station_id)?;
+                Some((station_id, station.lv95_coordinates()?))
+            })
+            .unwrap_or((stop_id, coordinates))
+    })
+}
+
+/// The coordinates of the stops reached by the routes, grouped by station if the config says so.
+fn stop_coordinates(
+    hrdf: &Hrdf,
+    routes: &[Route],
+    departure_at: NaiveDateTime,
+    config: &IsochroneConfig,
+) -> Vec<(Coordinates, Duration)> {
+    if config.group_stations {
+        unique_coordinates_by_station(hrdf, routes, departure_at)
+    } else {
+        unique_coordinates_from_routes(routes, departure_at)
+    }
+}
+
+/// Keeps the minimum duration of each stop, the stop id and the coordinates of the arrival stop of
+/// a route being given by location.
+fn unique_coordinates(
+    routes: &[Route],
+    departure_at: NaiveDateTime,
+    location: impl Fn(i32, Coordinates) -> (i32, Coordinates),
 ) -> Vec<(Coordinates, Duration)> {
     let mut coordinates_duration: HashMap<i32, (Coordinates, chrono::Duration)> = HashMap::new();
     for route in routes {
         let arrival_stop = route.sections().last().expect("Route sections was empty");
-        let arrival_stop_id = arrival_stop.arrival_stop_id();
         let arrival_stop_coords = if let Some(c) = arrival_stop.arrival_stop_lv95_coordinates() {
             c
         } else {
             continue;
         };
+        let (arrival_stop_id, arrival_stop_coords) =
+            location(arrival_stop.arrival_stop_id(), arrival_stop_coords);
         let new_duration = route.arrival_at() - departure_at;
         if let Some((_, duration)) = coordinates_duration.get_mut(&arrival_stop_id) {
             // We want the shortest trip duration to be kept only
@@ -559,6 +1508,16 @@ pub(crate) fn unique_coordinates_from_routes(
     coordinates_duration.into_values().collect()
 }
 
+/// Rounds the duration to reach each stop up to the first time limit it is within, so that the
+/// stop only contributes to the isochrones from that one on.
+fn snap_to_time_limits(data: &mut [(Coordinates, Duration)], time_limits: &[Duration]) {
+    for (_, duration) in data {
+        if let Some(&time_limit) = time_limits.iter().find(|&&t| t >= *duration) {
+            *duration = time_limit;
+        }
+    }
+}
+
 fn get_bounding_box(
     data: &[(Coordinates, Duration)],
     time_limit: Duration,
@@ -598,6 +1557,19 @@ fn get_bounding_box(
     ((min_x, min_y), (max_x, max_y))
 }
 
+/// Returns the time limit of each isochrone, every interval up to time_limit included.
+/// The last isochrone is closer to the previous one when the interval does not divide time_limit.
+/// The precision is the second.
+fn isochrone_time_limits(time_limit: Duration, interval: Duration) -> Vec<Duration> {
+    let time_limit = time_limit.num_seconds();
+    let interval = interval.num_seconds().max(1);
+    (interval..time_limit)
+        .step_by(interval as usize)
+        .chain(std::iter::once(time_limit))
+        .map(Duration::seconds)
+        .collect()
+}
+
 fn convert_bounding_box_to_wgs84(
     bounding_box: ((f64, f64), (f64, f64)),
 ) -> ((f64, f64), (f64, f64)) {
@@ -611,7 +1583,7 @@ fn convert_bounding_box_to_wgs84(
 mod polygon_tests {
     use super::*;
     use crate::routing::compute_routes_from_origin;
-    use crate::utils::create_date_time;
+    use crate::utils::{create_date_time, fixture_hrdf};
     use geo::{Area, Contains, Point};
     use std::f64::consts::PI;
     use std::fs::File;
@@ -838,6 +1810,191 @@ mod polygon_tests {
         );
     }
 
+    #[test]
+    fn test_snap_to_time_limits() {
+        let coord = Coordinates::new(CoordinateSystem::LV95, 2500000.0, 1117000.0);
+        let mut data = [0, 7, 10, 11, 55]
+            .map(|minutes| (coord, Duration::minutes(minutes)))
+            .to_vec();
+        snap_to_time_limits(
+            &mut data,
+            &isochrone_time_limits(Duration::minutes(55), Duration::minutes(10)),
+        );
+        assert_eq!(
+            data.iter()
+                .map(|(_, duration)| duration.num_minutes())
+                .collect::<Vec<_>>(),
+            vec![10, 10, 10, 20, 55]
+        );
+    }
+
+    #[test]
+    fn test_snap_to_interval_never_grows_the_bands() {
+        let hrdf = fixture_hrdf();
+        let excluded_polygons = MultiPolygon::new(vec![]);
+        let config = IsochroneConfig::new(&excluded_polygons);
+        // Alpha
+        let isochrone_args = IsochroneArgs {
+            latitude: 46.951081,
+            longitude: 7.438637,
+            departure_at: create_date_time(2025, 6, 2, 8, 0),
+            time_limit: Duration::minutes(30),
+            interval: Duration::minutes(10),
+            num_bands: None,
+            max_num_explorable_connections: 10,
+            num_starting_points: 5,
+            countries: Vec::new(),
+            snap_to_interval: true,
+            apply_excluded_polygons: true,
+            verbose: false,
+        };
+
+        let snapped = compute_isochrones(&hrdf, isochrone_args.clone(), &config).unwrap();
+        let smooth = compute_isochrones(
+            &hrdf,
+            IsochroneArgs {
+                snap_to_interval: false,
+                ..isochrone_args
+            },
+            &config,
+        )
+        .unwrap();
+        assert_eq!(snapped.areas().len(), smooth.areas().len());
+        // The stops are reached later, the isochrones can only shrink, up to the rounding of the
+        // union of the circles.
+        for (snapped, smooth) in snapped.areas().iter().zip(smooth.areas()) {
+            assert!(*snapped <= smooth * (1.0 + 1e-6), "{snapped} > {smooth}");
+        }
+    }
+
+    #[test]
+    fn test_unreached_reason_of_a_stop_which_is_not_an_exchange_point() {
+        let hrdf = fixture_hrdf();
+        let date = create_date_time(2025, 6, 2, 8, 0).date();
+        // Juliett isn't an exchange point, the R 301 goes from Alpha through Golf and Juliett to
+        // Delta.
+        let juliett = hrdf.data_storage().stops().find(8500010).unwrap();
+        let reason = |reached: &[i32]| {
+            unreached_reason(&hrdf, juliett, date, &reached.iter().copied().collect())
+        };
+
+        assert_eq!(reason(&[8500010]), None);
+        assert_eq!(reason(&[8500001]), Some(UnreachedReason::ExceededTimeLimit));
+        assert_eq!(
+            reason(&[8500004, 8500005]),
+            Some(UnreachedReason::NotAnExchangePoint)
+        );
+    }
+
+    #[test]
+    fn test_time_limits_split_by_num_bands() {
+        let hrdf = fixture_hrdf();
+        let excluded_polygons = MultiPolygon::new(vec![]);
+        let config = IsochroneConfig::new(&excluded_polygons);
+        // Alpha
+        let isochrone_args = |time_limit, num_bands| IsochroneArgs {
+            latitude: 46.951081,
+            longitude: 7.438637,
+            departure_at: create_date_time(2025, 6, 2, 8, 0),
+            time_limit,
+            interval: Duration::minutes(10),
+            num_bands: Some(num_bands),
+            max_num_explorable_connections: 10,
+            num_starting_points: 5,
+            countries: Vec::new(),
+            snap_to_interval: false,
+            apply_excluded_polygons: true,
+            verbose: false,
+        };
+        let time_limits = [Duration::minutes(20), Duration::minutes(40)];
+
+        let isochrone_maps = compute_isochrones_for_time_limits(
+            &hrdf,
+            isochrone_args(Duration::zero(), 2),
+            &time_limits,
+            &config,
+        )
+        .unwrap();
+        for (isochrone_map, time_limit) in isochrone_maps.iter().zip(time_limits) {
+            // Each time limit is split in 2, not the time_limit of the args.
+            let expected =
+                compute_isochrones(&hrdf, isochrone_args(time_limit, 2), &config).unwrap();
+            assert_eq!(isochrone_map.areas().len(), 2);
+            for (area, expected) in isochrone_map.areas().iter().zip(expected.areas()) {
+                assert!(
+                    (area - expected).abs() <= 1e-6 * expected,
+                    "{area} != {expected}"
+                );
+            }
+        }
+
+        // 20 minutes can't be split in 7 isochrones of whole seconds.
+        assert!(matches!(
+            compute_isochrones_for_time_limits(
+                &hrdf,
+                isochrone_args(Duration::zero(), 7),
+                &time_limits,
+                &config,
+            ),
+            Err(RError::InvalidNumBands)
+        ));
+    }
+
+    #[test]
+    fn test_isochrone_time_limits() {
+        let minutes = |limits: Vec<Duration>| -> Vec<i64> {
+            limits.iter().map(Duration::num_minutes).collect()
+        };
+        assert_eq!(
+            minutes(isochrone_time_limits(
+                Duration::minutes(60),
+                Duration::minutes(20)
+            )),
+            vec![20, 40, 60]
+        );
+        assert_eq!(
+            minutes(isochrone_time_limits(
+                Duration::minutes(60),
+                Duration::minutes(25)
+            )),
+            vec![25, 50, 60]
+        );
+        assert_eq!(
+            minutes(isochrone_time_limits(
+                Duration::minutes(10),
+                Duration::minutes(30)
+            )),
+            vec![10]
+        );
+        assert_eq!(
+            isochrone_time_limits(Duration::minutes(2), Duration::seconds(45)),
+            vec![
+                Duration::seconds(45),
+                Duration::seconds(90),
+                Duration::seconds(120)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_step_must_be_positive() {
+        let excluded_polygons = MultiPolygon::new(vec![]);
+        let config = IsochroneConfig::new(&excluded_polygons);
+        assert_eq!(
+            config
+                .with_search_step(Duration::seconds(30))
+                .unwrap()
+                .search_step(),
+            Duration::seconds(30)
+        );
+        for search_step in [Duration::zero(), Duration::seconds(-60)] {
+            assert!(matches!(
+                config.with_search_step(search_step),
+                Err(RError::InvalidSearchStep)
+            ));
+        }
+    }
+
     #[test]
     fn test_contour_polygon_geometry() {
         // Create sample grid data
@@ -886,15 +2043,28 @@ mod polygon_tests {
             area,
             max_four_min_area
         );
+
+        // The grid and the contour builder reused from the previous computation give the same
+        // polygons.
+        contour_line::recycle_grid(grid);
+        let (grid, nx, ny, dx) =
+            contour_line::create_grid(&data, bbox, Duration::minutes(10), dx, 1);
+        let reused = contour_line::get_polygons(&grid, nx, ny, bbox.0, Duration::minutes(5), dx);
+        assert_eq!(reused, result);
     }
 
     #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
     async fn test_polygon_generation_performance() {
         let hrdf = Hrdf::try_from_year(2025, true, None).await.unwrap();
         let departure_at = create_date_time(2025, 6, 15, 12, 10);
 
         let routes = compute_routes_from_origin(
             &hrdf,
+            &StopIndex::new(hrdf.data_storage()),
             47.3769,
             8.5417, // Zürich
             departure_at,
@@ -902,8 +2072,12 @@ mod polygon_tests {
             10,
             8,
             10,
+            None,
+            DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+            None,
             false,
-        );
+        )
+        .unwrap();
 
         let data = unique_coordinates_from_routes(&routes, departure_at);
 
@@ -954,6 +2128,560 @@ mod polygon_tests {
         );
     }
 
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_more_starting_points_reach_more_stops() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let departure_at = create_date_time(2025, 6, 15, 12, 10);
+
+        let count_reached_stops = |num_starting_points| {
+            let routes = compute_routes_from_origin(
+                &hrdf,
+                &StopIndex::new(hrdf.data_storage()),
+                47.3769,
+                8.5417, // Zürich
+                departure_at,
+                Duration::minutes(30),
+                num_starting_points,
+                8,
+                10,
+                None,
+                DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+                None,
+                false,
+            )
+            .unwrap();
+            unique_coordinates_from_routes(&routes, departure_at).len()
+        };
+
+        let reached_1 = count_reached_stops(1);
+        let reached_5 = count_reached_stops(5);
+        let reached_10 = count_reached_stops(10);
+        assert!(
+            reached_1 <= reached_5 && reached_5 <= reached_10,
+            "More starting points should reach at least as many stops, but we have: 1 => {}, 5 => {}, 10 => {}",
+            reached_1,
+            reached_5,
+            reached_10
+        );
+    }
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_reachable_iter_matches_the_isochrones() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let excluded_polygons = MultiPolygon::new(vec![]);
+        let config = IsochroneConfig::new(&excluded_polygons).with_num_threads(8);
+        let isochrone_args = IsochroneArgs {
+            latitude: 46.20956654,
+            longitude: 6.13536000, // Genève, gare Cornavin
+            departure_at: create_date_time(2025, 6, 15, 12, 10),
+            time_limit: Duration::minutes(20),
+            interval: Duration::minutes(20),
+            num_bands: None,
+            max_num_explorable_connections: 5,
+            num_starting_points: 5,
+            countries: Vec::new(),
+            snap_to_interval: false,
+            apply_excluded_polygons: true,
+            verbose: false,
+        };
+
+        let reachable: Vec<_> = reachable_iter(&hrdf, isochrone_args.clone(), &config)
+            .unwrap()
+            .collect();
+        let isochrone_map = compute_isochrones(&hrdf, isochrone_args, &config).unwrap();
+        assert_eq!(Some(reachable.len()), isochrone_map.stops_reached());
+        assert!(reachable.is_sorted_by_key(|&(_, _, duration)| duration));
+        assert!(reachable.iter().all(|&(_, arrival_at, duration)| {
+            duration <= Duration::minutes(20)
+                && arrival_at == create_date_time(2025, 6, 15, 12, 10) + duration
+        }));
+    }
+
+    /// Compares two JSON values, the numbers being equal up to the tolerance. Panics with the path
+    /// of the first difference.
+    fn assert_json_approx_eq(
+        current: &serde_json::Value,
+        reference: &serde_json::Value,
+        tolerance: f64,
+        path: &str,
+    ) {
+        use serde_json::Value;
+
+        match (current, reference) {
+            (Value::Number(lhs), Value::Number(rhs)) => {
+                let (lhs, rhs) = (lhs.as_f64().unwrap(), rhs.as_f64().unwrap());
+                // The areas and distances of the properties are compared relatively.
+                let tolerance = if path.contains(".properties.") {
+                    tolerance * rhs.abs().max(1.0)
+                } else {
+                    tolerance
+                };
+                assert!((lhs - rhs).abs() <= tolerance, "{path}: {lhs} != {rhs}");
+            }
+            (Value::Array(lhs), Value::Array(rhs)) => {
+                assert_eq!(lhs.len(), rhs.len(), "{path}: different lengths");
+                for (i, (lhs, rhs)) in lhs.iter().zip(rhs).enumerate() {
+                    assert_json_approx_eq(lhs, rhs, tolerance, &format!("{path}[{i}]"));
+                }
+            }
+            (Value::Object(lhs), Value::Object(rhs)) => {
+                assert_eq!(
+                    lhs.keys().collect::<Vec<_>>(),
+                    rhs.keys().collect::<Vec<_>>(),
+                    "{path}: different keys"
+                );
+                for (key, lhs) in lhs {
+                    assert_json_approx_eq(lhs, &rhs[key], tolerance, &format!("{path}.{key}"));
+                }
+            }
+            _ => assert_eq!(current, reference, "{path}"),
+        }
+    }
+
+    /// Guards the geometry of the circles and contour_line isochrones against regressions. The
+    /// reference in test_json is recorded again when UPDATE_SNAPSHOTS is set.
+    #[test]
+    fn test_isochrones_geojson_snapshot() {
+        let hrdf = fixture_hrdf();
+        let excluded_polygons = MultiPolygon::new(vec![]);
+        // Alpha
+        let isochrone_args = IsochroneArgs {
+            latitude: 46.951081,
+            longitude: 7.438637,
+            departure_at: create_date_time(2025, 6, 2, 8, 0),
+            time_limit: Duration::minutes(40),
+            interval: Duration::minutes(10),
+            num_bands: None,
+            max_num_explorable_connections: 10,
+            num_starting_points: 5,
+            countries: Vec::new(),
+            snap_to_interval: false,
+            apply_excluded_polygons: true,
+            verbose: false,
+        };
+
+        for display_mode in [
+            IsochroneDisplayMode::Circles,
+            IsochroneDisplayMode::ContourLine,
+        ] {
+            let config = IsochroneConfig::new(&excluded_polygons).with_display_mode(display_mode);
+            let current = compute_isochrones(&hrdf, isochrone_args.clone(), &config)
+                .unwrap()
+                .to_geojson();
+
+            let fname = format!("test_json/ref_isochrones_{display_mode}.geojson");
+            if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+                log::warn!("Recording {fname}");
+                std::fs::write(&fname, serde_json::to_string_pretty(&current).unwrap()).unwrap();
+                continue;
+            }
+            let reference: serde_json::Value = serde_json::from_str(
+                &std::fs::read_to_string(&fname)
+                    .unwrap_or_else(|e| panic!("{fname}: {e}, run with UPDATE_SNAPSHOTS=1")),
+            )
+            .unwrap();
+            assert_json_approx_eq(&current, &reference, 1e-6, &fname);
+        }
+    }
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_group_stations_at_zurich_hb() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let departure_at = create_date_time(2025, 6, 15, 12, 10);
+
+        let routes = compute_routes_from_origin(
+            &hrdf,
+            &StopIndex::new(hrdf.data_storage()),
+            47.37817,
+            8.54021, // Zürich HB
+            departure_at,
+            Duration::minutes(15),
+            5,
+            8,
+            10,
+            None,
+            DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+            None,
+            false,
+        )
+        .unwrap();
+        let by_stop = unique_coordinates_from_routes(&routes, departure_at);
+        let by_station = unique_coordinates_by_station(&hrdf, &routes, departure_at);
+        assert!(by_station.len() < by_stop.len());
+
+        // Zürich HB SZU (8503088) is replaced by Zürich HB (8503000).
+        let coordinates = |stop_id| {
+            hrdf.data_storage()
+                .stops()
+                .find(stop_id)
+                .unwrap()
+                .lv95_coordinates()
+                .unwrap()
+        };
+        let contains = |data: &[(Coordinates, Duration)], c: Coordinates| {
+            data.iter().any(|(coord, _)| {
+                coord.easting() == c.easting() && coord.northing() == c.northing()
+            })
+        };
+        assert!(contains(&by_stop, coordinates(8503088)));
+        assert!(!contains(&by_station, coordinates(8503088)));
+        assert!(contains(&by_station, coordinates(8503000)));
+    }
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_origin_between_two_stations_uses_both() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let excluded_polygons = MultiPolygon::new(vec![]);
+        let departure_at = create_date_time(2025, 6, 15, 12, 10);
+        // Halfway between Zürich HB and Zürich Stadelhofen.
+        let (latitude, longitude) = (47.37242, 8.54441);
+
+        let stop_index = StopIndex::new(hrdf.data_storage());
+        let routes = |num_starting_points| {
+            compute_routes_from_origin(
+                &hrdf,
+                &stop_index,
+                latitude,
+                longitude,
+                departure_at,
+                Duration::minutes(30),
+                num_starting_points,
+                8,
+                10,
+                None,
+                DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+                None,
+                false,
+            )
+            .unwrap()
+        };
+        let arrivals = |routes: Vec<Route>| -> HashMap<_, _> {
+            routes
+                .iter()
+                .map(|route| {
+                    (
+                        route.sections().last().unwrap().arrival_stop_id(),
+                        route.arrival_at(),
+                    )
+                })
+                .collect()
+        };
+        let routes_5 = routes(5);
+        let num_routes = routes_5.len();
+        let arrivals_5 = arrivals(routes_5);
+        // Each stop appears once, reached no later than from the closest departure stop alone.
+        assert_eq!(arrivals_5.len(), num_routes);
+        for (stop_id, arrival_at) in arrivals(routes(1)) {
+            assert!(arrivals_5[&stop_id] <= arrival_at, "{stop_id}");
+        }
+
+        let area = |num_starting_points| {
+            compute_isochrones(
+                &hrdf,
+                IsochroneArgs {
+                    latitude,
+                    longitude,
+                    departure_at,
+                    time_limit: Duration::minutes(30),
+                    interval: Duration::minutes(30),
+                    num_bands: None,
+                    max_num_explorable_connections: 10,
+                    num_starting_points,
+                    countries: Vec::new(),
+                    snap_to_interval: false,
+                    apply_excluded_polygons: true,
+                    verbose: false,
+                },
+                &IsochroneConfig::new(&excluded_polygons).with_num_threads(8),
+            )
+            .unwrap()
+            .compute_max_area()
+        };
+        let area_1 = area(1);
+        let area_5 = area(5);
+        assert!(
+            area_1 < area_5,
+            "Seeding from both stations should grow the isochrone: 1 => {area_1}, 5 => {area_5}"
+        );
+    }
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_area_increases_with_max_num_explorable_connections() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let excluded_polygons = MultiPolygon::new(vec![]);
+
+        let areas = [1, 2, 3, 5, 10]
+            .into_iter()
+            .map(|max_num_explorable_connections| {
+                compute_isochrones(
+                    &hrdf,
+                    IsochroneArgs {
+                        latitude: 46.20956654,
+                        longitude: 6.13536, // Genève
+                        departure_at: create_date_time(2025, 6, 15, 12, 10),
+                        time_limit: Duration::minutes(60),
+                        interval: Duration::minutes(60),
+                        num_bands: None,
+                        max_num_explorable_connections,
+                        num_starting_points: 5,
+                        countries: Vec::new(),
+                        snap_to_interval: false,
+                        apply_excluded_polygons: true,
+                        verbose: false,
+                    },
+                    &IsochroneConfig::new(&excluded_polygons).with_num_threads(8),
+                )
+                .unwrap()
+                .compute_max_area()
+            })
+            .collect::<Vec<_>>();
+
+        for pair in areas.windows(2) {
+            assert!(
+                pair[0] <= pair[1],
+                "Area should not decrease with the number of connections, but we have: {:?}",
+                areas
+            );
+        }
+    }
+
+    /// An isochrone map made of a single square isochrone.
+    fn square_isochrone_map(size: f64, departure_at: NaiveDateTime) -> IsochroneMap {
+        let square = geo::Polygon::new(
+            geo::LineString::from(vec![
+                (46.0, 6.0),
+                (46.0 + size, 6.0),
+                (46.0 + size, 6.0 + size),
+                (46.0, 6.0 + size),
+                (46.0, 6.0),
+            ]),
+            vec![],
+        );
+        let isochrone = Isochrone::new(MultiPolygon::new(vec![square]), 60);
+        let area = isochrone.compute_area();
+        IsochroneMap::new(
+            vec![isochrone],
+            vec![area],
+            vec![],
+            Coordinates::new(CoordinateSystem::WGS84, 46.0, 6.0),
+            departure_at,
+            ((46.0, 6.0), (46.0 + size, 6.0 + size)),
+        )
+    }
+
+    #[test]
+    fn test_equal_areas_select_earliest_departure() {
+        let early = create_date_time(2025, 6, 15, 12, 0);
+        let late = create_date_time(2025, 6, 15, 12, 1);
+        let other = create_date_time(2025, 6, 15, 11, 59);
+
+        for select in [larger_isochrone_map, smaller_isochrone_map] {
+            let selected = select(
+                square_isochrone_map(0.1, early),
+                square_isochrone_map(0.1, late),
+            );
+            assert_eq!(selected.departure_at(), early);
+            let selected = select(
+                square_isochrone_map(0.1, late),
+                square_isochrone_map(0.1, early),
+            );
+            assert_eq!(selected.departure_at(), early);
+        }
+
+        // The area still comes first.
+        let larger = larger_isochrone_map(
+            square_isochrone_map(0.1, early),
+            square_isochrone_map(0.2, other),
+        );
+        assert_eq!(larger.departure_at(), other);
+        let smaller = smaller_isochrone_map(
+            square_isochrone_map(0.2, other),
+            square_isochrone_map(0.1, late),
+        );
+        assert_eq!(smaller.departure_at(), late);
+    }
+
+    #[test]
+    fn test_walking_isochrone_is_a_circle() {
+        let time_limit = Duration::minutes(15);
+        let isochrone_map = compute_walking_isochrone(
+            46.20956654,
+            6.13536, // Genève
+            time_limit,
+            WALKING_SPEED_IN_KILOMETERS_PER_HOUR,
+        );
+
+        let radius = time_to_distance(time_limit, WALKING_SPEED_IN_KILOMETERS_PER_HOUR);
+        assert_eq!(isochrone_map.areas().len(), 1);
+        let expected = PI * radius * radius;
+        let diff = (isochrone_map.areas()[0] - expected).abs() / expected;
+        assert!(
+            diff < 0.01,
+            "area: {}, expected: {expected}",
+            isochrone_map.areas()[0]
+        );
+        let max_distance = isochrone_map.max_distances()[0].distance();
+        assert!(
+            (max_distance - radius).abs() < 0.01 * radius,
+            "{max_distance}"
+        );
+    }
+
+    #[test]
+    fn test_is_stop_in_countries() {
+        let switzerland_germany = [String::from("85"), String::from("80")];
+        assert!(is_stop_in_countries(8500010, &switzerland_germany));
+        assert!(is_stop_in_countries(8014482, &switzerland_germany));
+        assert!(!is_stop_in_countries(8774500, &switzerland_germany));
+    }
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_area_increases_with_german_stops_in_basel() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let excluded_polygons = MultiPolygon::new(vec![]);
+
+        let area = |countries: &[&str]| {
+            compute_isochrones(
+                &hrdf,
+                IsochroneArgs {
+                    latitude: 47.54741,
+                    longitude: 7.58956, // Basel SBB
+                    departure_at: create_date_time(2025, 6, 15, 12, 10),
+                    time_limit: Duration::minutes(30),
+                    interval: Duration::minutes(30),
+                    num_bands: None,
+                    max_num_explorable_connections: 5,
+                    num_starting_points: 5,
+                    countries: countries
+                        .iter()
+                        .map(|&country| String::from(country))
+                        .collect(),
+                    snap_to_interval: false,
+                    apply_excluded_polygons: true,
+                    verbose: false,
+                },
+                &IsochroneConfig::new(&excluded_polygons).with_num_threads(8),
+            )
+            .unwrap()
+            .compute_max_area()
+        };
+
+        let area_switzerland = area(&["85"]);
+        let area_switzerland_germany = area(&["85", "80"]);
+        assert!(
+            area_switzerland < area_switzerland_germany,
+            "Including the German stops should increase the area, but we have: {} >= {}",
+            area_switzerland,
+            area_switzerland_germany
+        );
+    }
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_time_limits_share_the_routes() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let excluded_polygons = MultiPolygon::new(vec![]);
+        let config = IsochroneConfig::new(&excluded_polygons).with_num_threads(8);
+        let isochrone_args = |time_limit| IsochroneArgs {
+            latitude: 46.20956654,
+            longitude: 6.13536000, // Genève, gare Cornavin
+            departure_at: create_date_time(2025, 6, 15, 12, 10),
+            time_limit,
+            interval: Duration::minutes(10),
+            num_bands: None,
+            max_num_explorable_connections: 5,
+            num_starting_points: 5,
+            countries: Vec::new(),
+            snap_to_interval: false,
+            apply_excluded_polygons: true,
+            verbose: false,
+        };
+        let time_limits = [Duration::minutes(20), Duration::minutes(10)];
+
+        let isochrone_maps = compute_isochrones_for_time_limits(
+            &hrdf,
+            isochrone_args(Duration::zero()),
+            &time_limits,
+            &config,
+        )
+        .unwrap();
+        assert_eq!(isochrone_maps.len(), time_limits.len());
+        for (isochrone_map, time_limit) in isochrone_maps.iter().zip(time_limits) {
+            let expected = compute_isochrones(&hrdf, isochrone_args(time_limit), &config).unwrap();
+            assert_eq!(isochrone_map.areas().len(), expected.areas().len());
+            for (area, expected) in isochrone_map.areas().iter().zip(expected.areas()) {
+                assert!(
+                    (area - expected).abs() <= 1e-6 * expected,
+                    "{area} != {expected}"
+                );
+            }
+            assert_eq!(isochrone_map.stops_reached(), expected.stops_reached());
+            assert_eq!(isochrone_map.lines_reached(), expected.lines_reached());
+        }
+        // More stops and lines are reached in 20 minutes than in 10.
+        assert!(isochrone_maps[0].stops_reached() > isochrone_maps[1].stops_reached());
+        assert!(isochrone_maps[0].lines_reached() >= isochrone_maps[1].lines_reached());
+        assert!(isochrone_maps[1].lines_reached() > Some(0));
+    }
+
+    #[cfg(feature = "hectare")]
+    #[test]
+    fn test_weighted_bands_population() {
+        // 100 people within 10 minutes, 150 more within 20 minutes, 50 more within 30 minutes.
+        let population_reached = [100, 250, 300];
+        assert_eq!(
+            weighted_bands_population(&population_reached, &[1.0, 0.5, 0.25]),
+            100.0 + 75.0 + 12.5
+        );
+        assert_eq!(
+            weighted_bands_population(&population_reached, &[1.0]),
+            100.0
+        );
+        assert_eq!(weighted_bands_population(&[], &[1.0, 0.5]), 0.0);
+    }
+
+    #[test]
+    fn test_departure_coordinates_are_not_swapped() {
+        // Genève, gare Cornavin
+        let (latitude, longitude) = (46.20956654, 6.13536);
+        let (wgs84, lv95) = departure_coordinates(latitude, longitude);
+
+        assert_eq!(wgs84.latitude(), Some(latitude));
+        assert_eq!(wgs84.longitude(), Some(longitude));
+        // In Switzerland the easting is around 2'600'000 and the northing around 1'200'000, a
+        // swap of latitude and longitude ends up far away from these ranges.
+        let (easting, northing) = (lv95.easting().unwrap(), lv95.northing().unwrap());
+        assert!((2_480_000.0..2_840_000.0).contains(&easting), "{easting}");
+        assert!((1_070_000.0..1_300_000.0).contains(&northing), "{northing}");
+    }
+
     #[test]
     fn test_polygon_area_bounds_single_point() {
         // For a single point with 30 min time limit and 5 km/h walking speed:
@@ -20,7 +20,8 @@ pub fn get_polygons(
     num_circle_points: usize,
     num_threads: usize,
 ) -> MultiPolygon {
-    data.par()
+    let circles = data
+        .par()
         .chunk_size(50)
         .num_threads(num_threads)
         .filter(|(_, duration)| prev_time_limit <= *duration && *duration <= time_limit)
@@ -28,26 +29,48 @@ pub fn get_polygons(
             let distance =
                 time_to_distance(time_limit - *duration, WALKING_SPEED_IN_KILOMETERS_PER_HOUR);
 
-            let polygon = generate_lv95_circle_points(
-                center_lv95.easting().expect("Wrong coordinate system"),
-                center_lv95.northing().expect("Wrong coordinate system"),
-                distance,
-                num_circle_points,
-            )
-            .into_iter()
-            .map(|lv95| {
-                let wgs84 = lv95_to_wgs84(
-                    lv95.easting().expect("Wrong coordinate system"),
-                    lv95.northing().expect("Wrong coordinate system"),
-                );
-                (wgs84.0, wgs84.1)
-            })
-            .collect::<Vec<_>>();
-            // Polygon::new(LineString::from(polygon), vec![])
-            MultiPolygon::new(vec![Polygon::new(LineString::from(polygon), vec![])])
+            get_circle(*center_lv95, distance, num_circle_points)
         })
-        .reduce(|lhs, rhs| lhs.union(&rhs))
-        .expect("Could not compute Polygon")
+        .collect::<Vec<_>>();
+    union_pairwise(circles, num_threads)
+}
+
+/// Returns the circle in wgs84 coordinates around an LV95 center, radius in meters.
+pub fn get_circle(center_lv95: Coordinates, radius: f64, num_circle_points: usize) -> MultiPolygon {
+    let polygon = generate_lv95_circle_points(
+        center_lv95.easting().expect("Wrong coordinate system"),
+        center_lv95.northing().expect("Wrong coordinate system"),
+        radius,
+        num_circle_points,
+    )
+    .into_iter()
+    .map(|lv95| {
+        let wgs84 = lv95_to_wgs84(
+            lv95.easting().expect("Wrong coordinate system"),
+            lv95.northing().expect("Wrong coordinate system"),
+        );
+        (wgs84.0, wgs84.1)
+    })
+    .collect::<Vec<_>>();
+    MultiPolygon::new(vec![Polygon::new(LineString::from(polygon), vec![])])
+}
+
+/// Unions the polygons two by two until a single one remains. Unlike a running union, each union
+/// then involves polygons of similar complexity.
+fn union_pairwise(mut polygons: Vec<MultiPolygon>, num_threads: usize) -> MultiPolygon {
+    while polygons.len() > 1 {
+        let pairs: Vec<_> = polygons.chunks(2).collect();
+        polygons = pairs
+            .par()
+            .num_threads(num_threads)
+            .map(|pair| match pair {
+                [lhs, rhs] => lhs.union(rhs),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    polygons.pop().unwrap_or_else(|| MultiPolygon::new(vec![]))
 }
 
 fn generate_lv95_circle_points(e: f64, n: f64, radius: f64, num_points: usize) -> Vec<Coordinates> {
@@ -1 +1,3 @@
 pub const WALKING_SPEED_IN_KILOMETERS_PER_HOUR: f64 = 4.0;
+/// Stops farther from a point are not walked to or from, about 22 minutes of walk.
+pub const DEFAULT_MAX_ACCESS_WALK_IN_METERS: f64 = 1500.0;
@@ -1,3 +1,5 @@
+use std::cell::{Cell, RefCell};
+
 use chrono::Duration;
 use contour::ContourBuilder;
 use geo::{BooleanOps, MapCoordsInPlace, MultiPolygon};
@@ -10,6 +12,28 @@ use super::{
     utils::{distance_between_2_points, distance_to_time, lv95_to_wgs84, time_to_distance},
 };
 
+/// The grids of more points are not kept for the next computation, so that a thread doesn't hold
+/// the memory of its largest isochrones forever.
+const MAX_CACHED_GRID_POINTS: usize = 250_000;
+
+/// The values thresholded by get_polygons and the contour builder of the grid dimensions.
+type ContourCache = (Vec<f64>, Option<(usize, usize, ContourBuilder)>);
+
+thread_local! {
+    /// The grid of the last isochrones computed on the thread, given back with recycle_grid, so
+    /// that the sweeps over the departure times don't reallocate it for each departure.
+    static GRID: Cell<Vec<(Coordinates, Duration)>> = const { Cell::new(Vec::new()) };
+    static CONTOURS: RefCell<ContourCache> = const { RefCell::new((Vec::new(), None)) };
+}
+
+/// Gives the grid returned by create_grid back for the next computation on the thread, unless it
+/// has more than MAX_CACHED_GRID_POINTS points.
+pub fn recycle_grid(grid: Vec<(Coordinates, Duration)>) {
+    if grid.capacity() <= MAX_CACHED_GRID_POINTS {
+        GRID.set(grid);
+    }
+}
+
 pub fn create_grid(
     data: &[(Coordinates, Duration)],
     bounding_box: ((f64, f64), (f64, f64)),
@@ -39,48 +63,44 @@ pub fn create_grid(
             .collect(),
     );
 
-    let grid = (0..num_points_y)
+    let mut grid = GRID.take();
+    grid.clear();
+    // The points are in rows, from the bottom left corner.
+    let grid = (0..num_points_x * num_points_y)
         .into_par()
         .num_threads(num_threads)
-        .flat_map(|y| {
-            let mut result = Vec::new();
-            let y = bounding_box.0.1 + dx * y as f64;
-
-            for x in 0..num_points_x {
-                let x = bounding_box.0.0 + dx * x as f64;
-
-                let coord = Coordinates::new(CoordinateSystem::LV95, x, y);
-
-                let points = tree.within_radius(
-                    &[
-                        coord.easting().expect("Wrong coordinate system"),
-                        coord.northing().expect("Wrong coordinate system"),
-                    ],
-                    time_to_distance(time_limit, WALKING_SPEED_IN_KILOMETERS_PER_HOUR),
-                );
-
-                if points.is_empty() {
-                    result.push((coord, time_limit * 2));
-                    continue;
-                }
-
-                let duration = points
-                    .iter()
-                    .map(|point| {
-                        let distance = distance_between_2_points(coord, point.coord());
-
-                        point.duration()
-                            + distance_to_time(distance, WALKING_SPEED_IN_KILOMETERS_PER_HOUR)
-                    })
-                    .min()
-                    .unwrap();
-
-                result.push((coord, duration));
+        .map(|i| {
+            let x = bounding_box.0.0 + dx * (i % num_points_x) as f64;
+            let y = bounding_box.0.1 + dx * (i / num_points_x) as f64;
+
+            let coord = Coordinates::new(CoordinateSystem::LV95, x, y);
+
+            let points = tree.within_radius(
+                &[
+                    coord.easting().expect("Wrong coordinate system"),
+                    coord.northing().expect("Wrong coordinate system"),
+                ],
+                time_to_distance(time_limit, WALKING_SPEED_IN_KILOMETERS_PER_HOUR),
+            );
+
+            if points.is_empty() {
+                return (coord, time_limit * 2);
             }
 
-            result
+            let duration = points
+                .iter()
+                .map(|point| {
+                    let distance = distance_between_2_points(coord, point.coord());
+
+                    point.duration()
+                        + distance_to_time(distance, WALKING_SPEED_IN_KILOMETERS_PER_HOUR)
+                })
+                .min()
+                .unwrap();
+
+            (coord, duration)
         })
-        .collect::<Vec<(Coordinates, Duration)>>();
+        .collect_into(grid);
 
     (grid, num_points_x, num_points_y, dx)
 }
@@ -93,17 +113,32 @@ pub fn get_polygons(
     time_limit: Duration,
     dx: f64,
 ) -> MultiPolygon {
-    let values: Vec<_> = grid
-        .iter()
-        .map(
+    let contours = CONTOURS.with_borrow_mut(|(values, contour_builder)| {
+        values.clear();
+        values.extend(grid.iter().map(
             |&(_, duration)| {
                 if duration <= time_limit { 1.0 } else { 0.0 }
             },
-        )
-        .collect();
-
-    let contour_builder = ContourBuilder::new(num_points_x, num_points_y, true);
-    let contours = contour_builder.contours(&values, &[0.5]).unwrap();
+        ));
+
+        let dimensions = (num_points_x, num_points_y);
+        if contour_builder
+            .as_ref()
+            .is_none_or(|&(nx, ny, _)| (nx, ny) != dimensions)
+        {
+            *contour_builder = Some((
+                num_points_x,
+                num_points_y,
+                ContourBuilder::new(num_points_x, num_points_y, true),
+            ));
+        }
+        let (_, _, contour_builder) = contour_builder.as_ref().unwrap();
+        let contours = contour_builder.contours(values, &[0.5]).unwrap();
+        if values.capacity() > MAX_CACHED_GRID_POINTS {
+            *values = Vec::new();
+        }
+        contours
+    });
 
     contours
         .into_iter()
@@ -145,3 +180,21 @@ impl MyPoint {
         self.duration
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use test_log::test;
+
+    use super::*;
+
+    #[test]
+    fn test_recycle_grid_drops_large_grids() {
+        let point = (Coordinates::default(), Duration::zero());
+
+        recycle_grid(vec![point; 10]);
+        assert!(GRID.take().capacity() >= 10);
+
+        recycle_grid(vec![point; MAX_CACHED_GRID_POINTS + 1]);
+        assert_eq!(GRID.take().capacity(), 0);
+    }
+}
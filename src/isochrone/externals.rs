@@ -205,9 +205,8 @@ impl HectareData {
             let compressed_data_path = if Url::parse(url_or_path).is_ok() {
                 let compressed_data_path = env::temp_dir()
                     .join(format!("{unique_filename}.zip"))
-                    .into_os_string()
-                    .into_string()
-                    .expect("Could not convert to string.");
+                    .to_string_lossy()
+                    .to_string();
 
                 if !Path::new(&compressed_data_path).exists() {
                     // The data must be downloaded.
@@ -225,9 +224,8 @@ impl HectareData {
 
             let decompressed_data_path = env::temp_dir()
                 .join(unique_filename)
-                .into_os_string()
-                .into_string()
-                .expect("Could not convert to string.");
+                .to_string_lossy()
+                .to_string();
 
             if !Path::new(&decompressed_data_path).exists() {
                 // The data must be decompressed.
@@ -282,6 +280,10 @@ impl HectareData {
         self.data
     }
 
+    pub fn records(&self) -> &[HectareRecord] {
+        &self.data
+    }
+
     fn build_cache(&self, path: &str) -> RResult<()> {
         let bytes = postcard::to_stdvec(self)?;
         fs::write(path, bytes)?;
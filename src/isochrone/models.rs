@@ -1,30 +1,184 @@
-use chrono::NaiveDateTime;
-use geo::{Area, Contains, MultiPolygon};
+use chrono::{Duration, NaiveDateTime};
+use geo::{Area, BooleanOps, Contains, LineString, MultiPolygon, Validation};
 use hrdf_parser::Coordinates;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use strum_macros::EnumString;
+use utoipa::ToSchema;
 
-#[cfg(feature = "svg")]
+#[cfg(any(feature = "svg", feature = "hectare"))]
 use geo::BoundingRect;
-use std::fmt::Display;
+#[cfg(feature = "hectare")]
+use geo::Point;
+#[cfg(feature = "png")]
+use std::ops::RangeInclusive;
+use std::{fmt::Display, fs, path::Path};
 #[cfg(feature = "svg")]
 use svg::Document;
 #[cfg(feature = "svg")]
 use svg::node::element::Polygon as SvgPolygon;
 
+#[cfg(feature = "hectare")]
+use super::externals::{HectareData, HectareRecord};
+use crate::{RResult, error::RError};
+
 #[cfg(feature = "svg")]
-use crate::RResult;
+use super::utils::multi_polygon_to_web_mercator;
+use super::utils::{
+    bearing, lv95_to_wgs84, multi_polygon_to_lv95, wgs84_to_lv95, wgs84_to_web_mercator,
+};
 
-use super::utils::{multi_polygon_to_lv95, wgs84_to_lv95};
+/// Version of the serialized IsochroneMap, bumped whenever its shape changes. The README lists
+/// the fields of each version.
+pub const ISOCHRONE_MAP_FORMAT_VERSION: u32 = 1;
 
-#[derive(Debug, Serialize, Default)]
+/// The isochrones computed from a departure point.
+/// The positions are WGS84 (latitude, longitude), the areas and distances are computed in LV95
+/// coordinates. The serialized names of the fields having a unit give it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct IsochroneMap {
+    /// ISOCHRONE_MAP_FORMAT_VERSION when serialized by this version, so that the clients can tell
+    /// the shapes apart.
+    format_version: u32,
     isochrones: Vec<Isochrone>,
+    /// In square meters.
+    #[serde(rename = "areas_m2")]
     areas: Vec<f64>,
-    max_distances: Vec<((f64, f64), f64)>,
+    areas_km2: Vec<f64>,
+    /// The number of inhabitants within each isochrone, only known when hectare data is supplied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    population_reached: Option<Vec<u64>>,
+    /// The number of distinct stops and lines reached within the time limit, not known for the
+    /// average isochrones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stops_reached: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines_reached: Option<usize>,
+    max_distances: Vec<MaxDistance>,
+    /// WGS84 coordinates of the departure point.
+    #[schema(value_type = Object)]
     departure_stop_coord: Coordinates,
     departure_at: NaiveDateTime,
+    /// WGS84 (latitude, longitude) of the south-west and north-east corners.
+    #[serde(rename = "bounding_box_wgs84")]
+    #[schema(value_type = Vec<Vec<f64>>)]
     bounding_box: ((f64, f64), (f64, f64)),
+    /// The part of the largest isochrone which cannot be reached by walking only, when computed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transit_contribution: Option<Isochrone>,
+    #[serde(
+        rename = "transit_contribution_area_m2",
+        skip_serializing_if = "Option::is_none"
+    )]
+    transit_contribution_area: Option<f64>,
+}
+
+/// The problems found by IsochroneMap::validate, none for a well-formed map. The bands are given
+/// by their index in the isochrones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ValidationReport {
+    /// The bands whose polygons are invalid (e.g. self-intersecting), with the reason.
+    pub invalid_bands: Vec<(usize, String)>,
+    /// The bands not contained in the next one.
+    pub non_nested_bands: Vec<usize>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.invalid_bands.is_empty() && self.non_nested_bands.is_empty()
+    }
+}
+
+/// Why a stop within the bounding box of the isochrones was not reached. The reason is the first
+/// one found, in the order of the variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnreachedReason {
+    /// No journey serves the stop on the departure date.
+    NoService,
+    /// The journeys cannot be changed at the stop, the routing only reaches it by staying on a
+    /// journey passing through it, and none comes from a reached stop.
+    NotAnExchangePoint,
+    /// The stop is served, but not reached within the time limit.
+    ExceededTimeLimit,
+}
+
+/// Whether a stop close to the origin was reached, see compute_isochrones_with_diagnostics.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StopDiagnostic {
+    pub stop_id: i32,
+    pub name: String,
+    pub reached: bool,
+    /// None when the stop was reached.
+    pub reason: Option<UnreachedReason>,
+}
+
+/// The stops within the bounding box of the isochrones, sorted by id.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Diagnostics {
+    pub stops: Vec<StopDiagnostic>,
+}
+
+impl Diagnostics {
+    pub fn unreached(&self) -> impl Iterator<Item = &StopDiagnostic> {
+        self.stops.iter().filter(|stop| !stop.reached)
+    }
+
+    /// The number of stops not reached for the reason.
+    pub fn count(&self, reason: UnreachedReason) -> usize {
+        self.unreached()
+            .filter(|stop| stop.reason == Some(reason))
+            .count()
+    }
+}
+
+/// The point of an isochrone the farthest from the departure point.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MaxDistance {
+    /// WGS84 (latitude, longitude).
+    #[serde(rename = "point_wgs84")]
+    #[schema(value_type = Vec<f64>)]
+    point: (f64, f64),
+    /// Straight-line distance from the departure point in meters.
+    #[serde(rename = "distance_meters")]
+    distance: f64,
+    /// Direction of the point seen from the departure point, in degrees clockwise from the north.
+    #[serde(rename = "bearing_degrees")]
+    bearing: Option<f64>,
+}
+
+impl MaxDistance {
+    pub fn point(&self) -> (f64, f64) {
+        self.point
+    }
+
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+
+    pub fn bearing(&self) -> Option<f64> {
+        self.bearing
+    }
+}
+
+impl Default for IsochroneMap {
+    fn default() -> Self {
+        Self {
+            format_version: ISOCHRONE_MAP_FORMAT_VERSION,
+            isochrones: Vec::new(),
+            areas: Vec::new(),
+            areas_km2: Vec::new(),
+            population_reached: None,
+            stops_reached: None,
+            lines_reached: None,
+            max_distances: Vec::new(),
+            departure_stop_coord: Coordinates::default(),
+            departure_at: NaiveDateTime::default(),
+            bounding_box: Default::default(),
+            transit_contribution: None,
+            transit_contribution_area: None,
+        }
+    }
 }
 
 impl IsochroneMap {
@@ -36,16 +190,130 @@ impl IsochroneMap {
         departure_at: NaiveDateTime,
         bounding_box: ((f64, f64), (f64, f64)),
     ) -> Self {
+        let areas_km2 = areas.iter().map(|area| area / 1e6).collect();
+        let departure = departure_stop_coord
+            .latitude()
+            .zip(departure_stop_coord.longitude());
+        let max_distances = max_distances
+            .into_iter()
+            .map(|(point, distance)| MaxDistance {
+                point,
+                distance,
+                bearing: departure
+                    .map(|(latitude, longitude)| bearing(latitude, longitude, point.0, point.1)),
+            })
+            .collect();
         Self {
+            format_version: ISOCHRONE_MAP_FORMAT_VERSION,
             isochrones,
             areas,
+            areas_km2,
+            population_reached: None,
+            stops_reached: None,
+            lines_reached: None,
             max_distances,
             departure_stop_coord,
             departure_at,
             bounding_box,
+            transit_contribution: None,
+            transit_contribution_area: None,
         }
     }
 
+    /// Adds the area reachable only thanks to the public transport: the largest isochrone minus
+    /// the walking isochrone of the same origin and time limit (see compute_walking_isochrone).
+    pub fn with_transit_contribution(mut self, walking_isochrone_map: &IsochroneMap) -> Self {
+        if let (Some(largest), Some(walking)) = (
+            self.isochrones.last(),
+            walking_isochrone_map.isochrones.last(),
+        ) {
+            let isochrone = Isochrone {
+                polygons: largest.polygons().difference(walking.polygons()),
+                time_limit: largest.time_limit,
+                time_limit_seconds: largest.time_limit_seconds,
+            };
+            self.transit_contribution_area = Some(isochrone.compute_area());
+            self.transit_contribution = Some(isochrone);
+        }
+        self
+    }
+
+    /// In square meters (LV95), None when the transit contribution was not computed.
+    pub fn transit_contribution_area(&self) -> Option<f64> {
+        self.transit_contribution_area
+    }
+
+    /// Computes the number of inhabitants within each isochrone from the population per hectare.
+    #[cfg(feature = "hectare")]
+    pub fn with_population(mut self, hectare: &HectareData) -> Self {
+        self.population_reached = Some(self.compute_population(hectare.records()));
+        self
+    }
+
+    pub(crate) fn with_reached(mut self, stops_reached: usize, lines_reached: usize) -> Self {
+        self.stops_reached = Some(stops_reached);
+        self.lines_reached = Some(lines_reached);
+        self
+    }
+
+    /// The version of the shape the map was serialized with, see ISOCHRONE_MAP_FORMAT_VERSION.
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+
+    /// WGS84 (latitude, longitude) of the south-west and north-east corners.
+    pub fn bounding_box(&self) -> ((f64, f64), (f64, f64)) {
+        self.bounding_box
+    }
+
+    pub fn stops_reached(&self) -> Option<usize> {
+        self.stops_reached
+    }
+
+    pub fn lines_reached(&self) -> Option<usize> {
+        self.lines_reached
+    }
+
+    /// The area of each isochrone in square meters (LV95).
+    pub fn areas(&self) -> &[f64] {
+        &self.areas
+    }
+
+    /// The area of each isochrone in square kilometers.
+    pub fn areas_km2(&self) -> &[f64] {
+        &self.areas_km2
+    }
+
+    pub fn population_reached(&self) -> Option<&[u64]> {
+        self.population_reached.as_deref()
+    }
+
+    pub fn max_distances(&self) -> &[MaxDistance] {
+        &self.max_distances
+    }
+
+    #[cfg(feature = "hectare")]
+    pub(crate) fn compute_population(&self, records: &[HectareRecord]) -> Vec<u64> {
+        self.isochrones
+            .iter()
+            .map(|isochrone| {
+                let polygons = isochrone.polygons();
+                let Some(bounding_rect) = polygons.bounding_rect() else {
+                    return 0;
+                };
+                records
+                    .iter()
+                    .filter(|record| {
+                        // The polygons are stored as (latitude, longitude).
+                        let point = Point::new(record.latitude, record.longitude);
+                        bounding_rect.contains(&point) && polygons.contains(&point)
+                    })
+                    .map(|record| record.population)
+                    .sum()
+            })
+            .collect()
+    }
+
     pub fn compute_areas(&self) -> Vec<f64> {
         self.isochrones.iter().map(|i| i.compute_area()).collect()
     }
@@ -100,12 +368,240 @@ impl IsochroneMap {
         polygons
     }
 
+    /// Checks that the polygons of each band are valid and that each band is contained in the
+    /// next one, as get_polygons assumes. An empty band is contained in any band.
+    pub fn validate(&self) -> ValidationReport {
+        let invalid_bands = self
+            .isochrones
+            .iter()
+            .enumerate()
+            .filter_map(|(i, isochrone)| {
+                isochrone
+                    .polygons()
+                    .check_validation()
+                    .err()
+                    .map(|error| (i, error.to_string()))
+            })
+            .collect();
+        let non_nested_bands = self
+            .isochrones
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| {
+                !pair[0].polygons().0.is_empty() && !pair[1].polygons().contains(pair[0].polygons())
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        ValidationReport {
+            invalid_bands,
+            non_nested_bands,
+        }
+    }
+
+    /// Logs a warning when the map is malformed. The check is costly, it only runs in the debug
+    /// builds.
+    pub(crate) fn debug_validate(self) -> Self {
+        if cfg!(debug_assertions) {
+            let report = self.validate();
+            if !report.is_valid() {
+                log::warn!("Malformed isochrones: {report:?}");
+            }
+        }
+        self
+    }
+
     pub fn departure_at(&self) -> NaiveDateTime {
         self.departure_at
     }
 
+    /// Writes the isochrone map to path in the given format. The svg and png formats are only
+    /// available with the corresponding features.
+    pub fn write(
+        &self,
+        path: &Path,
+        format: OutputFormat,
+        scale_factor: f64,
+        c: Option<Coordinates>,
+    ) -> RResult<()> {
+        match format {
+            #[cfg(feature = "svg")]
+            OutputFormat::Svg => {
+                self.write_svg(&path.to_string_lossy(), scale_factor, c, Projection::Lv95)
+            }
+            #[cfg(feature = "png")]
+            OutputFormat::Png => self.write_png(path, scale_factor, c),
+            OutputFormat::GeoJson => self.write_geojson(path),
+            OutputFormat::Json => self.write_json(path),
+            #[allow(unreachable_patterns)]
+            _ => Err(RError::FormatNotEnabled(format.to_string())),
+        }
+    }
+
+    /// Writes the full isochrone map (polygons, areas, max distances, ...) as JSON.
+    pub fn write_json(&self, path: &Path) -> RResult<()> {
+        let data = serde_json::to_string_pretty(self)?;
+        fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// Converts the isochrones to a GeoJSON FeatureCollection with one MultiPolygon feature per
+    /// isochrone. The coordinates are given as (longitude, latitude) as mandated by GeoJSON.
+    /// The transit contribution, when computed, is an additional feature whose kind property is
+    /// "transit_contribution".
+    pub fn to_geojson(&self) -> serde_json::Value {
+        let mut features = self
+            .isochrones
+            .iter()
+            .zip(self.areas.iter().copied())
+            .chain(
+                self.transit_contribution
+                    .iter()
+                    .zip(self.transit_contribution_area),
+            )
+            .map(|(isochrone, area)| {
+                // The polygons are stored as (latitude, longitude).
+                let to_ring =
+                    |ring: &LineString| ring.coords().map(|c| [c.y, c.x]).collect::<Vec<_>>();
+                let coordinates = isochrone
+                    .polygons()
+                    .iter()
+                    .map(|p| {
+                        std::iter::once(p.exterior())
+                            .chain(p.interiors())
+                            .map(to_ring)
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+
+                json!({
+                    "type": "Feature",
+                    "properties": {
+                        "time_limit": isochrone.time_limit,
+                        "time_limit_seconds": isochrone.time_limit_seconds,
+                        "area": area,
+                    },
+                    "geometry": {
+                        "type": "MultiPolygon",
+                        "coordinates": coordinates,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        if self.transit_contribution.is_some() {
+            features.last_mut().unwrap()["properties"]["kind"] = json!("transit_contribution");
+        }
+
+        json!({
+            "type": "FeatureCollection",
+            "features": features,
+        })
+    }
+
+    /// Writes the GeoJSON representation of the isochrones to a file.
+    pub fn write_geojson(&self, path: &Path) -> RResult<()> {
+        fs::write(path, serde_json::to_string_pretty(&self.to_geojson())?)?;
+        Ok(())
+    }
+
+    /// Renders the svg representation of the isochrone map to a png image.
+    #[cfg(feature = "png")]
+    pub fn write_png(&self, path: &Path, scale_factor: f64, c: Option<Coordinates>) -> RResult<()> {
+        use resvg::{tiny_skia, usvg};
+
+        let document = self.svg_document(scale_factor, c, Projection::Lv95)?;
+        let tree = usvg::Tree::from_str(&document.to_string(), &usvg::Options::default())
+            .map_err(|e| RError::PngError(e.to_string()))?;
+        let size = tree.size().to_int_size();
+        let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+            .ok_or_else(|| RError::PngError(format!("invalid image size {size:?}")))?;
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+        pixmap
+            .save_png(path)
+            .map_err(|e| RError::PngError(e.to_string()))
+    }
+
+    /// Rasterizes the isochrones into the XYZ (slippy-map) PNG tiles of the zoom levels covering
+    /// them, written as dir/z/x/y.png. Returns the number of tiles written.
+    /// The zoom levels go up to MAX_TILE_ZOOM.
+    #[cfg(feature = "png")]
+    pub fn write_tiles(&self, dir: &Path, zooms: RangeInclusive<u8>) -> RResult<usize> {
+        use resvg::{tiny_skia, usvg};
+
+        if *zooms.end() > MAX_TILE_ZOOM {
+            return Err(RError::InvalidZoom(*zooms.end()));
+        }
+
+        // The svg document is in Web Mercator meters, its origin is the top left corner of the
+        // bounding rectangle of the isochrones.
+        let document = self.svg_document(1.0, None, Projection::WebMercator)?;
+        let tree = usvg::Tree::from_str(&document.to_string(), &usvg::Options::default())
+            .map_err(|e| RError::PngError(e.to_string()))?;
+        let bounding_rect = self
+            .get_polygons()
+            .last()
+            .map(multi_polygon_to_web_mercator)
+            .and_then(|m| m.bounding_rect())
+            .ok_or_else(|| RError::NoBoundingRect)?;
+        let (min_x, min_y) = bounding_rect.min().x_y();
+        let (max_x, max_y) = bounding_rect.max().x_y();
+
+        let mut num_tiles = 0;
+        for zoom in zooms {
+            let resolution = WEB_MERCATOR_EXTENT / f64::from(TILE_SIZE) / 2f64.powi(zoom.into());
+            let (xs, ys) = tile_range((min_x, min_y), (max_x, max_y), zoom);
+            for x in xs {
+                let tile_dir = dir.join(zoom.to_string()).join(x.to_string());
+                fs::create_dir_all(&tile_dir)?;
+                for y in ys.clone() {
+                    // Top left corner of the tile.
+                    let left = f64::from(x) * f64::from(TILE_SIZE) * resolution
+                        - WEB_MERCATOR_EXTENT / 2.0;
+                    let top = WEB_MERCATOR_EXTENT / 2.0
+                        - f64::from(y) * f64::from(TILE_SIZE) * resolution;
+                    let transform = tiny_skia::Transform::from_row(
+                        (1.0 / resolution) as f32,
+                        0.0,
+                        0.0,
+                        (1.0 / resolution) as f32,
+                        ((min_x - left) / resolution) as f32,
+                        ((top - max_y) / resolution) as f32,
+                    );
+                    let mut pixmap = tiny_skia::Pixmap::new(TILE_SIZE, TILE_SIZE)
+                        .ok_or_else(|| RError::PngError(String::from("invalid tile size")))?;
+                    resvg::render(&tree, transform, &mut pixmap.as_mut());
+                    pixmap
+                        .save_png(tile_dir.join(format!("{y}.png")))
+                        .map_err(|e| RError::PngError(e.to_string()))?;
+                    num_tiles += 1;
+                }
+            }
+        }
+        Ok(num_tiles)
+    }
+
+    /// Writes the isochrones as svg, in the coordinates of the projection. With
+    /// `Projection::WebMercator`, the svg can be overlaid on slippy-map tiles as is.
+    #[cfg(feature = "svg")]
+    pub fn write_svg(
+        &self,
+        path: &str,
+        scale_factor: f64,
+        c: Option<Coordinates>,
+        projection: Projection,
+    ) -> RResult<()> {
+        let document = self.svg_document(scale_factor, c, projection)?;
+        svg::save(path, &document)?;
+        Ok(())
+    }
+
     #[cfg(feature = "svg")]
-    pub fn write_svg(&self, path: &str, scale_factor: f64, c: Option<Coordinates>) -> RResult<()> {
+    fn svg_document(
+        &self,
+        scale_factor: f64,
+        c: Option<Coordinates>,
+        projection: Projection,
+    ) -> RResult<Document> {
         const HEXES: [&str; 6] = [
             "#36AB68", // Nearest.
             "#91CF60", //
@@ -116,12 +612,13 @@ impl IsochroneMap {
         ];
         use svg::node::element::Line;
 
-        use crate::error::RError;
-
         let polys = self
             .get_polygons()
             .into_iter()
-            .map(|m| multi_polygon_to_lv95(&m))
+            .map(|m| match projection {
+                Projection::Lv95 => multi_polygon_to_lv95(&m),
+                Projection::WebMercator => multi_polygon_to_web_mercator(&m),
+            })
             .collect::<Vec<_>>();
         let areas = self.compute_areas();
         let max_distances = if let Some(coord) = c {
@@ -186,15 +683,15 @@ impl IsochroneMap {
             .fold(document, |mut doc, dist| {
                 if let Some(coord) = c {
                     if let Some(((x, y), _)) = dist {
+                        let (x, y) = projection.project_lv95(x, y);
+                        let (x_c, y_c) = projection
+                            .project_lv95(coord.easting().unwrap(), coord.northing().unwrap());
                         doc = doc.add(
                             Line::new()
                                 .set("x1", x * scale_factor)
                                 .set("y1", (min_y + (max_y - y)) * scale_factor)
-                                .set("x2", coord.easting().unwrap() * scale_factor)
-                                .set(
-                                    "y2",
-                                    (min_y + (max_y - coord.northing().unwrap())) * scale_factor,
-                                )
+                                .set("x2", x_c * scale_factor)
+                                .set("y2", (min_y + (max_y - y_c)) * scale_factor)
                                 .set("stroke", "black"),
                         );
                         doc
@@ -205,15 +702,44 @@ impl IsochroneMap {
                     doc
                 }
             });
-        svg::save(path, &document)?;
-        Ok(())
+        Ok(document)
     }
 }
 
-#[derive(Debug, Serialize)]
+/// Size of the XYZ tiles in pixels.
+#[cfg(feature = "png")]
+const TILE_SIZE: u32 = 256;
+/// Highest zoom level of the XYZ tiles, as in the usual slippy maps.
+#[cfg(feature = "png")]
+const MAX_TILE_ZOOM: u8 = 22;
+/// Width and height of the Web Mercator plane in meters.
+#[cfg(feature = "png")]
+const WEB_MERCATOR_EXTENT: f64 = 2.0 * std::f64::consts::PI * 6378137.0;
+
+/// The x and y of the tiles of the zoom level covering the Web Mercator rectangle.
+#[cfg(feature = "png")]
+fn tile_range(
+    (min_x, min_y): (f64, f64),
+    (max_x, max_y): (f64, f64),
+    zoom: u8,
+) -> (RangeInclusive<u32>, RangeInclusive<u32>) {
+    let num_tiles = 1u32 << zoom;
+    let tile = |value: f64| {
+        ((value / WEB_MERCATOR_EXTENT * f64::from(num_tiles)).floor() as u32).min(num_tiles - 1)
+    };
+    // The tiles are counted from the top left corner of the plane.
+    let x = |x: f64| tile(x + WEB_MERCATOR_EXTENT / 2.0);
+    let y = |y: f64| tile(WEB_MERCATOR_EXTENT / 2.0 - y);
+    (x(min_x)..=x(max_x), y(max_y)..=y(min_y))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Isochrone {
+    /// WGS84, the latitude as x and the longitude as y.
+    #[schema(value_type = Object)]
     polygons: MultiPolygon,
-    time_limit: u32, // In minutes.
+    time_limit: u32, // In minutes, rounded down for intervals given in seconds.
+    time_limit_seconds: u32,
 }
 
 impl Isochrone {
@@ -221,6 +747,16 @@ impl Isochrone {
         Self {
             polygons,
             time_limit,
+            time_limit_seconds: time_limit * 60,
+        }
+    }
+
+    /// Same as new, the time limit can be a number of seconds.
+    pub fn from_time_limit(polygons: MultiPolygon, time_limit: Duration) -> Self {
+        Self {
+            polygons,
+            time_limit: time_limit.num_minutes() as u32,
+            time_limit_seconds: time_limit.num_seconds() as u32,
         }
     }
 
@@ -230,11 +766,13 @@ impl Isochrone {
         &self.polygons
     }
 
+    /// Computes the area in square meters (LV95). The isochrone polygons don't overlap (they
+    /// result from the difference with the excluded polygons) and the area of their holes is
+    /// subtracted, so nothing is counted twice.
+    /// The isochrones are cumulative: the area of the band between two isochrones is the
+    /// difference of their areas.
     pub fn compute_area(&self) -> f64 {
-        multi_polygon_to_lv95(self.polygons())
-            .iter()
-            .map(|p| p.unsigned_area())
-            .sum()
+        multi_polygon_to_lv95(self.polygons()).unsigned_area()
     }
 
     /// Computes the max distance from all the points in the isochrone to the c Coord.
@@ -276,3 +814,399 @@ impl Display for DisplayMode {
         }
     }
 }
+
+#[derive(Debug, EnumString, PartialEq, Clone, Copy)]
+pub enum OutputFormat {
+    #[strum(serialize = "svg")]
+    Svg,
+    #[strum(serialize = "geojson")]
+    GeoJson,
+    #[strum(serialize = "png")]
+    Png,
+    #[strum(serialize = "json")]
+    Json,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Svg => "svg",
+            Self::GeoJson => "geojson",
+            Self::Png => "png",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// The coordinate system of the svg output.
+#[derive(Debug, EnumString, PartialEq, Clone, Copy, Default)]
+pub enum Projection {
+    /// Swiss LV95 (EPSG:2056), in meters.
+    #[default]
+    #[strum(serialize = "lv95")]
+    Lv95,
+    /// Web Mercator (EPSG:3857), in meters, the projection of the slippy-map tiles.
+    #[strum(serialize = "web_mercator")]
+    WebMercator,
+}
+
+impl Projection {
+    /// Projects LV95 coordinates.
+    pub fn project_lv95(&self, easting: f64, northing: f64) -> (f64, f64) {
+        match self {
+            Self::Lv95 => (easting, northing),
+            Self::WebMercator => {
+                let (latitude, longitude) = lv95_to_wgs84(easting, northing);
+                wgs84_to_web_mercator(latitude, longitude)
+            }
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, str::FromStr};
+
+    use chrono::NaiveDate;
+    use geo::Polygon;
+    use hrdf_parser::CoordinateSystem;
+
+    use super::*;
+
+    fn isochrone_map() -> IsochroneMap {
+        // (latitude, longitude) as stored by the isochrones.
+        let polygon = Polygon::new(
+            LineString::from(vec![(46.0, 6.0), (46.0, 6.1), (46.1, 6.1), (46.0, 6.0)]),
+            vec![],
+        );
+        let isochrone = Isochrone::new(MultiPolygon::new(vec![polygon]), 10);
+        let area = isochrone.compute_area();
+        IsochroneMap::new(
+            vec![isochrone],
+            vec![area],
+            vec![((46.1, 6.1), 1500.0)],
+            Coordinates::new(CoordinateSystem::WGS84, 46.0, 6.0),
+            NaiveDate::from_ymd_opt(2025, 4, 10)
+                .unwrap()
+                .and_hms_opt(15, 36, 0)
+                .unwrap(),
+            ((46.0, 6.0), (46.1, 6.1)),
+        )
+    }
+
+    fn square(latitude: f64, longitude: f64, size: f64) -> LineString {
+        LineString::from(vec![
+            (latitude, longitude),
+            (latitude + size, longitude),
+            (latitude + size, longitude + size),
+            (latitude, longitude + size),
+            (latitude, longitude),
+        ])
+    }
+
+    #[test]
+    fn test_ring_shaped_isochrone_area() {
+        let outer = Isochrone::new(
+            MultiPolygon::new(vec![Polygon::new(square(46.0, 6.0, 0.1), vec![])]),
+            20,
+        );
+        let hole = Isochrone::new(
+            MultiPolygon::new(vec![Polygon::new(square(46.03, 6.03, 0.04), vec![])]),
+            10,
+        );
+        let ring = Isochrone::new(
+            MultiPolygon::new(vec![Polygon::new(
+                square(46.0, 6.0, 0.1),
+                vec![square(46.03, 6.03, 0.04)],
+            )]),
+            20,
+        );
+
+        let expected = outer.compute_area() - hole.compute_area();
+        assert!(hole.compute_area() > 0.0);
+        assert!((ring.compute_area() - expected).abs() < 1e-6 * expected);
+    }
+
+    #[test]
+    fn test_nested_bands_do_not_change_areas() {
+        let inner = Isochrone::new(
+            MultiPolygon::new(vec![Polygon::new(square(46.03, 6.03, 0.04), vec![])]),
+            10,
+        );
+        let outer = Isochrone::new(
+            MultiPolygon::new(vec![Polygon::new(square(46.0, 6.0, 0.1), vec![])]),
+            20,
+        );
+        let areas = vec![inner.compute_area(), outer.compute_area()];
+        let isochrone_map = IsochroneMap::new(
+            vec![inner, outer],
+            areas.clone(),
+            vec![],
+            Coordinates::new(CoordinateSystem::WGS84, 46.05, 6.05),
+            NaiveDateTime::default(),
+            ((46.0, 6.0), (46.1, 6.1)),
+        );
+
+        // The bands used for the display have the inner isochrone as hole.
+        let bands = isochrone_map.get_polygons();
+        assert_eq!(bands[1].0[0].interiors().len(), 1);
+        assert_eq!(isochrone_map.compute_areas(), areas);
+        assert_eq!(isochrone_map.compute_max_area(), areas[1]);
+    }
+
+    #[test]
+    fn test_validate() {
+        let band = |polygon: Polygon, time_limit: u32| {
+            Isochrone::new(MultiPolygon::new(vec![polygon]), time_limit)
+        };
+        let isochrone_map = |isochrones: Vec<Isochrone>| {
+            IsochroneMap::new(
+                isochrones,
+                vec![],
+                vec![],
+                Coordinates::new(CoordinateSystem::WGS84, 46.05, 6.05),
+                NaiveDateTime::default(),
+                ((46.0, 6.0), (46.1, 6.1)),
+            )
+        };
+
+        let nested = isochrone_map(vec![
+            band(Polygon::new(square(46.03, 6.03, 0.04), vec![]), 10),
+            band(Polygon::new(square(46.0, 6.0, 0.1), vec![]), 20),
+        ]);
+        assert!(nested.validate().is_valid());
+
+        let bow_tie = LineString::from(vec![
+            (46.0, 6.0),
+            (46.1, 6.1),
+            (46.1, 6.0),
+            (46.0, 6.1),
+            (46.0, 6.0),
+        ]);
+        let malformed = isochrone_map(vec![
+            band(Polygon::new(square(46.0, 6.0, 0.1), vec![]), 10),
+            band(Polygon::new(square(46.03, 6.03, 0.04), vec![]), 20),
+            band(Polygon::new(bow_tie, vec![]), 30),
+        ]);
+        let report = malformed.validate();
+        assert_eq!(
+            report
+                .invalid_bands
+                .iter()
+                .map(|(i, _)| *i)
+                .collect::<Vec<_>>(),
+            [2]
+        );
+        assert!(report.non_nested_bands.contains(&0));
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let json = serde_json::to_value(isochrone_map()).unwrap();
+        assert_eq!(json["format_version"], ISOCHRONE_MAP_FORMAT_VERSION);
+        assert_eq!(json["bounding_box_wgs84"][1][0], 46.1);
+        assert_eq!(json["max_distances"][0]["point_wgs84"][0], 46.1);
+        assert_eq!(json["max_distances"][0]["distance_meters"], 1500.0);
+        let bearing = json["max_distances"][0]["bearing_degrees"]
+            .as_f64()
+            .unwrap();
+        // The point is north-east of the departure point.
+        assert!((0.0..90.0).contains(&bearing));
+
+        let isochrone_map: IsochroneMap = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(isochrone_map.format_version(), ISOCHRONE_MAP_FORMAT_VERSION);
+        assert_eq!(isochrone_map.max_distances()[0].point(), (46.1, 6.1));
+        assert_eq!(isochrone_map.max_distances()[0].distance(), 1500.0);
+        assert_eq!(isochrone_map.max_distances()[0].bearing(), Some(bearing));
+        assert_eq!(serde_json::to_value(&isochrone_map).unwrap(), json);
+    }
+
+    #[test]
+    fn test_areas_km2() {
+        let isochrone_map = isochrone_map();
+        assert_eq!(isochrone_map.areas_km2().len(), 1);
+        assert!((isochrone_map.areas_km2()[0] * 1e6 - isochrone_map.areas()[0]).abs() < 1e-6);
+        assert_eq!(isochrone_map.population_reached(), None);
+    }
+
+    #[cfg(feature = "hectare")]
+    #[test]
+    fn test_compute_population() {
+        let record = |latitude, longitude, population| HectareRecord {
+            reli: 0,
+            longitude,
+            latitude,
+            population,
+            area: None,
+        };
+        let records = vec![
+            record(46.05, 6.09, 100), // Inside.
+            record(46.01, 6.09, 20),  // Inside.
+            record(46.09, 6.01, 3),   // Outside, in the bounding rectangle.
+            record(47.0, 7.0, 4000),  // Far away.
+        ];
+        assert_eq!(isochrone_map().compute_population(&records), vec![120]);
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!(
+            OutputFormat::from_str("geojson").unwrap(),
+            OutputFormat::GeoJson
+        );
+        assert_eq!(OutputFormat::from_str("json").unwrap(), OutputFormat::Json);
+        assert!(OutputFormat::from_str("pdf").is_err());
+        assert_eq!(OutputFormat::Png.to_string(), "png");
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_svg_document_projection() {
+        let svg_view_box = |projection| {
+            let document = isochrone_map()
+                .svg_document(1.0, None, projection)
+                .unwrap()
+                .to_string();
+            let start = document.find("viewBox=\"").unwrap() + "viewBox=\"".len();
+            let end = start + document[start..].find('"').unwrap();
+            document[start..end]
+                .split(' ')
+                .map(|value| value.parse::<f64>().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // The view box starts at the minimum coordinates of the projected vertices.
+        let min_corner = |project: fn(f64, f64) -> (f64, f64)| {
+            [(46.0, 6.0), (46.0, 6.1), (46.1, 6.1)]
+                .map(|(latitude, longitude)| project(latitude, longitude))
+                .into_iter()
+                .fold((f64::INFINITY, f64::INFINITY), |(min_x, min_y), (x, y)| {
+                    (min_x.min(x), min_y.min(y))
+                })
+        };
+        for (projection, project) in [
+            (
+                Projection::Lv95,
+                wgs84_to_lv95 as fn(f64, f64) -> (f64, f64),
+            ),
+            (Projection::WebMercator, wgs84_to_web_mercator),
+        ] {
+            let view_box = svg_view_box(projection);
+            let (min_x, min_y) = min_corner(project);
+            assert!(
+                (view_box[0] - min_x).abs() < 1.0 && (view_box[1] - min_y).abs() < 1.0,
+                "{projection:?}: {view_box:?}"
+            );
+        }
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_write_tiles() {
+        // The isochrone is on the border of two tiles in latitude at zooms 8 and 9.
+        let (min, max) = (
+            wgs84_to_web_mercator(46.0, 6.0),
+            wgs84_to_web_mercator(46.1, 6.1),
+        );
+        assert_eq!(tile_range(min, max, 0), (0..=0, 0..=0));
+        assert_eq!(tile_range(min, max, 8), (132..=132, 90..=91));
+
+        let dir = env::temp_dir().join("hrdf_routing_engine_tiles");
+        let _ = fs::remove_dir_all(&dir);
+        let num_tiles = isochrone_map().write_tiles(&dir, 8..=9).unwrap();
+        assert_eq!(num_tiles, 2 + 2);
+        // The isochrone is drawn on both tiles of zoom 8.
+        for y in [90, 91] {
+            let tile =
+                resvg::tiny_skia::Pixmap::load_png(dir.join(format!("8/132/{y}.png"))).unwrap();
+            assert!(tile.pixels().iter().any(|pixel| pixel.alpha() > 0), "{y}");
+        }
+
+        assert!(matches!(
+            isochrone_map().write_tiles(&dir, 8..=23),
+            Err(RError::InvalidZoom(23))
+        ));
+    }
+
+    #[test]
+    fn test_write_geojson_uses_longitude_latitude() {
+        let path = env::temp_dir().join("hrdf_routing_engine_isochrones.geojson");
+        isochrone_map().write_geojson(&path).unwrap();
+
+        let geojson: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let feature = &geojson["features"][0];
+        assert_eq!(feature["properties"]["time_limit"], 10);
+        assert_eq!(feature["properties"]["time_limit_seconds"], 600);
+        assert_eq!(
+            feature["geometry"]["coordinates"][0][0][1],
+            serde_json::json!([6.1, 46.0])
+        );
+    }
+
+    #[test]
+    fn test_transit_contribution() {
+        let isochrone = |size| {
+            let polygon = Polygon::new(square(46.0, 6.0, size), vec![]);
+            Isochrone::new(MultiPolygon::new(vec![polygon]), 20)
+        };
+        let isochrone_map = |isochrone: Isochrone| {
+            let area = isochrone.compute_area();
+            IsochroneMap::new(
+                vec![isochrone],
+                vec![area],
+                vec![],
+                Coordinates::new(CoordinateSystem::WGS84, 46.0, 6.0),
+                NaiveDateTime::default(),
+                ((46.0, 6.0), (46.1, 6.1)),
+            )
+        };
+        let walking_isochrone_map = isochrone_map(isochrone(0.04));
+        let isochrone_map =
+            isochrone_map(isochrone(0.1)).with_transit_contribution(&walking_isochrone_map);
+
+        let expected = isochrone_map.areas()[0] - walking_isochrone_map.areas()[0];
+        let area = isochrone_map.transit_contribution_area().unwrap();
+        // The edges of the difference are split at the corners of the walking isochrone, which
+        // are not exactly on the projected edges of the larger one.
+        assert!(
+            (area - expected).abs() < 1e-4 * expected,
+            "{area} != {expected}"
+        );
+
+        let path = env::temp_dir().join("hrdf_routing_engine_transit_contribution.geojson");
+        isochrone_map.write_geojson(&path).unwrap();
+        let geojson: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(geojson["features"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            geojson["features"][1]["properties"]["kind"],
+            "transit_contribution"
+        );
+        assert_eq!(
+            geojson["features"][0]["properties"]["kind"],
+            serde_json::Value::Null
+        );
+    }
+
+    #[test]
+    fn test_write_json_contains_areas() {
+        let path = env::temp_dir().join("hrdf_routing_engine_isochrones.json");
+        let isochrone_map = isochrone_map();
+        isochrone_map
+            .write(&path, OutputFormat::Json, 1.0, None)
+            .unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["areas_m2"][0], isochrone_map.compute_areas()[0]);
+        assert!(json["max_distances"].is_array());
+    }
+}
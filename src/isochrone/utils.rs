@@ -1,12 +1,25 @@
 use std::f64::consts::PI;
 
 use chrono::{Duration, NaiveDateTime};
-use geo::{LineString, MultiPolygon, Polygon};
+use geo::{Coord, CoordsIter, LineString, MapCoords, MultiPolygon, Polygon, Simplify};
+use geojson::GeoJson;
 use hrdf_parser::{Coordinates, Stop};
+use rustc_hash::FxHashMap;
 
 use super::constants::WALKING_SPEED_IN_KILOMETERS_PER_HOUR;
+use crate::{RResult, error::RError};
 
+/// Converts LV95 (easting, northing) to WGS84 (latitude, longitude), to about a meter.
 /// https://github.com/antistatique/swisstopo
+///
+/// ```
+/// use hrdf_routing_engine::geo_utils::lv95_to_wgs84;
+///
+/// // The origin of the Swiss coordinates, the old observatory of Bern.
+/// let (latitude, longitude) = lv95_to_wgs84(2600000.0, 1200000.0);
+/// assert!((latitude - 46.95108).abs() < 1e-5);
+/// assert!((longitude - 7.43864).abs() < 1e-5);
+/// ```
 #[rustfmt::skip]
 pub fn lv95_to_wgs84(easting: f64, northing: f64) -> (f64, f64) {
     let y_aux = (easting - 2600000.0) / 1000000.0;
@@ -32,7 +45,17 @@ pub fn lv95_to_wgs84(easting: f64, northing: f64) -> (f64, f64) {
     (latitude, longitude)
 }
 
+/// Converts WGS84 (latitude, longitude) to LV95 (easting, northing), to about a meter.
 /// https://github.com/antistatique/swisstopo
+///
+/// ```
+/// use hrdf_routing_engine::geo_utils::wgs84_to_lv95;
+///
+/// // The old observatory of Bern, the origin of the Swiss coordinates.
+/// let (easting, northing) = wgs84_to_lv95(46.95108, 7.43864);
+/// assert!((easting - 2600000.0).abs() < 1.0);
+/// assert!((northing - 1200000.0).abs() < 1.0);
+/// ```
 #[rustfmt::skip]
 pub fn wgs84_to_lv95(latitude: f64, longitude: f64) -> (f64, f64) {
     let latitude = deg_to_sex(latitude);
@@ -65,18 +88,52 @@ pub fn wgs84_to_lv95(latitude: f64, longitude: f64) -> (f64, f64) {
 /// Creates a new MultiPolygon in lv95 coordinates. We suppose the original polygon was in wgs84
 /// coordinates
 pub fn multi_polygon_to_lv95(mp: &MultiPolygon) -> MultiPolygon {
+    project_multi_polygon(mp, wgs84_to_lv95)
+}
+
+/// Simplifies the WGS84 polygons with the Ramer-Douglas-Peucker algorithm, no vertex moving by
+/// more than tolerance meters. The simplification is done in LV95 coordinates, the vertices kept
+/// are the original WGS84 ones.
+pub fn simplify_multi_polygon(mp: &MultiPolygon, tolerance: f64) -> MultiPolygon {
+    let lv95 = multi_polygon_to_lv95(mp);
+    let originals: FxHashMap<_, Coord> = lv95
+        .coords_iter()
+        .map(|c| (c.x.to_bits(), c.y.to_bits()))
+        .zip(mp.coords_iter())
+        .collect();
+    lv95.simplify(tolerance)
+        .map_coords(|c| originals[&(c.x.to_bits(), c.y.to_bits())])
+}
+
+/// Converts WGS84 coordinates to Web Mercator (EPSG:3857) ones, the projection of the slippy-map
+/// tiles. Returns (x, y) in meters, y increasing northward.
+pub fn wgs84_to_web_mercator(latitude: f64, longitude: f64) -> (f64, f64) {
+    const EARTH_RADIUS_IN_METERS: f64 = 6378137.0;
+
+    let x = EARTH_RADIUS_IN_METERS * longitude.to_radians();
+    let y = EARTH_RADIUS_IN_METERS * (PI / 4.0 + latitude.to_radians() / 2.0).tan().ln();
+    (x, y)
+}
+
+#[cfg(feature = "svg")]
+pub fn multi_polygon_to_web_mercator(mp: &MultiPolygon) -> MultiPolygon {
+    project_multi_polygon(mp, wgs84_to_web_mercator)
+}
+
+/// Applies project to the (latitude, longitude) coordinates of the polygons.
+fn project_multi_polygon(mp: &MultiPolygon, project: fn(f64, f64) -> (f64, f64)) -> MultiPolygon {
     mp.iter()
         .map(|p| {
             let exterior = LineString::from(
                 p.exterior()
                     .coords()
-                    .map(|c| wgs84_to_lv95(c.x, c.y))
+                    .map(|c| project(c.x, c.y))
                     .collect::<Vec<_>>(),
             );
             let interiors = p
                 .interiors()
                 .iter()
-                .map(|ls| ls.coords().map(|c| wgs84_to_lv95(c.x, c.y)).collect())
+                .map(|ls| ls.coords().map(|c| project(c.x, c.y)).collect())
                 .collect();
             Polygon::new(exterior, interiors)
         })
@@ -111,11 +168,27 @@ pub fn distance_between_2_points(point1: Coordinates, point2: Coordinates) -> f6
     (x_sqr + y_sqr).sqrt()
 }
 
+/// Time to cover the distance in meters at the speed, rounded down to the second.
+///
+/// ```
+/// use chrono::Duration;
+/// use hrdf_routing_engine::geo_utils::distance_to_time;
+///
+/// assert_eq!(distance_to_time(1000.0, 3.6), Duration::seconds(1000));
+/// ```
 pub fn distance_to_time(distance: f64, speed_in_kilometers_per_hour: f64) -> Duration {
     let speed_in_meters_per_second = speed_in_kilometers_per_hour / 3.6;
     Duration::seconds((distance / speed_in_meters_per_second) as i64)
 }
 
+/// Distance in meters covered in the duration at the speed.
+///
+/// ```
+/// use chrono::Duration;
+/// use hrdf_routing_engine::geo_utils::time_to_distance;
+///
+/// assert_eq!(time_to_distance(Duration::minutes(10), 3.6), 600.0);
+/// ```
 pub fn time_to_distance(duration: Duration, speed_in_kilometers_per_hour: f64) -> f64 {
     let speed_in_meters_per_second = speed_in_kilometers_per_hour / 3.6;
     duration.num_seconds() as f64 * speed_in_meters_per_second
@@ -125,6 +198,15 @@ fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
+/// Great circle distance in kilometers between two WGS84 points.
+///
+/// ```
+/// use hrdf_routing_engine::geo_utils::haversine_distance;
+///
+/// // Zürich HB => Bern, as the crow flies.
+/// let distance = haversine_distance(47.37817, 8.54021, 46.94899, 7.43913);
+/// assert!((distance - 95.95).abs() < 0.01);
+/// ```
 pub fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     let radius_of_earth_km = 6371.0;
 
@@ -143,6 +225,57 @@ pub fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     radius_of_earth_km * c
 }
 
+/// Initial bearing of the great circle from the first point to the second, in degrees clockwise
+/// from the north, between 0 and 360.
+pub fn bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let lat1_rad = degrees_to_radians(lat1);
+    let lat2_rad = degrees_to_radians(lat2);
+    let delta_lon = degrees_to_radians(lon2 - lon1);
+
+    let y = delta_lon.sin() * lat2_rad.cos();
+    let x = lat1_rad.cos() * lat2_rad.sin() - lat1_rad.sin() * lat2_rad.cos() * delta_lon.cos();
+
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Returns the WGS84 (latitude, longitude) of a GeoJSON Point, given as a geometry, a feature or
+/// the first feature of a collection. The first point of a MultiPoint is used.
+pub fn origin_from_geojson(geojson: &str) -> RResult<(f64, f64)> {
+    let geometry = match geojson.parse::<GeoJson>()? {
+        GeoJson::Geometry(geometry) => Some(geometry),
+        GeoJson::Feature(feature) => feature.geometry,
+        GeoJson::FeatureCollection(collection) => collection
+            .features
+            .into_iter()
+            .next()
+            .and_then(|feature| feature.geometry),
+    };
+    let position = match geometry.map(|geometry| geometry.value) {
+        Some(geojson::Value::Point(position)) => position,
+        Some(geojson::Value::MultiPoint(positions)) => {
+            positions.into_iter().next().ok_or(RError::NotAPoint)?
+        }
+        _ => return Err(RError::NotAPoint),
+    };
+    // The GeoJSON positions are (longitude, latitude).
+    match position[..] {
+        [longitude, latitude, ..] => Ok((latitude, longitude)),
+        _ => Err(RError::NotAPoint),
+    }
+}
+
+/// Walking time from long/lat to the stop, which must have WGS84 coordinates.
+pub fn walking_time_to_stop(latitude: f64, longitude: f64, stop: &Stop) -> Duration {
+    let coord = stop.wgs84_coordinates().unwrap();
+    let distance = haversine_distance(
+        latitude,
+        longitude,
+        coord.latitude().expect("Wrong coordinate system"),
+        coord.longitude().expect("Wrong coordinate system"),
+    ) * 1000.0;
+    distance_to_time(distance, WALKING_SPEED_IN_KILOMETERS_PER_HOUR)
+}
+
 /// Adjusts the departure time from a stop, given the person is walking from long/lat to stop
 pub fn adjust_departure_at(
     departure_at: NaiveDateTime,
@@ -151,18 +284,11 @@ pub fn adjust_departure_at(
     origin_point_longitude: f64,
     departure_stop: &Stop,
 ) -> (NaiveDateTime, Duration) {
-    let distance = {
-        let coord = departure_stop.wgs84_coordinates().unwrap();
-
-        haversine_distance(
-            origin_point_latitude,
-            origin_point_longitude,
-            coord.latitude().expect("Wrong coordinate system"),
-            coord.longitude().expect("Wrong coordinate system"),
-        ) * 1000.0
-    };
-
-    let duration = distance_to_time(distance, WALKING_SPEED_IN_KILOMETERS_PER_HOUR);
+    let duration = walking_time_to_stop(
+        origin_point_latitude,
+        origin_point_longitude,
+        departure_stop,
+    );
 
     let adjusted_departure_at = departure_at.checked_add_signed(duration).unwrap();
     let adjusted_time_limit = time_limit - duration;
@@ -170,6 +296,7 @@ pub fn adjust_departure_at(
     (adjusted_departure_at, adjusted_time_limit)
 }
 
+/// Iterates over the half-open interval [from, to) with a step of incr.
 #[derive(Debug, Clone, Copy)]
 pub struct NaiveDateTimeRange {
     from: NaiveDateTime,
@@ -190,9 +317,8 @@ impl Iterator for NaiveDateTimeRange {
             return None;
         }
         let current = self.from;
-        let maybe_next = self.from + self.incr;
-        self.from = maybe_next;
-        (current < self.to).then_some(current)
+        self.from += self.incr;
+        Some(current)
     }
 }
 
@@ -304,6 +430,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_wgs84_to_web_mercator() {
+        let (x, y) = wgs84_to_web_mercator(0.0, 0.0);
+        assert!(x.abs() < 1e-6 && y.abs() < 1e-6);
+
+        // Zürich HB: 47.3769°N, 8.5417°E, approximately 950858, 6003812 in EPSG:3857
+        let (x, y) = wgs84_to_web_mercator(47.3769, 8.5417);
+        assert!((x - 950858.0).abs() < 10.0, "x was {x}, expected ~950858");
+        assert!((y - 6003812.0).abs() < 10.0, "y was {y}, expected ~6003812");
+    }
+
     #[test]
     fn test_haversine_distance_bern_zurich() {
         // Bern to Zürich: approximately 94.5 km
@@ -524,4 +661,120 @@ mod tests {
 
         assert_eq!(times, expected);
     }
+
+    #[test]
+    fn test_naive_date_time_range_increment_not_dividing_range() {
+        use chrono::NaiveDateTime;
+
+        let start =
+            NaiveDateTime::parse_from_str("2025-06-15 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let end =
+            NaiveDateTime::parse_from_str("2025-06-15 10:05:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        let range = NaiveDateTimeRange::new(start, end, Duration::minutes(2));
+        let times: Vec<_> = range.collect();
+        let expected = vec![
+            NaiveDateTime::parse_from_str("2025-06-15 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            NaiveDateTime::parse_from_str("2025-06-15 10:02:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            NaiveDateTime::parse_from_str("2025-06-15 10:04:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        ];
+
+        // The upper bound is never yielded
+        assert_eq!(times, expected);
+    }
+
+    #[test]
+    fn test_origin_from_geojson() {
+        let point = r#"{"type": "Point", "coordinates": [6.1353, 46.2095]}"#;
+        assert_eq!(origin_from_geojson(point).unwrap(), (46.2095, 6.1353));
+
+        let feature = r#"{
+            "type": "Feature",
+            "properties": {"name": "Genève"},
+            "geometry": {"type": "Point", "coordinates": [6.1353, 46.2095, 375.0]}
+        }"#;
+        assert_eq!(origin_from_geojson(feature).unwrap(), (46.2095, 6.1353));
+
+        let collection = r#"{
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "properties": null,
+                "geometry": {"type": "MultiPoint", "coordinates": [[7.4474, 46.9479], [6.0, 46.0]]}
+            }]
+        }"#;
+        assert_eq!(origin_from_geojson(collection).unwrap(), (46.9479, 7.4474));
+
+        let line = r#"{"type": "LineString", "coordinates": [[6.0, 46.0], [7.0, 47.0]]}"#;
+        assert!(matches!(origin_from_geojson(line), Err(RError::NotAPoint)));
+        assert!(origin_from_geojson("{").is_err());
+    }
+
+    #[test]
+    fn test_bearing() {
+        // Bern => Zürich HB, towards the north-east
+        let to_zurich = bearing(46.9479, 7.4474, 47.3769, 8.5417);
+        assert!((55.0..65.0).contains(&to_zurich), "{to_zurich}");
+
+        assert!((bearing(46.0, 7.0, 47.0, 7.0) - 0.0).abs() < 1e-9);
+        assert!((bearing(46.0, 7.0, 46.0, 8.0) - 90.0).abs() < 1.0);
+        assert!((bearing(47.0, 7.0, 46.0, 7.0) - 180.0).abs() < 1e-9);
+        assert!((bearing(46.0, 8.0, 46.0, 7.0) - 270.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_naive_date_time_range_symmetric_window() {
+        use chrono::NaiveDateTime;
+
+        // Same window as the one used by the optimal/worst/average isochrones
+        let departure_at =
+            NaiveDateTime::parse_from_str("2025-06-15 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let delta_time = Duration::minutes(30);
+
+        let times: Vec<_> = NaiveDateTimeRange::new(
+            departure_at - delta_time,
+            departure_at + delta_time,
+            Duration::minutes(1),
+        )
+        .collect();
+
+        assert_eq!(times.len(), 60);
+        assert_eq!(times.first(), Some(&(departure_at - delta_time)));
+        assert_eq!(
+            times.last(),
+            Some(&(departure_at + delta_time - Duration::minutes(1)))
+        );
+        assert!(times.contains(&departure_at));
+    }
+
+    #[test]
+    fn test_simplify_multi_polygon() {
+        use geo::Area;
+
+        // A circle of 1 km around Bern with a vertex every 10 m or so.
+        let num_points = 628;
+        let circle: Vec<_> = (0..=num_points)
+            .map(|i| {
+                let angle = 2.0 * PI * i as f64 / num_points as f64;
+                lv95_to_wgs84(
+                    2600000.0 + 1000.0 * angle.cos(),
+                    1200000.0 + 1000.0 * angle.sin(),
+                )
+            })
+            .collect();
+        let mp = MultiPolygon::new(vec![Polygon::new(LineString::from(circle), vec![])]);
+
+        let simplified = simplify_multi_polygon(&mp, 5.0);
+        assert!(simplified.coords_count() < mp.coords_count() / 4);
+        // The vertices kept are the original ones.
+        assert!(
+            simplified
+                .coords_iter()
+                .all(|c| mp.coords_iter().any(|o| o == c))
+        );
+        // The area is within the tolerance along the perimeter.
+        let area = multi_polygon_to_lv95(&mp).unsigned_area();
+        let simplified_area = multi_polygon_to_lv95(&simplified).unsigned_area();
+        assert!((area - simplified_area).abs() < 5.0 * 2.0 * PI * 1000.0);
+    }
 }
@@ -1,22 +1,59 @@
 use std::fmt::Display;
 
 use chrono::NaiveDateTime;
+use hrdf_parser::Hrdf;
+
+use crate::{
+    error::{RError, RResult},
+    stop_search::resolve_stop,
+};
 
 #[derive(Debug, Clone)]
 pub struct JourneyArgs {
     pub departure_stop_id: i32,
     pub arrival_stop_id: i32,
+    pub departure_stop_name: Option<String>,
+    pub arrival_stop_name: Option<String>,
+    pub via_stop_id: Option<i32>,
     pub departure_at: NaiveDateTime,
     pub max_num_explorable_connections: i32,
+    pub explain: bool,
     pub verbose: bool,
 }
 
+impl JourneyArgs {
+    /// Replaces the departure and arrival stop ids by the best match of their stop name, if any.
+    pub fn resolve_stop_names(&mut self, hrdf: &Hrdf) -> RResult<()> {
+        if let Some(name) = &self.departure_stop_name {
+            self.departure_stop_id = best_match(hrdf, name)?;
+        }
+        if let Some(name) = &self.arrival_stop_name {
+            self.arrival_stop_id = best_match(hrdf, name)?;
+        }
+        Ok(())
+    }
+}
+
+fn best_match(hrdf: &Hrdf, name: &str) -> RResult<i32> {
+    let (stop_id, stop_name, _) = resolve_stop(hrdf, name)
+        .into_iter()
+        .next()
+        .ok_or_else(|| RError::StopNotFound(name.to_string()))?;
+    // Logged so that the user can check the stop and give its id next time.
+    log::info!("Stop \"{name}\" resolved to {stop_name} ({stop_id})");
+    Ok(stop_id)
+}
+
 impl Display for JourneyArgs {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "departure_stop_id: {}, arrival_stop_id: {}, departure_at: {}",
             self.departure_stop_id, self.arrival_stop_id, self.departure_at
-        )
+        )?;
+        if let Some(via_stop_id) = self.via_stop_id {
+            write!(f, ", via_stop_id: {via_stop_id}")?;
+        }
+        Ok(())
     }
 }
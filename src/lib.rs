@@ -2,42 +2,99 @@ mod app;
 mod cli;
 mod debug;
 mod error;
+mod graph_export;
 mod isochrone;
 mod journey;
+mod local_time;
+mod output;
 mod routing;
 mod service;
+mod stop_index;
+mod stop_search;
+mod timetable;
 mod utils;
 
-#[cfg(feature = "hectare")]
-pub use app::run_surface_per_ha;
+/// Conversions between the Swiss LV95 and the WGS84 coordinates, and the distances and times used
+/// by the isochrones.
+pub mod geo_utils {
+    pub use crate::isochrone::utils::{
+        distance_to_time, haversine_distance, lv95_to_wgs84, time_to_distance, wgs84_to_lv95,
+    };
+}
+
 pub use app::{run_average, run_comparison, run_optimal, run_simple, run_worst};
+#[cfg(feature = "hectare")]
+pub use app::{run_surface_per_ha, run_surface_per_ha_to_geojsonl};
 pub use cli::{Cli, Mode};
-pub use debug::run_debug;
-pub use error::RResult;
+pub use debug::{run_debug, run_validate, warmup};
+pub use error::{RError, RResult};
+pub use graph_export::{GraphFormat, graph_to_geojson, write_graph};
 pub use isochrone::externals::{ExcludedPolygons, LAKES_GEOJSON_URLS};
-pub use isochrone::{IsochroneArgs, IsochroneDisplayMode};
+pub use isochrone::{
+    ISOCHRONE_MAP_FORMAT_VERSION, IsochroneArgs, IsochroneConfig, IsochroneDiagnostics,
+    IsochroneDisplayMode, IsochroneMap, IsochroneOutputFormat, IsochroneProjection,
+    IsochroneValidationReport, ReliabilitySpread, StopDiagnostic, UnreachedReason,
+    compute_isochrones_for_time_limits, compute_isochrones_series,
+    compute_isochrones_with_diagnostics, compute_reliability_spread, compute_walking_isochrone,
+    constants::{DEFAULT_MAX_ACCESS_WALK_IN_METERS, WALKING_SPEED_IN_KILOMETERS_PER_HOUR},
+    origin_from_geojson, reachable_iter, wgs84_to_web_mercator,
+};
 #[cfg(feature = "hectare")]
-pub use isochrone::{IsochroneHectareArgs, compute_isochrones, externals::HectareData};
+pub use isochrone::{
+    IsochroneHectareArgs, accessibility_score, compute_isochrones, externals::HectareData,
+};
 pub use journey::JourneyArgs;
-pub use routing::{Route, plan_journey, plan_shortest_journey};
+pub use local_time::{elapsed_between, to_swiss_time};
+pub use output::OutputArgs;
+pub use routing::{
+    BoardingRestrictions, ComparisonCriterion, ComparisonTrace, DelaySource, JourneyPlan,
+    JourneyQuery, NoDelays, OccupancyLevel, OccupancySource, ReachableStopsPage, Route, RouteDiff,
+    RouteSection, SectionDifference, Transport, compute_routes_from_origin, earliest_arrivals,
+    explain_journey, find_reachable_stops_from_origins, find_reachable_stops_within_time_limit,
+    paginate_reachable_stops, plan_journey, plan_journey_from_coord, plan_journey_or_walk,
+    plan_journey_to_any, plan_journey_to_coord, plan_journey_via,
+    plan_journey_with_transfer_penalty, plan_shortest_journey, read_routes, travel_time_matrix,
+    write_routes,
+};
 pub use service::run_service;
+pub use stop_index::StopIndex;
+pub use stop_search::resolve_stop;
+pub use timetable::{
+    DepartureBoardEntry, HeadwayStats, clamp_to_timetable, dangling_stop_ids, departure_board,
+    headway_stats, hrdf_from_directory, is_date_within_timetable, is_exchange_point,
+    is_stop_served_on, parent_station, served_days_for_stop,
+};
 
 #[cfg(test)]
 mod tests {
-    use std::{env, error::Error, fs::read_to_string, time::Instant};
+    use std::{env, error::Error, fs::read_to_string, sync::Arc, time::Instant};
 
     use crate::{
         ExcludedPolygons, HectareData, LAKES_GEOJSON_URLS,
-        isochrone::unique_coordinates_from_routes, routing::compute_routes_from_origin,
+        isochrone::{
+            compute_optimal_isochrones, compute_worst_isochrones, unique_coordinates_from_routes,
+        },
+        routing::compute_routes_from_origin,
         utils::create_date_time,
     };
-    use chrono::{Duration, TimeDelta, Timelike};
+    use chrono::{Duration, Timelike};
+    use geo::MultiPolygon;
     use hrdf_parser::Hrdf;
-    use ojp_rs::{OJP, SimplifiedLeg, SimplifiedTrip};
+    use ojp_rs::{OJP, SimplifiedTrip};
 
     use test_log::test;
 
-    use crate::{Route, plan_shortest_journey};
+    use crate::{
+        DEFAULT_MAX_ACCESS_WALK_IN_METERS, IsochroneArgs, IsochroneConfig, JourneyPlan,
+        JourneyQuery, RError, StopIndex, UnreachedReason, clamp_to_timetable,
+        compute_isochrones_with_diagnostics, compute_reliability_spread, dangling_stop_ids,
+        departure_board, earliest_arrivals, explain_journey, find_reachable_stops_from_origins,
+        find_reachable_stops_within_time_limit, headway_stats, is_date_within_timetable,
+        is_exchange_point, parent_station, plan_journey, plan_journey_from_coord,
+        plan_journey_or_walk, plan_journey_to_any, plan_journey_to_coord, plan_journey_via,
+        plan_journey_with_transfer_penalty, plan_shortest_journey, travel_time_matrix,
+        utils::{fixture_boarding_restrictions, fixture_hrdf},
+    };
     use futures::future::join_all;
 
     use pretty_assertions::assert_eq;
@@ -60,39 +117,6 @@ mod tests {
         ))
     }
 
-    struct STrip(SimplifiedTrip);
-
-    impl STrip {
-        fn from(value: &Route, hrdf: &Hrdf) -> Self {
-            let mut prev_arr_time = value.departure_at();
-            let legs = value
-                .sections()
-                .iter()
-                .map(|s| {
-                    let departure_id = s.departure_stop_id();
-                    let departure_stop = s.departure_stop_name(hrdf.data_storage());
-                    let arrival_id = s.arrival_stop_id();
-                    let arrival_stop = s.arrival_stop_name(hrdf.data_storage());
-                    let departure_time = s.departure_at().unwrap_or(prev_arr_time);
-                    let arrival_time = s.arrival_at().unwrap_or(
-                        prev_arr_time + TimeDelta::minutes(s.duration().unwrap_or(0) as i64),
-                    );
-                    prev_arr_time = arrival_time;
-                    SimplifiedLeg::new(
-                        departure_id,
-                        departure_stop,
-                        arrival_id,
-                        arrival_stop,
-                        departure_time,
-                        arrival_time,
-                        format!("{:?}", s.transport()),
-                    )
-                })
-                .collect::<Vec<_>>();
-            STrip(SimplifiedTrip::new(legs))
-        }
-    }
-
     static IDS: [(i32, i32); 34] = [
         (8577820, 8501120),
         (8572662, 8576724),
@@ -156,14 +180,14 @@ mod tests {
                 log::info!("Testing trip: {from_id} - {to_id} at {date_time}");
                 plan_shortest_journey(hrdf, from_id, to_id, date_time, 10, false)
                     .as_ref()
-                    .map(|r| STrip::from(r, hrdf).0)
+                    .map(|r| r.to_ojp_trip(hrdf.data_storage()))
             })
             .collect::<Vec<_>>();
         let hrdf_trips: Vec<_> = join_all(hrdf_trips).await;
         // We are only interested in the "failures" of the hrdf routing engine
         let failed_comparison = ref_trips
             .into_iter()
-            .zip(hrdf_trips.into_iter())
+            .zip(hrdf_trips)
             .filter_map(|(rt, ht)| {
                 if let Some(ht) = ht
                     && !rt.approx_equal(&ht, 0.1)
@@ -178,6 +202,411 @@ mod tests {
         Ok(failed_comparison)
     }
 
+    pub fn test_cross_midnight_journey(hrdf: &Hrdf) {
+        // Zürich HB (8503000) to Zürich Flughafen (8503016), late on a Friday evening.
+        let departure_at = create_date_time(2025, 6, 13, 23, 55);
+        let route = plan_journey(hrdf, 8503000, 8503016, departure_at, 10, false).unwrap();
+
+        assert_eq!(
+            route.arrival_at().date(),
+            departure_at.date().succ_opt().unwrap()
+        );
+        let mut previous_arrival_at = departure_at;
+        for section in route.sections().iter().filter(|s| !s.is_walking_trip()) {
+            // The sections after midnight must be dated the next day.
+            let section_departure_at = section.departure_at().unwrap();
+            let section_arrival_at = section.arrival_at().unwrap();
+            assert!(previous_arrival_at <= section_departure_at);
+            assert!(section_departure_at <= section_arrival_at);
+            previous_arrival_at = section_arrival_at;
+        }
+    }
+
+    pub fn test_earliest_arrivals(hrdf: &Hrdf) {
+        // Petit-Lancy, Les Esserts (8587418)
+        let departure_at = create_date_time(2025, 6, 1, 12, 30);
+        let time_limit = Duration::minutes(30);
+        let arrivals = earliest_arrivals(hrdf, 8587418, departure_at, time_limit, 10);
+        let routes = find_reachable_stops_within_time_limit(
+            hrdf,
+            8587418,
+            departure_at,
+            time_limit,
+            10,
+            None,
+            false,
+        );
+
+        assert!(!arrivals.is_empty());
+        assert_eq!(arrivals.len(), routes.len());
+        for route in routes {
+            let arrival_stop_id = route.arrival_stop_id().unwrap();
+            assert_eq!(arrivals[&arrival_stop_id], route.arrival_at());
+        }
+    }
+
+    pub fn test_find_reachable_stops_from_origins(hrdf: &Hrdf) {
+        // Petit-Lancy, Les Esserts (8587418) and Genève, gare Cornavin (8587057)
+        let departure_stop_ids = [8587418, 8587057];
+        let departure_at = create_date_time(2025, 6, 1, 12, 30);
+        let time_limit = Duration::minutes(20);
+        let arrivals = departure_stop_ids.map(|departure_stop_id| {
+            earliest_arrivals(hrdf, departure_stop_id, departure_at, time_limit, 10)
+        });
+
+        let routes = find_reachable_stops_from_origins(
+            hrdf,
+            &departure_stop_ids,
+            departure_at,
+            time_limit,
+            10,
+            None,
+            None,
+            false,
+        );
+        let num_reachable_stops = arrivals[0]
+            .keys()
+            .chain(arrivals[1].keys())
+            .collect::<std::collections::HashSet<_>>()
+            .len();
+        assert_eq!(routes.len(), num_reachable_stops);
+        for route in routes {
+            let arrival_stop_id = route.arrival_stop_id().unwrap();
+            let earliest = arrivals
+                .iter()
+                .filter_map(|arrivals| arrivals.get(&arrival_stop_id))
+                .min()
+                .unwrap();
+            assert_eq!(route.arrival_at(), *earliest);
+        }
+    }
+
+    pub fn test_travel_time_matrix(hrdf: &Hrdf) {
+        // Petit-Lancy, Les Esserts (8587418) and Genève, gare Cornavin (8587057)
+        let stop_ids = [8587418, 8587057];
+        let departure_at = create_date_time(2025, 6, 1, 12, 30);
+        let time_limit = Duration::minutes(30);
+        // Lausanne (8501120) is out of reach within 30 minutes.
+        let destinations = [8587418, 8587057, 8501120];
+
+        let matrix = travel_time_matrix(
+            hrdf,
+            &stop_ids,
+            &destinations,
+            departure_at,
+            time_limit,
+            10,
+            2,
+        );
+        assert_eq!(matrix.len(), stop_ids.len());
+        for (origin, row) in stop_ids.into_iter().zip(&matrix) {
+            let arrivals = earliest_arrivals(hrdf, origin, departure_at, time_limit, 10);
+            assert_eq!(row.len(), destinations.len());
+            for (destination, travel_time) in destinations.into_iter().zip(row) {
+                let expected = if destination == origin {
+                    Some(Duration::zero())
+                } else {
+                    arrivals
+                        .get(&destination)
+                        .map(|arrival_at| *arrival_at - departure_at)
+                };
+                assert_eq!(*travel_time, expected);
+            }
+            assert_eq!(row[2], None);
+        }
+    }
+
+    pub fn test_plan_journey_to_any(hrdf: &Hrdf) {
+        // Petit-Lancy, Les Esserts (8587418) to Genève, gare Cornavin (8587057) or Genève-Aéroport
+        // (8501026)
+        let arrival_stop_ids = [8587057, 8501026];
+        let departure_at = create_date_time(2025, 6, 1, 12, 30);
+
+        let route =
+            plan_journey_to_any(hrdf, 8587418, &arrival_stop_ids, departure_at, 10, false).unwrap();
+        let earliest = arrival_stop_ids
+            .iter()
+            .filter_map(|&arrival_stop_id| {
+                plan_journey(hrdf, 8587418, arrival_stop_id, departure_at, 10, false)
+            })
+            .min_by_key(|route| route.arrival_at())
+            .unwrap();
+        assert_eq!(route.arrival_at(), earliest.arrival_at());
+        assert!(arrival_stop_ids.contains(&route.arrival_stop_id().unwrap()));
+        assert!(plan_journey_to_any(hrdf, 8587418, &[], departure_at, 10, false).is_none());
+    }
+
+    pub fn test_parent_station(hrdf: &Hrdf) {
+        // Zürich HB SZU (8503088) is a stop of Zürich HB (8503000).
+        assert_eq!(parent_station(hrdf, 8503088), Some(8503000));
+        assert_eq!(parent_station(hrdf, 8503000), None);
+        assert_eq!(parent_station(hrdf, 0), None);
+    }
+
+    pub fn test_departure_board(hrdf: &Hrdf) {
+        // Bern
+        let from = create_date_time(2025, 6, 2, 8, 0);
+        let departures = departure_board(hrdf, 8507000, from, 20, None);
+        assert_eq!(departures.len(), 20);
+        assert!(departures[0].departure_at >= from);
+        assert!(
+            departures
+                .windows(2)
+                .all(|pair| pair[0].departure_at <= pair[1].departure_at)
+        );
+        // Unlike the routing, several departures go to the same terminus.
+        let destinations: std::collections::HashSet<_> = departures
+            .iter()
+            .map(|departure| departure.destination_stop_id)
+            .collect();
+        assert!(destinations.len() < departures.len());
+
+        // The departures of the next day follow the last ones of the day.
+        let from = create_date_time(2025, 6, 2, 23, 50);
+        let departures = departure_board(hrdf, 8507000, from, 20, None);
+        assert!(
+            departures
+                .iter()
+                .any(|departure| departure.departure_at.date() > from.date())
+        );
+    }
+
+    pub fn test_headway_stats(hrdf: &Hrdf) {
+        // Zürich HB (8503000) in the morning peak.
+        let date = create_date_time(2025, 6, 2, 0, 0).date();
+        let window =
+            create_date_time(2025, 6, 2, 8, 0).time()..create_date_time(2025, 6, 2, 9, 0).time();
+        let stats = headway_stats(hrdf, 8503000, date, window.clone());
+        assert!(stats.count > 10);
+        let (min, mean, max) = (stats.min.unwrap(), stats.mean.unwrap(), stats.max.unwrap());
+        assert!(min <= mean && mean <= max);
+        assert!(max < Duration::minutes(60));
+
+        // No departure outside the timetable period.
+        let date = create_date_time(2000, 1, 1, 0, 0).date();
+        let stats = headway_stats(hrdf, 8503000, date, window);
+        assert_eq!((stats.count, stats.mean), (0, None));
+    }
+
+    pub fn test_journey_query(hrdf: &Hrdf) {
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        // Genève, gare Cornavin - Zermatt
+        let route = JourneyQuery::new(hrdf)
+            .from(8587057)
+            .to(8501689)
+            .at(departure_at)
+            .verbose(false)
+            .plan()
+            .unwrap();
+        let expected = plan_journey(hrdf, 8587057, 8501689, departure_at, 10, false).unwrap();
+        assert_eq!(route.arrival_at(), expected.arrival_at());
+        assert_eq!(route.number_changes(), expected.number_changes());
+    }
+
+    pub fn test_reliability_spread(hrdf: &Hrdf) {
+        let excluded_polygons = MultiPolygon::new(Vec::new());
+        let config = IsochroneConfig::new(&excluded_polygons)
+            .with_delta_time(Duration::minutes(10))
+            .with_search_step(Duration::minutes(5))
+            .unwrap();
+        // Bern
+        let isochrone_args = IsochroneArgs {
+            latitude: 46.94908,
+            longitude: 7.43938,
+            departure_at: create_date_time(2025, 6, 2, 8, 0),
+            time_limit: Duration::minutes(20),
+            // Ignored, the two bands of num_bands give the same 10 minutes interval.
+            interval: Duration::minutes(5),
+            num_bands: Some(2),
+            max_num_explorable_connections: 10,
+            num_starting_points: 5,
+            countries: Vec::new(),
+            snap_to_interval: false,
+            apply_excluded_polygons: true,
+            verbose: false,
+        };
+
+        let spread = compute_reliability_spread(hrdf, isochrone_args.clone(), &config).unwrap();
+        assert!(spread.optimal.compute_max_area() >= spread.worst.compute_max_area());
+        assert!(spread.area_ratio > 0.0 && spread.area_ratio <= 1.0);
+
+        // The circles are united in parallel, in any order: the areas are equal up to the rounding.
+        let assert_same_areas = |areas: &[f64], expected: &[f64]| {
+            assert_eq!(areas.len(), expected.len());
+            for (area, expected) in areas.iter().zip(expected) {
+                assert!(
+                    (area - expected).abs() <= expected * 1e-6,
+                    "{area} != {expected}"
+                );
+            }
+        };
+        let optimal = compute_optimal_isochrones(hrdf, isochrone_args.clone(), &config).unwrap();
+        assert_eq!(spread.optimal.areas().len(), 2);
+        assert_same_areas(spread.optimal.areas(), optimal.areas());
+        let worst = compute_worst_isochrones(hrdf, isochrone_args, &config).unwrap();
+        assert_same_areas(spread.worst.areas(), worst.areas());
+    }
+
+    pub fn test_plan_journey_from_and_to_coord(hrdf: &Hrdf) {
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+
+        // From the square in front of Genève, gare Cornavin to Bern (8507000).
+        let route = plan_journey_from_coord(
+            hrdf,
+            &StopIndex::new(hrdf.data_storage()),
+            46.20960,
+            6.14250,
+            8507000,
+            departure_at,
+            10,
+            DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        let first = route.sections().first().unwrap();
+        assert!(first.is_walking_trip());
+        assert_eq!(first.departure_stop_id(), 0);
+        assert_eq!(route.departure_at(), departure_at);
+        assert_eq!(route.arrival_stop_id(), Some(8507000));
+
+        // From Genève (8501008) to the Bundesplatz in Bern.
+        let route = plan_journey_to_coord(
+            hrdf,
+            &StopIndex::new(hrdf.data_storage()),
+            8501008,
+            46.94680,
+            7.44400,
+            departure_at,
+            10,
+            DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        let last = route.sections().last().unwrap();
+        assert!(last.is_walking_trip());
+        assert_eq!(last.arrival_stop_id(), 0);
+        assert!(route.arrival_at() > departure_at);
+
+        // From the middle of the Lac Léman, several kilometers from any stop.
+        let result = plan_journey_from_coord(
+            hrdf,
+            &StopIndex::new(hrdf.data_storage()),
+            46.45000,
+            6.55000,
+            8507000,
+            departure_at,
+            10,
+            DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+            false,
+        );
+        assert!(matches!(result, Err(RError::NoStopWithinAccessRange(..))));
+    }
+
+    pub fn test_isochrone_diagnostics(hrdf: &Hrdf) {
+        let excluded_polygons = MultiPolygon::new(Vec::new());
+        let config = IsochroneConfig::new(&excluded_polygons);
+        // Bern
+        let isochrone_args = IsochroneArgs {
+            latitude: 46.94908,
+            longitude: 7.43938,
+            departure_at: create_date_time(2025, 6, 2, 8, 0),
+            time_limit: Duration::minutes(20),
+            interval: Duration::minutes(10),
+            num_bands: None,
+            max_num_explorable_connections: 10,
+            num_starting_points: 5,
+            countries: Vec::new(),
+            snap_to_interval: false,
+            apply_excluded_polygons: true,
+            verbose: false,
+        };
+
+        let (isochrone_map, diagnostics) =
+            compute_isochrones_with_diagnostics(hrdf, isochrone_args, &config).unwrap();
+        // Bern
+        let stop = diagnostics
+            .stops
+            .iter()
+            .find(|stop| stop.stop_id == 8507000)
+            .unwrap();
+        assert!(stop.reached && stop.reason.is_none());
+        assert!(
+            diagnostics.stops.iter().filter(|stop| stop.reached).count()
+                <= isochrone_map.stops_reached().unwrap()
+        );
+        assert!(diagnostics.unreached().all(|stop| stop.reason.is_some()));
+        assert!(diagnostics.count(UnreachedReason::ExceededTimeLimit) > 0);
+    }
+
+    pub fn test_clamp_to_timetable(hrdf: &Hrdf) {
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        assert_eq!(clamp_to_timetable(hrdf, departure_at), departure_at);
+
+        let clamped = clamp_to_timetable(hrdf, create_date_time(2000, 1, 1, 8, 0));
+        assert!(is_date_within_timetable(hrdf, clamped.date()));
+        assert_eq!(clamped.time(), departure_at.time());
+    }
+
+    pub fn test_dangling_stop_ids(hrdf: &Hrdf) {
+        let stop_ids = dangling_stop_ids(hrdf);
+        assert!(stop_ids.is_sorted());
+        assert!(
+            stop_ids
+                .iter()
+                .all(|&stop_id| hrdf.data_storage().stops().find(stop_id).is_none())
+        );
+    }
+
+    pub fn test_route_distance_and_speed(hrdf: &Hrdf) {
+        // Zürich HB (8503000) to Zürich Flughafen (8503016), about 10 km by train.
+        let departure_at = create_date_time(2025, 6, 13, 12, 0);
+        let route = plan_journey(hrdf, 8503000, 8503016, departure_at, 10, false).unwrap();
+
+        let distance = route.total_distance_meters(hrdf.data_storage());
+        assert!((8_000.0..15_000.0).contains(&distance), "{distance} m");
+        let speed = route.average_speed_kmh(hrdf.data_storage()).unwrap();
+        assert!((20.0..150.0).contains(&speed), "{speed} km/h");
+    }
+
+    pub fn test_is_exchange_point(hrdf: &Hrdf) {
+        // Zürich HB
+        assert!(is_exchange_point(hrdf, 8503000));
+        assert!(!is_exchange_point(hrdf, -1));
+    }
+
+    pub fn test_plan_journey_or_walk(hrdf: &Hrdf) {
+        let departure_at = create_date_time(2025, 6, 1, 12, 30);
+
+        // Petit-Lancy, Les Esserts (8587418) to itself
+        let plan = plan_journey_or_walk(hrdf, 8587418, 8587418, departure_at, 10, false);
+        assert!(matches!(plan, Some(JourneyPlan::WalkOnly { duration: 0 })));
+
+        // Two stops linked by a footpath are always reachable, either by walking or by a journey
+        // having at least one vehicle section.
+        let stop_connection = hrdf
+            .data_storage()
+            .stop_connections()
+            .entries()
+            .into_iter()
+            .next()
+            .unwrap();
+        let plan = plan_journey_or_walk(
+            hrdf,
+            stop_connection.stop_id_1(),
+            stop_connection.stop_id_2(),
+            departure_at,
+            10,
+            false,
+        );
+        match plan {
+            Some(JourneyPlan::Transit(route)) => assert!(route.has_vehicle_section()),
+            Some(JourneyPlan::WalkOnly { duration }) => assert!(duration > 0),
+            None => panic!("No plan found for a footpath"),
+        }
+    }
+
     pub fn test_find_reachable_stops_within_time_limit(hrdf: &Hrdf) {
         let max_num_explorable_connections = 10;
         let mut departures = Vec::new();
@@ -244,6 +673,7 @@ mod tests {
                 .unwrap();
             let routes = compute_routes_from_origin(
                 hrdf,
+                &StopIndex::new(hrdf.data_storage()),
                 coordinates.latitude().unwrap(),
                 coordinates.longitude().unwrap(),
                 departure_at,
@@ -251,8 +681,12 @@ mod tests {
                 1,
                 1,
                 max_num_explorable_connections,
+                None,
+                DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+                None,
                 false,
-            );
+            )
+            .unwrap();
             let mut data = unique_coordinates_from_routes(&routes, departure_at)
                 .into_iter()
                 .map(|(c, td)| {
@@ -286,7 +720,424 @@ mod tests {
         println!("{:.2?}", start_time.elapsed());
     }
 
+    #[test]
+    fn test_plan_journey_on_fixture() {
+        let hrdf = fixture_hrdf();
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+
+        // Alpha (8500001) to Echo (8500005): IC 101 to Charlie, then the bus 201.
+        let route = plan_journey(&hrdf, 8500001, 8500005, departure_at, 10, false).unwrap();
+        assert_eq!(route.departure_at(), departure_at);
+        assert_eq!(route.arrival_at(), create_date_time(2025, 6, 2, 8, 32));
+        assert_eq!(route.number_changes(), 1);
+        let stop_ids: Vec<_> = route
+            .sections()
+            .iter()
+            .map(|section| (section.departure_stop_id(), section.arrival_stop_id()))
+            .collect();
+        assert_eq!(stop_ids, [(8500001, 8500003), (8500003, 8500005)]);
+
+        // Foxtrot (8500006) is only reached by the footpath from Echo.
+        let route = plan_journey(&hrdf, 8500001, 8500006, departure_at, 10, false).unwrap();
+        assert_eq!(route.arrival_at(), create_date_time(2025, 6, 2, 8, 37));
+        assert!(route.sections().last().unwrap().is_walking_trip());
+
+        let query = JourneyQuery::new(&hrdf)
+            .from(8500001)
+            .to(8500005)
+            .at(departure_at)
+            .plan()
+            .unwrap();
+        assert_eq!(query.arrival_at(), create_date_time(2025, 6, 2, 8, 32));
+    }
+
+    #[test]
+    fn test_boarding_restrictions_on_fixture() {
+        let hrdf = fixture_hrdf();
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        let query = JourneyQuery::new(&hrdf)
+            .from(8500001)
+            .to(8500002)
+            .at(departure_at);
+
+        // Alpha (8500001) to Bravo (8500002): the IC 101 arrives at 08:05 but can't be left
+        // there, the S 302 arrives at 08:20.
+        assert_eq!(
+            query.plan().unwrap().arrival_at(),
+            create_date_time(2025, 6, 2, 8, 5)
+        );
+        let route = query
+            .boarding_restrictions(Arc::new(fixture_boarding_restrictions()))
+            .plan()
+            .unwrap();
+        assert_eq!(route.arrival_at(), create_date_time(2025, 6, 2, 8, 20));
+        assert_eq!(route.number_changes(), 0);
+    }
+
+    #[test]
+    fn test_max_transfer_walk_on_fixture() {
+        let hrdf = fixture_hrdf();
+        // Alpha (8500001) to Foxtrot (8500006), only reached by the 5 minutes footpath from Echo.
+        let query = JourneyQuery::new(&hrdf)
+            .from(8500001)
+            .to(8500006)
+            .at(create_date_time(2025, 6, 2, 8, 0));
+
+        let route = query
+            .clone()
+            .max_transfer_walk(Some(Duration::minutes(5)))
+            .plan()
+            .unwrap();
+        assert_eq!(route.arrival_at(), create_date_time(2025, 6, 2, 8, 37));
+        assert!(
+            query
+                .max_transfer_walk(Some(Duration::minutes(4)))
+                .plan()
+                .is_none()
+        );
+
+        // The same via Charlie (8500003) and explained.
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        let via = |minutes| {
+            plan_journey_via(
+                &hrdf,
+                8500001,
+                8500003,
+                8500006,
+                departure_at,
+                10,
+                Some(Duration::minutes(minutes)),
+                false,
+            )
+        };
+        assert_eq!(
+            via(5).unwrap().arrival_at(),
+            create_date_time(2025, 6, 2, 8, 37)
+        );
+        assert!(via(4).is_none());
+        let explain = |minutes| {
+            explain_journey(
+                &hrdf,
+                8500001,
+                8500006,
+                departure_at,
+                10,
+                Some(Duration::minutes(minutes)),
+            )
+        };
+        assert!(explain(5).is_some());
+        assert!(explain(4).is_none());
+    }
+
+    #[test]
+    fn test_cross_midnight_journey_on_fixture() {
+        let hrdf = fixture_hrdf();
+        // Alpha (8500001) to Delta (8500004) with the SN 501, arriving at 00:40.
+        let departure_at = create_date_time(2025, 6, 13, 23, 45);
+        let route = plan_journey(&hrdf, 8500001, 8500004, departure_at, 10, false).unwrap();
+        assert_eq!(route.arrival_at(), create_date_time(2025, 6, 14, 0, 40));
+        assert_eq!(route.number_changes(), 0);
+    }
+
+    #[test]
+    fn test_earliest_arrivals_on_fixture() {
+        let hrdf = fixture_hrdf();
+        // Alpha (8500001)
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        let time_limit = Duration::minutes(45);
+        let arrivals = earliest_arrivals(&hrdf, 8500001, departure_at, time_limit, 10);
+
+        // Hotel (8500008) is served from 09:00. Juliett (8500010), not an exchange point, is only
+        // passed through by the R 301.
+        assert_eq!(arrivals.len(), 7);
+        assert_eq!(arrivals[&8500002], create_date_time(2025, 6, 2, 8, 5));
+        assert_eq!(arrivals[&8500003], create_date_time(2025, 6, 2, 8, 12));
+        assert_eq!(arrivals[&8500004], create_date_time(2025, 6, 2, 8, 25));
+        assert_eq!(arrivals[&8500005], create_date_time(2025, 6, 2, 8, 32));
+        assert_eq!(arrivals[&8500006], create_date_time(2025, 6, 2, 8, 37));
+        assert_eq!(arrivals[&8500007], create_date_time(2025, 6, 2, 8, 15));
+        assert_eq!(arrivals[&8500010], create_date_time(2025, 6, 2, 8, 30));
+        assert!(!arrivals.contains_key(&8500008));
+
+        let routes = find_reachable_stops_within_time_limit(
+            &hrdf,
+            8500001,
+            departure_at,
+            time_limit,
+            10,
+            None,
+            false,
+        );
+        assert_eq!(arrivals.len(), routes.len());
+        for route in routes {
+            let arrival_stop_id = route.arrival_stop_id().unwrap();
+            assert_eq!(arrivals[&arrival_stop_id], route.arrival_at());
+        }
+    }
+
+    #[test]
+    fn test_travel_time_matrix_on_fixture() {
+        let hrdf = fixture_hrdf();
+        // Alpha (8500001) and Charlie (8500003) to Alpha, Echo (8500005) and Hotel (8500008).
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        let matrix = travel_time_matrix(
+            &hrdf,
+            &[8500001, 8500003],
+            &[8500001, 8500005, 8500008],
+            departure_at,
+            Duration::minutes(60),
+            10,
+            2,
+        );
+        assert_eq!(
+            matrix,
+            [
+                [Some(Duration::zero()), Some(Duration::minutes(32)), None],
+                [
+                    Some(Duration::minutes(42)),
+                    Some(Duration::minutes(32)),
+                    None
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_journey_to_any_on_fixture() {
+        let hrdf = fixture_hrdf();
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        // Alpha (8500001) to Delta (8500004), reached before Echo (8500005).
+        let route =
+            plan_journey_to_any(&hrdf, 8500001, &[8500005, 8500004], departure_at, 10, false)
+                .unwrap();
+        assert_eq!(route.arrival_stop_id(), Some(8500004));
+        assert_eq!(route.arrival_at(), create_date_time(2025, 6, 2, 8, 25));
+        assert!(plan_journey_to_any(&hrdf, 8500001, &[], departure_at, 10, false).is_none());
+    }
+
+    #[test]
+    fn test_plan_journey_or_walk_on_fixture() {
+        let hrdf = fixture_hrdf();
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+
+        // Alpha (8500001) to itself
+        let plan = plan_journey_or_walk(&hrdf, 8500001, 8500001, departure_at, 10, false);
+        assert!(matches!(plan, Some(JourneyPlan::WalkOnly { duration: 0 })));
+        // Echo (8500005) to Foxtrot (8500006), only linked by a 5 minutes footpath.
+        let plan = plan_journey_or_walk(&hrdf, 8500005, 8500006, departure_at, 10, false);
+        assert!(matches!(plan, Some(JourneyPlan::WalkOnly { duration: 5 })));
+        // Alpha to Charlie (8500003)
+        let plan = plan_journey_or_walk(&hrdf, 8500001, 8500003, departure_at, 10, false);
+        assert!(matches!(plan, Some(JourneyPlan::Transit(_))));
+    }
+
+    #[test]
+    fn test_departure_board_on_fixture() {
+        let hrdf = fixture_hrdf();
+        // Alpha (8500001)
+        let from = create_date_time(2025, 6, 2, 8, 0);
+        let departures = departure_board(&hrdf, 8500001, from, 3, None);
+        let designations: Vec<_> = departures
+            .iter()
+            .map(|departure| {
+                (
+                    departure.designation.as_str(),
+                    departure.departure_at.time(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            designations,
+            [
+                ("IC", from.time()),
+                ("R", create_date_time(2025, 6, 2, 8, 3).time()),
+                ("S", create_date_time(2025, 6, 2, 8, 10).time()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_departure_board_after_midnight_and_with_restrictions() {
+        let hrdf = fixture_hrdf();
+        // Golf (8500007), served after midnight by SN 501, which started the previous day.
+        let from = create_date_time(2025, 6, 14, 0, 5);
+        let departures = departure_board(&hrdf, 8500007, from, 1, None);
+        assert_eq!(departures.len(), 1);
+        assert_eq!(departures[0].designation, "SN");
+        assert_eq!(
+            departures[0].departure_at,
+            create_date_time(2025, 6, 14, 0, 11)
+        );
+
+        // Bravo (8500002), IC 102 can't be boarded there at 08:37.
+        let from = create_date_time(2025, 6, 2, 8, 30);
+        let departures = departure_board(&hrdf, 8500002, from, 1, None);
+        assert_eq!(
+            departures[0].departure_at,
+            create_date_time(2025, 6, 2, 8, 37)
+        );
+        let restrictions = fixture_boarding_restrictions();
+        let departures = departure_board(&hrdf, 8500002, from, 1, Some(&restrictions));
+        assert_eq!(departures.len(), 1);
+        assert_eq!(
+            departures[0].departure_at,
+            create_date_time(2025, 6, 2, 9, 6)
+        );
+    }
+
+    #[test]
+    fn test_route_distance_and_speed_on_fixture() {
+        let hrdf = fixture_hrdf();
+        // Alpha (8500001) to Charlie (8500003), 16 km in 12 minutes.
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        let route = plan_journey(&hrdf, 8500001, 8500003, departure_at, 10, false).unwrap();
+
+        let distance = route.total_distance_meters(hrdf.data_storage());
+        assert!((15_500.0..16_500.0).contains(&distance), "{distance} m");
+        let speed = route.average_speed_kmh(hrdf.data_storage()).unwrap();
+        assert!((75.0..85.0).contains(&speed), "{speed} km/h");
+    }
+
+    #[test]
+    fn test_plan_journey_with_transfer_penalty_on_fixture() {
+        let hrdf = fixture_hrdf();
+        // Alpha (8500001) => Delta (8500004)
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        let plan = |transfer_penalty| {
+            plan_journey_with_transfer_penalty(
+                &hrdf,
+                8500001,
+                8500004,
+                departure_at,
+                transfer_penalty,
+                10,
+                false,
+            )
+            .unwrap()
+        };
+
+        // IC 101 and B 201, changing at Charlie.
+        let without_penalty = plan(Duration::zero());
+        assert_eq!(
+            without_penalty.arrival_at(),
+            create_date_time(2025, 6, 2, 8, 25)
+        );
+        assert_eq!(without_penalty.number_changes(), 1);
+
+        // R 301 arrives 20 minutes later, without any change.
+        let with_penalty = plan(Duration::minutes(30));
+        assert_eq!(
+            with_penalty.arrival_at(),
+            create_date_time(2025, 6, 2, 8, 45)
+        );
+        assert_eq!(with_penalty.number_changes(), 0);
+    }
+
+    #[test]
+    fn test_plan_journey_from_and_to_coord_on_fixture() {
+        let hrdf = fixture_hrdf();
+        let stop_index = StopIndex::new(hrdf.data_storage());
+        // About 160 meters north of Alpha (8500001).
+        let (latitude, longitude) = (46.9525, 7.438637);
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+
+        let from_coord = plan_journey_from_coord(
+            &hrdf,
+            &stop_index,
+            latitude,
+            longitude,
+            8500001,
+            departure_at,
+            10,
+            DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        let to_coord = plan_journey_to_coord(
+            &hrdf,
+            &stop_index,
+            8500001,
+            latitude,
+            longitude,
+            departure_at,
+            10,
+            DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        let walk = from_coord.sections()[0].duration().unwrap();
+        assert!(walk > 0);
+        for route in [from_coord, to_coord] {
+            assert_eq!(route.departure_at(), departure_at);
+            assert_eq!(
+                route.arrival_at(),
+                departure_at + Duration::minutes(walk.into())
+            );
+        }
+
+        // To Charlie (8500003) with IC 105, departing from Alpha at 09:00.
+        let route = plan_journey_from_coord(
+            &hrdf,
+            &stop_index,
+            latitude,
+            longitude,
+            8500003,
+            departure_at,
+            10,
+            DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(route.departure_at(), departure_at);
+        assert_eq!(route.arrival_at(), create_date_time(2025, 6, 2, 9, 12));
+
+        // From Charlie with IC 102, arriving at Alpha at 08:42.
+        let route = plan_journey_to_coord(
+            &hrdf,
+            &stop_index,
+            8500003,
+            latitude,
+            longitude,
+            create_date_time(2025, 6, 2, 8, 20),
+            10,
+            DEFAULT_MAX_ACCESS_WALK_IN_METERS,
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            route.arrival_at(),
+            create_date_time(2025, 6, 2, 8, 42) + Duration::minutes(walk.into())
+        );
+    }
+
+    #[test]
+    fn test_reliability_spread_on_fixture() {
+        test_reliability_spread(&fixture_hrdf());
+    }
+
+    #[test]
+    fn test_timetable_on_fixture() {
+        let hrdf = fixture_hrdf();
+        // Alpha (8500001) and Juliett (8500010)
+        assert!(is_exchange_point(&hrdf, 8500001));
+        assert!(!is_exchange_point(&hrdf, 8500010));
+        assert!(!is_exchange_point(&hrdf, -1));
+
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        assert_eq!(clamp_to_timetable(&hrdf, departure_at), departure_at);
+        let clamped = clamp_to_timetable(&hrdf, create_date_time(2000, 1, 1, 8, 0));
+        assert_eq!(clamped, create_date_time(2025, 6, 1, 8, 0));
+    }
+
     #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
     async fn test_journeys() {
         // First build hrdf file
         let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
@@ -305,9 +1156,30 @@ mod tests {
         }
         assert!(failures.is_empty());
         test_find_reachable_stops_within_time_limit(&hrdf);
+        test_plan_journey_or_walk(&hrdf);
+        test_cross_midnight_journey(&hrdf);
+        test_earliest_arrivals(&hrdf);
+        test_find_reachable_stops_from_origins(&hrdf);
+        test_route_distance_and_speed(&hrdf);
+        test_is_exchange_point(&hrdf);
+        test_dangling_stop_ids(&hrdf);
+        test_travel_time_matrix(&hrdf);
+        test_plan_journey_to_any(&hrdf);
+        test_parent_station(&hrdf);
+        test_headway_stats(&hrdf);
+        test_departure_board(&hrdf);
+        test_clamp_to_timetable(&hrdf);
+        test_reliability_spread(&hrdf);
+        test_journey_query(&hrdf);
+        test_isochrone_diagnostics(&hrdf);
+        test_plan_journey_from_and_to_coord(&hrdf);
     }
 
     #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the lakes polygons"
+    )]
     async fn test_real_polygons_cache() {
         let original = ExcludedPolygons::try_new(
             &LAKES_GEOJSON_URLS,
@@ -328,6 +1200,7 @@ mod tests {
     }
 
     #[test(tokio::test)]
+    #[cfg_attr(not(feature = "network-tests"), ignore = "downloads the hectare data")]
     #[cfg(feature = "hectare")]
     async fn test_real_hectare_data_cache() {
         use std::env;
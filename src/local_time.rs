@@ -0,0 +1,73 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, TimeZone};
+use chrono_tz::{Europe::Zurich, Tz};
+
+/// Interprets the date and time as Swiss local time, the time zone of the HRDF times which the
+/// routing ignores.
+/// A time within the hour skipped when the clocks go forward (e.g. 02:30 on 2025-03-30) is moved
+/// forward by the gap, as a clock would show it: 03:30 summer time. A time within the hour repeated
+/// when the clocks go back (e.g. 02:30 on 2025-10-26) is the first one, in summer time.
+pub fn to_swiss_time(date_time: NaiveDateTime) -> DateTime<Tz> {
+    match Zurich.from_local_datetime(&date_time) {
+        LocalResult::Single(swiss_time) => swiss_time,
+        LocalResult::Ambiguous(summer_time, _) => summer_time,
+        // The gap is one hour long in Switzerland.
+        LocalResult::None => Zurich
+            .from_local_datetime(&(date_time + Duration::hours(1)))
+            .earliest()
+            .unwrap_or_else(|| panic!("{date_time} is not a Swiss local time")),
+    }
+}
+
+/// Real time elapsed between two Swiss local times. It is one hour shorter than the difference of
+/// the local times across the spring clock change, and one hour longer across the autumn one.
+pub fn elapsed_between(from: NaiveDateTime, to: NaiveDateTime) -> Duration {
+    to_swiss_time(to).signed_duration_since(to_swiss_time(from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_date_time;
+
+    #[test]
+    fn test_to_swiss_time() {
+        // Winter and summer time.
+        let swiss_time = to_swiss_time(create_date_time(2025, 1, 15, 12, 0));
+        assert_eq!(swiss_time.naive_utc(), create_date_time(2025, 1, 15, 11, 0));
+        let swiss_time = to_swiss_time(create_date_time(2025, 7, 15, 12, 0));
+        assert_eq!(swiss_time.naive_utc(), create_date_time(2025, 7, 15, 10, 0));
+
+        // 02:30 does not exist on 2025-03-30, it is 03:30 summer time.
+        let swiss_time = to_swiss_time(create_date_time(2025, 3, 30, 2, 30));
+        assert_eq!(
+            swiss_time.naive_local(),
+            create_date_time(2025, 3, 30, 3, 30)
+        );
+        assert_eq!(swiss_time.naive_utc(), create_date_time(2025, 3, 30, 1, 30));
+
+        // 02:30 happens twice on 2025-10-26, the first time in summer time.
+        let swiss_time = to_swiss_time(create_date_time(2025, 10, 26, 2, 30));
+        assert_eq!(
+            swiss_time.naive_utc(),
+            create_date_time(2025, 10, 26, 0, 30)
+        );
+    }
+
+    #[test]
+    fn test_elapsed_between() {
+        let elapsed = |(from_day, from_hour), (to_day, to_hour), month| {
+            elapsed_between(
+                create_date_time(2025, month, from_day, from_hour, 30),
+                create_date_time(2025, month, to_day, to_hour, 30),
+            )
+        };
+
+        assert_eq!(elapsed((15, 1), (15, 3), 6), Duration::hours(2));
+        // Spring forward, 2025-03-30 02:00 => 03:00.
+        assert_eq!(elapsed((30, 1), (30, 3), 3), Duration::hours(1));
+        assert_eq!(elapsed((29, 23), (30, 4), 3), Duration::hours(4));
+        // Fall back, 2025-10-26 03:00 => 02:00.
+        assert_eq!(elapsed((26, 1), (26, 3), 10), Duration::hours(3));
+        assert_eq!(elapsed((25, 23), (26, 4), 10), Duration::hours(6));
+    }
+}
@@ -1,23 +1,24 @@
 use futures::future::join_all;
 use hrdf_routing_engine::{Cli, Mode};
-use std::error::Error;
 use std::fs::File;
 use std::io::Write;
 use std::sync::Arc;
 
 use chrono::Duration;
 use clap::Parser;
+use geo::MultiPolygon;
 use hrdf_parser::Hrdf;
 use hrdf_routing_engine::{
-    ExcludedPolygons, LAKES_GEOJSON_URLS, plan_journey, run_average, run_comparison, run_debug,
-    run_optimal, run_service, run_simple, run_worst,
+    ExcludedPolygons, IsochroneConfig, JourneyPlan, JourneyQuery, LAKES_GEOJSON_URLS, RError,
+    RResult, clamp_to_timetable, explain_journey, plan_journey_via, run_average, run_comparison,
+    run_debug, run_optimal, run_service, run_simple, run_validate, run_worst, write_graph,
 };
 #[cfg(feature = "hectare")]
-use hrdf_routing_engine::{HectareData, run_surface_per_ha};
+use hrdf_routing_engine::{HectareData, run_surface_per_ha, run_surface_per_ha_to_geojsonl};
 use log::LevelFilter;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+async fn main() -> RResult<()> {
     simple_logger::SimpleLogger::new()
         .with_level(LevelFilter::Off)
         .with_module_level("hrdf_routing_engine", LevelFilter::Info)
@@ -34,137 +35,203 @@ async fn main() -> Result<(), Box<dyn Error>> {
     )
     .await?;
 
-    match cli.mode {
+    match cli.mode.clone() {
         Mode::Debug => {
             let hrdf =
                 Hrdf::try_from_year(2025, cli.force_rebuild, cli.cache_prefix.clone()).await?;
             run_debug(hrdf);
         }
+        Mode::Validate => {
+            run_validate(
+                &excluded_polygons,
+                cli.force_rebuild,
+                cli.cache_prefix.clone(),
+            )
+            .await?;
+        }
+        Mode::ExportGraph {
+            output_args,
+            format,
+            year,
+        } => {
+            let output_args = output_args.finalize()?;
+            let hrdf =
+                Hrdf::try_from_year(year, cli.force_rebuild, cli.cache_prefix.clone()).await?;
+            let path = output_args.file_path(&format!("graph_{year}"), format.extension());
+            write_graph(hrdf.data_storage(), &path, format)?;
+        }
         Mode::Journey { journey_args } => {
-            let journey_args = journey_args.finalize()?;
+            let mut journey_args = journey_args.finalize()?;
             let hrdf = Hrdf::try_from_date(
                 journey_args.departure_at.date(),
                 cli.force_rebuild,
                 cli.cache_prefix.clone(),
             )
             .await?;
+            journey_args.departure_at = clamp_to_timetable(&hrdf, journey_args.departure_at);
+            journey_args.resolve_stop_names(&hrdf)?;
 
-            let _ = plan_journey(
-                &hrdf,
-                journey_args.departure_stop_id,
-                journey_args.arrival_stop_id,
-                journey_args.departure_at,
-                journey_args.max_num_explorable_connections,
-                journey_args.verbose,
-            )
-            .unwrap_or_else(|| panic!("Error: no journey found for {journey_args}"));
+            let max_transfer_walk = cli.max_transfer_walk.map(Duration::minutes);
+            let plan = if let Some(via_stop_id) = journey_args.via_stop_id {
+                plan_journey_via(
+                    &hrdf,
+                    journey_args.departure_stop_id,
+                    via_stop_id,
+                    journey_args.arrival_stop_id,
+                    journey_args.departure_at,
+                    journey_args.max_num_explorable_connections,
+                    max_transfer_walk,
+                    journey_args.verbose,
+                )
+                .map(JourneyPlan::Transit)
+            } else {
+                JourneyQuery::new(&hrdf)
+                    .from(journey_args.departure_stop_id)
+                    .to(journey_args.arrival_stop_id)
+                    .at(journey_args.departure_at)
+                    .max_num_explorable_connections(journey_args.max_num_explorable_connections)
+                    .max_transfer_walk(max_transfer_walk)
+                    .verbose(journey_args.verbose)
+                    .plan_or_walk()
+            };
+            let plan = plan.ok_or_else(|| RError::NoRouteFound(journey_args.to_string()))?;
+            if journey_args.verbose
+                && let JourneyPlan::WalkOnly { duration } = plan
+            {
+                println!("Approx. {duration}-minute walk, no transit needed");
+            }
+            if journey_args.explain
+                && let Some((_, traces)) = explain_journey(
+                    &hrdf,
+                    journey_args.departure_stop_id,
+                    journey_args.arrival_stop_id,
+                    journey_args.departure_at,
+                    journey_args.max_num_explorable_connections,
+                    max_transfer_walk,
+                )
+            {
+                for trace in traces {
+                    println!("{trace}");
+                }
+            }
         }
-        Mode::Serve { address, ports } => {
+        Mode::Serve {
+            address,
+            ports,
+            warmup,
+        } => {
             let hrdf_2026 =
                 Hrdf::try_from_year(2026, cli.force_rebuild, cli.cache_prefix.clone()).await?;
+            if warmup {
+                hrdf_routing_engine::warmup(&hrdf_2026, &excluded_polygons);
+            }
             let ahrdf = Arc::new(hrdf_2026);
             let services: Vec<_> = ports
                 .into_iter()
                 .map(|p| {
                     let value = excluded_polygons.clone();
                     let hrdf = Arc::clone(&ahrdf);
-                    async move {
-                        run_service(hrdf, cli.num_threads, value, address, p).await;
-                    }
+                    async move { run_service(hrdf, cli.num_threads, value, address, p).await }
                 })
                 .collect();
-            join_all(services).await;
+            join_all(services)
+                .await
+                .into_iter()
+                .collect::<RResult<Vec<_>>>()?;
         }
         Mode::Optimal {
             isochrone_args,
+            output_args,
+            format,
             delta_time,
             mode,
         } => {
-            let isochrone_args = isochrone_args.finalize()?;
+            let mut isochrone_args = isochrone_args.finalize()?;
+            let output_args = output_args.finalize()?;
             let hrdf = Hrdf::try_from_date(
                 isochrone_args.departure_at.date(),
                 cli.force_rebuild,
                 cli.cache_prefix.clone(),
             )
             .await?;
-            run_optimal(
-                hrdf,
-                excluded_polygons,
-                isochrone_args,
-                Duration::minutes(delta_time),
-                mode,
-                cli.num_threads,
-            )?;
+            isochrone_args.departure_at = clamp_to_timetable(&hrdf, isochrone_args.departure_at);
+            let config = isochrone_config(&cli, &excluded_polygons)?
+                .with_delta_time(Duration::minutes(delta_time))
+                .with_display_mode(mode);
+            run_optimal(hrdf, isochrone_args, &config, format, &output_args)?;
         }
         Mode::Worst {
             isochrone_args,
+            output_args,
+            format,
             delta_time,
             mode,
         } => {
-            let isochrone_args = isochrone_args.finalize()?;
+            let mut isochrone_args = isochrone_args.finalize()?;
+            let output_args = output_args.finalize()?;
             let hrdf = Hrdf::try_from_date(
                 isochrone_args.departure_at.date(),
                 cli.force_rebuild,
                 cli.cache_prefix.clone(),
             )
             .await?;
-            run_worst(
-                hrdf,
-                excluded_polygons,
-                isochrone_args,
-                Duration::minutes(delta_time),
-                mode,
-                cli.num_threads,
-            )?;
+            isochrone_args.departure_at = clamp_to_timetable(&hrdf, isochrone_args.departure_at);
+            let config = isochrone_config(&cli, &excluded_polygons)?
+                .with_delta_time(Duration::minutes(delta_time))
+                .with_display_mode(mode);
+            run_worst(hrdf, isochrone_args, &config, format, &output_args)?;
         }
         Mode::Simple {
             isochrone_args,
+            output_args,
+            format,
             mode,
         } => {
-            let isochrone_args = isochrone_args.finalize()?;
+            let mut isochrone_args = isochrone_args.finalize()?;
+            let output_args = output_args.finalize()?;
             let hrdf = Hrdf::try_from_date(
                 isochrone_args.departure_at.date(),
                 cli.force_rebuild,
                 cli.cache_prefix.clone(),
             )
             .await?;
-            run_simple(
-                hrdf,
-                excluded_polygons,
-                isochrone_args,
-                mode,
-                cli.num_threads,
-            )?;
+            isochrone_args.departure_at = clamp_to_timetable(&hrdf, isochrone_args.departure_at);
+            let config = isochrone_config(&cli, &excluded_polygons)?.with_display_mode(mode);
+            run_simple(hrdf, isochrone_args, &config, format, &output_args)?;
         }
         Mode::Average {
             isochrone_args,
+            output_args,
+            format,
             delta_time,
         } => {
-            let isochrone_args = isochrone_args.finalize()?;
+            let mut isochrone_args = isochrone_args.finalize()?;
+            let output_args = output_args.finalize()?;
             let hrdf_2026 = Hrdf::try_from_date(
                 isochrone_args.departure_at.date(),
                 cli.force_rebuild,
                 cli.cache_prefix.clone(),
             )
             .await?;
-            run_average(
-                hrdf_2026,
-                excluded_polygons,
-                isochrone_args,
-                Duration::minutes(delta_time),
-                cli.num_threads,
-            )?;
+            isochrone_args.departure_at =
+                clamp_to_timetable(&hrdf_2026, isochrone_args.departure_at);
+            let config = isochrone_config(&cli, &excluded_polygons)?
+                .with_delta_time(Duration::minutes(delta_time));
+            run_average(hrdf_2026, isochrone_args, &config, format, &output_args)?;
         }
         Mode::Compare {
             isochrone_args,
+            output_args,
+            format,
             mode,
             old_departure_at,
             delta_time,
         } => {
-            let args_new = isochrone_args.clone().finalize()?;
-            let args_old = isochrone_args
+            let mut args_new = isochrone_args.clone().finalize()?;
+            let mut args_old = isochrone_args
                 .set_departure_at(old_departure_at)
                 .finalize()?;
+            let output_args = output_args.finalize()?;
 
             let hrdf_old = Hrdf::try_from_date(
                 args_old.departure_at.date(),
@@ -172,33 +239,38 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 cli.cache_prefix.clone(),
             )
             .await?;
+            args_old.departure_at = clamp_to_timetable(&hrdf_old, args_old.departure_at);
             let hrdf_new = Hrdf::try_from_date(
                 args_new.departure_at.date(),
                 cli.force_rebuild,
-                cli.cache_prefix,
+                cli.cache_prefix.clone(),
             )
             .await?;
+            args_new.departure_at = clamp_to_timetable(&hrdf_new, args_new.departure_at);
+            let config = isochrone_config(&cli, &excluded_polygons)?
+                .with_delta_time(Duration::minutes(delta_time))
+                .with_display_mode(mode);
             run_comparison(
                 hrdf_old,
                 hrdf_new,
-                excluded_polygons,
                 args_old,
                 args_new,
-                Duration::minutes(delta_time),
-                mode,
-                cli.num_threads,
+                &config,
+                format,
+                &output_args,
             )?;
         }
 
         #[cfg(feature = "hectare")]
         Mode::Hectare {
             isochrone_args,
+            output_args,
             delta_time,
             url,
+            geojsonl,
         } => {
-            use hrdf_routing_engine::IsochroneDisplayMode;
-
-            let isochrone_args = isochrone_args.finalize()?;
+            let mut isochrone_args = isochrone_args.finalize()?;
+            let output_args = output_args.finalize()?;
             let hectare =
                 HectareData::new(&url, cli.force_rebuild, cli.cache_prefix.clone()).await?;
             let hrdf_2026 = Hrdf::try_from_date(
@@ -207,25 +279,47 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 cli.cache_prefix.clone(),
             )
             .await?;
-            let surfaces = run_surface_per_ha(
-                hrdf_2026,
-                excluded_polygons,
-                hectare,
-                isochrone_args.clone(),
-                Duration::minutes(delta_time),
-                IsochroneDisplayMode::Circles,
-                cli.num_threads,
-            )?;
-
-            let data = serde_json::to_string_pretty(&surfaces).unwrap();
-            let fname = format!(
-                "hectare_{}_{}.json",
+            isochrone_args.departure_at =
+                clamp_to_timetable(&hrdf_2026, isochrone_args.departure_at);
+            let config = isochrone_config(&cli, &excluded_polygons)?
+                .with_delta_time(Duration::minutes(delta_time));
+            let name = format!(
+                "hectare_{}_{}",
                 isochrone_args.departure_at, isochrone_args.time_limit
             );
-            let mut f = File::create(&fname).expect("Unable to create file");
-            f.write_all(data.as_bytes()).expect("Unable to write data");
+            if geojsonl {
+                let fname = output_args.file_path(&name, "geojsonl");
+                run_surface_per_ha_to_geojsonl(
+                    hrdf_2026,
+                    hectare,
+                    isochrone_args,
+                    &config,
+                    &fname,
+                )?;
+            } else {
+                let surfaces =
+                    run_surface_per_ha(hrdf_2026, hectare, isochrone_args.clone(), &config)?;
+
+                let data = serde_json::to_string_pretty(&surfaces)?;
+                let fname = output_args.file_path(&name, "json");
+                let mut f = File::create(&fname)?;
+                f.write_all(data.as_bytes())?;
+            }
         }
     }
 
     Ok(())
 }
+
+/// The isochrone configuration shared by the isochrone modes, set from the global options.
+fn isochrone_config<'a>(
+    cli: &Cli,
+    excluded_polygons: &'a MultiPolygon,
+) -> RResult<IsochroneConfig<'a>> {
+    Ok(IsochroneConfig::new(excluded_polygons)
+        .with_search_step(Duration::seconds(cli.search_step))?
+        .with_num_threads(cli.num_threads)
+        .with_max_transfer_walk(cli.max_transfer_walk.map(Duration::minutes))
+        .with_max_access_walk(cli.max_access_walk)
+        .with_simplify_tolerance(cli.simplify_tolerance))
+}
@@ -0,0 +1,106 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::error::{RError, RResult};
+
+/// Where the CLI modes write their results.
+#[derive(Debug, Clone)]
+pub struct OutputArgs {
+    out_dir: PathBuf,
+    out_name: Option<String>,
+}
+
+impl OutputArgs {
+    /// Creates the output directory if it doesn't exist and checks that it is writable.
+    pub fn try_new(out_dir: impl AsRef<Path>, out_name: Option<String>) -> RResult<Self> {
+        let out_dir = out_dir.as_ref().to_path_buf();
+
+        fs::create_dir_all(&out_dir)
+            .map_err(|e| RError::OutputDirNotWritable(out_dir.clone(), e))?;
+        let is_read_only = fs::metadata(&out_dir)
+            .map_err(|e| RError::OutputDirNotWritable(out_dir.clone(), e))?
+            .permissions()
+            .readonly();
+        if is_read_only {
+            return Err(RError::OutputDirNotWritable(
+                out_dir,
+                std::io::ErrorKind::PermissionDenied.into(),
+            ));
+        }
+
+        Ok(Self { out_dir, out_name })
+    }
+
+    pub fn out_dir(&self) -> &Path {
+        &self.out_dir
+    }
+
+    /// Returns the path of an output file. The name given with `--out-name` takes precedence
+    /// over the default one.
+    pub fn file_path(&self, default_name: &str, extension: &str) -> PathBuf {
+        let name = self.out_name.as_deref().unwrap_or(default_name);
+        self.out_dir.join(format!("{name}.{extension}"))
+    }
+
+    /// Same as `file_path`, but a custom name gets the suffix appended so that modes writing
+    /// several files don't overwrite them.
+    pub fn file_path_with_suffix(
+        &self,
+        default_name: &str,
+        suffix: &str,
+        extension: &str,
+    ) -> PathBuf {
+        match &self.out_name {
+            Some(out_name) => self
+                .out_dir
+                .join(format!("{out_name}_{suffix}.{extension}")),
+            None => self.out_dir.join(format!("{default_name}.{extension}")),
+        }
+    }
+}
+
+impl Default for OutputArgs {
+    fn default() -> Self {
+        Self {
+            out_dir: PathBuf::from("."),
+            out_name: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_file_path_defaults() {
+        let output = OutputArgs::default();
+        assert_eq!(
+            output.file_path("isochrones_60_10", "svg"),
+            PathBuf::from("./isochrones_60_10.svg")
+        );
+        assert_eq!(
+            output.file_path_with_suffix("isochrones_2025_60_10", "old", "svg"),
+            PathBuf::from("./isochrones_2025_60_10.svg")
+        );
+    }
+
+    #[test]
+    fn test_file_path_with_out_name() {
+        let out_dir = env::temp_dir().join("hrdf_routing_engine_output_test");
+        let output = OutputArgs::try_new(&out_dir, Some(String::from("geneva"))).unwrap();
+        assert!(out_dir.is_dir());
+        assert_eq!(
+            output.file_path("isochrones_60_10", "svg"),
+            out_dir.join("geneva.svg")
+        );
+        assert_eq!(
+            output.file_path_with_suffix("isochrones_2025_60_10", "old", "svg"),
+            out_dir.join("geneva_old.svg")
+        );
+    }
+}
@@ -3,22 +3,51 @@ mod core;
 mod display;
 mod exploration;
 mod models;
+#[cfg(feature = "ojp")]
+mod ojp;
+mod query;
+mod restrictions;
 mod route_impl;
 mod utils;
 
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use crate::error::{RError, RResult};
+use crate::isochrone::constants::WALKING_SPEED_IN_KILOMETERS_PER_HOUR;
 use crate::isochrone::utils::adjust_departure_at;
+use crate::isochrone::utils::time_to_distance;
+use crate::isochrone::utils::walking_time_to_stop;
 use crate::isochrone::utils::wgs84_to_lv95;
-use crate::routing::models::Transport;
+use crate::stop_index::StopIndex;
+use crate::utils::log_prefix;
+pub(crate) use connections::{departures_within_window, get_operating_journeys};
 use hrdf_parser::DataStorage;
 use hrdf_parser::Hrdf;
 use hrdf_parser::Model;
 use hrdf_parser::Stop;
 use hrdf_parser::{CoordinateSystem, Coordinates};
+pub use models::ComparisonCriterion;
+pub use models::ComparisonTrace;
+pub use models::DelaySource;
+pub use models::JourneyPlan;
+pub use models::NoDelays;
+pub use models::OccupancyLevel;
+pub use models::OccupancySource;
+pub use models::ReachableStopsPage;
+pub use models::RouteDiff;
 pub use models::RouteResult as Route;
 pub use models::RouteSectionResult as RouteSection;
+pub use models::SectionDifference;
+pub use models::Transport;
 use orx_parallel::*;
+pub use query::JourneyQuery;
+pub use restrictions::BoardingRestrictions;
+use rustc_hash::FxHashMap;
 
-use core::compute_routing;
+use core::{compute_routing, compute_routing_with_traces};
+use utils::get_stop_connections;
 
 use chrono::{Duration, NaiveDateTime};
 use models::RoutingAlgorithmArgs;
@@ -53,6 +82,130 @@ pub fn plan_journey(
     result
 }
 
+/// Same as plan_journey, the routes being compared on their arrival time plus transfer_penalty per
+/// connection: a route with one connection less is preferred if it arrives at most transfer_penalty
+/// later. A zero penalty gives the same route as plan_journey.
+pub fn plan_journey_with_transfer_penalty(
+    hrdf: &Hrdf,
+    departure_stop_id: i32,
+    arrival_stop_id: i32,
+    departure_at: NaiveDateTime,
+    transfer_penalty: Duration,
+    max_num_explorable_connections: i32,
+    verbose: bool,
+) -> Option<Route> {
+    compute_routing(
+        hrdf.data_storage(),
+        departure_stop_id,
+        departure_at,
+        max_num_explorable_connections,
+        verbose,
+        RoutingAlgorithmArgs::solve_from_departure_stop_to_arrival_stop(arrival_stop_id)
+            .with_transfer_penalty(transfer_penalty),
+    )
+    .remove(&arrival_stop_id)
+}
+
+/// Same as plan_journey with several acceptable arrival stops, e.g. the entrances of an airport or
+/// the platforms of a station. The route arriving the earliest at any of them is returned.
+/// The departure date and time must be within the timetable period.
+pub fn plan_journey_to_any(
+    hrdf: &Hrdf,
+    departure_stop_id: i32,
+    arrival_stop_ids: &[i32],
+    departure_at: NaiveDateTime,
+    max_num_explorable_connections: i32,
+    verbose: bool,
+) -> Option<Route> {
+    if arrival_stop_ids.is_empty() {
+        // Without an arrival stop, the whole network would be explored for nothing.
+        return None;
+    }
+    let mut solutions = compute_routing(
+        hrdf.data_storage(),
+        departure_stop_id,
+        departure_at,
+        max_num_explorable_connections,
+        verbose,
+        RoutingAlgorithmArgs::solve_from_departure_stop_to_any_arrival_stop(
+            arrival_stop_ids.to_vec(),
+        ),
+    );
+    // In the order of arrival_stop_ids on equal arrival times, to be deterministic.
+    let arrival_stop_id = arrival_stop_ids
+        .iter()
+        .filter(|arrival_stop_id| solutions.contains_key(arrival_stop_id))
+        .min_by_key(|arrival_stop_id| solutions[arrival_stop_id].arrival_at())?;
+    let result = solutions.remove(arrival_stop_id);
+
+    if verbose && let Some(rou) = &result {
+        println!();
+        rou.print(hrdf.data_storage());
+    }
+
+    result
+}
+
+/// Same as plan_journey, also returning the routes which lost against the solution at the arrival
+/// stop and the criterion which decided each comparison, to explain why the route was chosen.
+/// Footpaths longer than max_transfer_walk are not used, see JourneyQuery::max_transfer_walk.
+pub fn explain_journey(
+    hrdf: &Hrdf,
+    departure_stop_id: i32,
+    arrival_stop_id: i32,
+    departure_at: NaiveDateTime,
+    max_num_explorable_connections: i32,
+    max_transfer_walk: Option<Duration>,
+) -> Option<(Route, Vec<ComparisonTrace>)> {
+    let (mut solutions, mut traces) = compute_routing_with_traces(
+        hrdf.data_storage(),
+        departure_stop_id,
+        departure_at,
+        max_num_explorable_connections,
+        false,
+        RoutingAlgorithmArgs::solve_from_departure_stop_to_arrival_stop(arrival_stop_id)
+            .with_max_transfer_walk(max_transfer_walk)
+            .with_explain(true),
+    );
+    let route = solutions.remove(&arrival_stop_id)?;
+    Some((route, traces.remove(&arrival_stop_id).unwrap_or_default()))
+}
+
+/// Same as plan_journey, but walking is also considered. If the arrival stop can be reached by
+/// walking from the departure stop (same stop or footpath between them) no later than by
+/// transit, JourneyPlan::WalkOnly is returned instead of a route.
+pub fn plan_journey_or_walk(
+    hrdf: &Hrdf,
+    departure_stop_id: i32,
+    arrival_stop_id: i32,
+    departure_at: NaiveDateTime,
+    max_num_explorable_connections: i32,
+    verbose: bool,
+) -> Option<JourneyPlan> {
+    JourneyQuery::new(hrdf)
+        .from(departure_stop_id)
+        .to(arrival_stop_id)
+        .at(departure_at)
+        .max_num_explorable_connections(max_num_explorable_connections)
+        .verbose(verbose)
+        .plan_or_walk()
+}
+
+/// Walking duration in minutes between two stops, if they are the same or linked by a footpath.
+fn walking_duration(
+    data_storage: &DataStorage,
+    departure_stop_id: i32,
+    arrival_stop_id: i32,
+) -> Option<i16> {
+    if departure_stop_id == arrival_stop_id {
+        return Some(0);
+    }
+    get_stop_connections(data_storage, departure_stop_id)?
+        .into_iter()
+        .find(|stop_connection| stop_connection.stop_id_2() == arrival_stop_id)
+        .map(|stop_connection| stop_connection.duration())
+}
+
 /// Finds the route that takes the least time while arriving the earliest possioble.
 /// It basically moves from the departure stop to the arrival stop.
 /// The departure date and time must be within the timetable period.
@@ -103,43 +256,414 @@ pub fn plan_shortest_journey(
     Some(route)
 }
 
-/// Finds all stops that can be reached within a time limit from the departured stop.
+/// Finds the fastest route from the departure stop to the arrival stop going through the via stop.
+/// The route is planned in two parts, the second one departing from the via stop when the first
+/// one arrives there. The waiting time at the via stop is therefore part of the route.
+/// Returns None if any of the two parts cannot be planned. Footpaths longer than max_transfer_walk
+/// are not used, see JourneyQuery::max_transfer_walk.
+/// The departure date and time must be within the timetable period.
+#[allow(clippy::too_many_arguments)]
+pub fn plan_journey_via(
+    hrdf: &Hrdf,
+    departure_stop_id: i32,
+    via_stop_id: i32,
+    arrival_stop_id: i32,
+    departure_at: NaiveDateTime,
+    max_num_explorable_connections: i32,
+    max_transfer_walk: Option<Duration>,
+    verbose: bool,
+) -> Option<Route> {
+    let plan_part = |from, to, departure_at| {
+        JourneyQuery::new(hrdf)
+            .from(from)
+            .to(to)
+            .at(departure_at)
+            .max_num_explorable_connections(max_num_explorable_connections)
+            .max_transfer_walk(max_transfer_walk)
+            .plan()
+    };
+
+    let Some(first_part) = plan_part(departure_stop_id, via_stop_id, departure_at) else {
+        log::info!("No journey found from {departure_stop_id} to the via stop {via_stop_id}");
+        return None;
+    };
+
+    let Some(second_part) = plan_part(via_stop_id, arrival_stop_id, first_part.arrival_at()) else {
+        log::info!("No journey found from the via stop {via_stop_id} to {arrival_stop_id}");
+        return None;
+    };
+
+    let route = first_part.concat(second_part);
+
+    if verbose {
+        println!();
+        route.print(hrdf.data_storage());
+    }
+
+    Some(route)
+}
+
+/// Number of stops closest to a point tried as departure or arrival stop by
+/// plan_journey_from_coord and plan_journey_to_coord, as the num_starting_points of the isochrones.
+const NUM_ACCESS_STOPS: usize = 5;
+
+/// Same as plan_journey, from a point (WGS84) instead of a stop. The stops closest to the point
+/// are tried as departure stop, the walk to each of them delaying the departure as for the
+/// isochrones, and the route arriving the earliest is returned. It starts with the walk, whose
+/// departure is the point, given the stop id 0.
+/// Only the stops within max_access_walk meters of the point are tried (see
+/// DEFAULT_MAX_ACCESS_WALK_IN_METERS), an error is returned when there is none. They are
+/// searched in stop_index, which must be built from the stops of hrdf.
+#[allow(clippy::too_many_arguments)]
+pub fn plan_journey_from_coord(
+    hrdf: &Hrdf,
+    stop_index: &StopIndex,
+    departure_latitude: f64,
+    departure_longitude: f64,
+    arrival_stop_id: i32,
+    departure_at: NaiveDateTime,
+    max_num_explorable_connections: i32,
+    max_access_walk: f64,
+    verbose: bool,
+) -> RResult<Option<Route>> {
+    let stops = access_stops(
+        hrdf.data_storage(),
+        stop_index,
+        departure_latitude,
+        departure_longitude,
+        max_access_walk,
+    )?;
+    let routes = stops.into_iter().filter_map(|(stop, walk)| {
+        let walk_section =
+            walk_to_or_from_point(departure_latitude, departure_longitude, stop, walk, true);
+        let walk_route = walk_route(departure_at, walk, walk_section);
+        if stop.id() == arrival_stop_id {
+            return Some(walk_route);
+        }
+        let route = plan_journey(
+            hrdf,
+            stop.id(),
+            arrival_stop_id,
+            departure_at + walk,
+            max_num_explorable_connections,
+            false,
+        )?;
+        Some(walk_route.concat(route))
+    });
+    Ok(earliest_route(hrdf, routes, verbose))
+}
+
+/// Same as plan_journey_from_coord, from a stop to a point (WGS84): the stops closest to the point
+/// are tried as arrival stop, and the route ending the earliest at the point is returned. It ends
+/// with the walk, whose arrival is the point, given the stop id 0.
+#[allow(clippy::too_many_arguments)]
+pub fn plan_journey_to_coord(
+    hrdf: &Hrdf,
+    stop_index: &StopIndex,
+    departure_stop_id: i32,
+    arrival_latitude: f64,
+    arrival_longitude: f64,
+    departure_at: NaiveDateTime,
+    max_num_explorable_connections: i32,
+    max_access_walk: f64,
+    verbose: bool,
+) -> RResult<Option<Route>> {
+    let stops = access_stops(
+        hrdf.data_storage(),
+        stop_index,
+        arrival_latitude,
+        arrival_longitude,
+        max_access_walk,
+    )?;
+    let routes = stops.into_iter().filter_map(|(stop, walk)| {
+        let walk_section =
+            walk_to_or_from_point(arrival_latitude, arrival_longitude, stop, walk, false);
+        if stop.id() == departure_stop_id {
+            return Some(walk_route(departure_at, walk, walk_section));
+        }
+        let route = plan_journey(
+            hrdf,
+            departure_stop_id,
+            stop.id(),
+            departure_at,
+            max_num_explorable_connections,
+            false,
+        )?;
+        let arrival_at = route.arrival_at();
+        Some(route.concat(walk_route(arrival_at, walk, walk_section)))
+    });
+    Ok(earliest_route(hrdf, routes, verbose))
+}
+
+/// The route of plan_journey_from_coord and plan_journey_to_coord, the one arriving the earliest.
+fn earliest_route(
+    hrdf: &Hrdf,
+    routes: impl Iterator<Item = Route>,
+    verbose: bool,
+) -> Option<Route> {
+    let result = routes.min_by_key(|route| route.arrival_at());
+
+    if verbose && let Some(rou) = &result {
+        println!();
+        rou.print(hrdf.data_storage());
+    }
+
+    result
+}
+
+/// The NUM_ACCESS_STOPS stops closest to the point (WGS84), with the walking time to each of them.
+/// The stops farther than max_access_walk meters are left out, none remaining is an error rather
+/// than a long walk.
+fn access_stops<'a>(
+    data_storage: &'a DataStorage,
+    stop_index: &StopIndex,
+    latitude: f64,
+    longitude: f64,
+    max_access_walk: f64,
+) -> RResult<Vec<(&'a Stop, Duration)>> {
+    let stops: Vec<_> = stop_index
+        .nearest(latitude, longitude, NUM_ACCESS_STOPS)
+        .into_iter()
+        .filter(|(_, distance)| *distance <= max_access_walk)
+        .filter_map(|(stop_id, _)| data_storage.stops().find(stop_id))
+        .filter(|stop| stop.wgs84_coordinates().is_some())
+        .map(|stop| {
+            // Rounded up to the minute as in the walking section, so that the times of the route
+            // add up.
+            let walk = walking_time_to_stop(latitude, longitude, stop);
+            (stop, Duration::minutes((walk.num_seconds() + 59) / 60))
+        })
+        .collect();
+    if stops.is_empty() {
+        return Err(RError::NoStopWithinAccessRange(
+            latitude,
+            longitude,
+            max_access_walk,
+        ));
+    }
+    Ok(stops)
+}
+
+/// The route of the walking section alone, walked from departure_at. Route::departure_at is
+/// before a first walking section and Route::arrival_at after a last one, the route is therefore
+/// given the departure departure_at + walk and the arrival departure_at.
+fn walk_route(departure_at: NaiveDateTime, walk: Duration, walk_section: RouteSection) -> Route {
+    Route::new(departure_at + walk, departure_at, vec![walk_section])
+}
+
+/// The walking section from the point (WGS84) to the stop, or from the stop to the point. The
+/// point is given the stop id 0.
+fn walk_to_or_from_point(
+    latitude: f64,
+    longitude: f64,
+    stop: &Stop,
+    walk: Duration,
+    to_stop: bool,
+) -> RouteSection {
+    let (easting, northing) = wgs84_to_lv95(latitude, longitude);
+    let point = (
+        0,
+        Some(Coordinates::new(CoordinateSystem::LV95, easting, northing)),
+        Some(Coordinates::new(
+            CoordinateSystem::WGS84,
+            latitude,
+            longitude,
+        )),
+    );
+    let stop = (stop.id(), stop.lv95_coordinates(), stop.wgs84_coordinates());
+    let (departure, arrival) = if to_stop {
+        (point, stop)
+    } else {
+        (stop, point)
+    };
+    // The sections give the walking time in whole minutes, see access_stops.
+    let minutes = walk.num_minutes();
+    RouteSection::new(
+        None,
+        departure.0,
+        departure.1,
+        departure.2,
+        arrival.0,
+        arrival.1,
+        arrival.2,
+        None,
+        None,
+        Some(i16::try_from(minutes).unwrap_or(i16::MAX)),
+        Transport::Walk,
+    )
+}
+
+/// Finds all stops that can be reached within a time limit from the departure stop.
+/// One route is returned per reachable stop, the one arriving the earliest.
+/// Footpaths between two stops longer than max_transfer_walk are not used, None means no limit.
+/// 15 minutes is a sensible limit, longer footpaths are rarely taken.
 /// The departure date and time must be within the timetable period.
-#[allow(dead_code)]
 pub fn find_reachable_stops_within_time_limit(
     hrdf: &Hrdf,
     departure_stop_id: i32,
     departure_at: NaiveDateTime,
     time_limit: Duration,
     max_num_explorable_connections: i32,
+    max_transfer_walk: Option<Duration>,
     verbose: bool,
 ) -> Vec<Route> {
-    let routes = compute_routing(
+    find_reachable_stops_from_origins(
+        hrdf,
+        &[departure_stop_id],
+        departure_at,
+        time_limit,
+        max_num_explorable_connections,
+        max_transfer_walk,
+        None,
+        verbose,
+    )
+}
+
+/// Same as find_reachable_stops_within_time_limit from several departure stops, e.g. the stops
+/// reachable from any of the depots. Per reachable stop, the route arriving the earliest from any
+/// of the departure stops is returned.
+/// The label, e.g. a request id, is included in the log messages.
+#[allow(clippy::too_many_arguments)]
+pub fn find_reachable_stops_from_origins(
+    hrdf: &Hrdf,
+    departure_stop_ids: &[i32],
+    departure_at: NaiveDateTime,
+    time_limit: Duration,
+    max_num_explorable_connections: i32,
+    max_transfer_walk: Option<Duration>,
+    label: Option<&str>,
+    verbose: bool,
+) -> Vec<Route> {
+    let mut earliest_routes: FxHashMap<i32, Route> = FxHashMap::default();
+    for &departure_stop_id in departure_stop_ids {
+        let routes = compute_routing(
+            hrdf.data_storage(),
+            departure_stop_id,
+            departure_at,
+            max_num_explorable_connections,
+            verbose,
+            RoutingAlgorithmArgs::solve_from_departure_stop_to_reachable_arrival_stops(
+                departure_at.checked_add_signed(time_limit).unwrap(),
+            )
+            .with_max_transfer_walk(max_transfer_walk)
+            .with_label(label.map(String::from)),
+        );
+        for (stop_id, route) in routes {
+            if earliest_routes
+                .get(&stop_id)
+                .is_none_or(|earliest| route.arrival_at() < earliest.arrival_at())
+            {
+                earliest_routes.insert(stop_id, route);
+            }
+        }
+    }
+    earliest_routes.into_values().collect()
+}
+
+/// Sorts the routes of find_reachable_stops_within_time_limit (or of another search of the
+/// reachable stops) by arrival time, the arrival stop ids breaking the ties, and keeps at most
+/// limit of them from offset, all the remaining ones if limit is None. The page gives the total
+/// number of routes so that the following pages can be requested.
+pub fn paginate_reachable_stops(
+    mut routes: Vec<Route>,
+    offset: usize,
+    limit: Option<usize>,
+) -> ReachableStopsPage {
+    let total = routes.len();
+    routes.sort_by_key(|route| (route.arrival_at(), route.arrival_stop_id()));
+    let routes = routes
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+    ReachableStopsPage::new(total, offset, routes)
+}
+
+/// Returns the earliest arrival date and time at every stop reachable within the time limit from
+/// the departure stop. It is the table find_reachable_stops_within_time_limit builds its routes
+/// from, without the routes.
+/// The departure date and time must be within the timetable period.
+pub fn earliest_arrivals(
+    hrdf: &Hrdf,
+    departure_stop_id: i32,
+    departure_at: NaiveDateTime,
+    time_limit: Duration,
+    max_num_explorable_connections: i32,
+) -> FxHashMap<i32, NaiveDateTime> {
+    compute_routing(
         hrdf.data_storage(),
         departure_stop_id,
         departure_at,
         max_num_explorable_connections,
-        verbose,
+        false,
         RoutingAlgorithmArgs::solve_from_departure_stop_to_reachable_arrival_stops(
             departure_at.checked_add_signed(time_limit).unwrap(),
         ),
-    );
-    routes.into_values().collect()
+    )
+    .into_iter()
+    .map(|(stop_id, route)| (stop_id, route.arrival_at()))
+    .collect()
+}
+
+/// Returns the travel time from each origin stop (rows) to each destination stop (columns), None
+/// when the destination cannot be reached within the time limit. The reachable stops are searched
+/// once per origin, the origins being processed in parallel on num_threads threads (0 for the
+/// global pool).
+/// The departure date and time must be within the timetable period.
+pub fn travel_time_matrix(
+    hrdf: &Hrdf,
+    origins: &[i32],
+    destinations: &[i32],
+    departure_at: NaiveDateTime,
+    time_limit: Duration,
+    max_num_explorable_connections: i32,
+    num_threads: usize,
+) -> Vec<Vec<Option<Duration>>> {
+    origins
+        .par()
+        .num_threads(num_threads)
+        .map(|&origin| {
+            let arrivals = earliest_arrivals(
+                hrdf,
+                origin,
+                departure_at,
+                time_limit,
+                max_num_explorable_connections,
+            );
+            destinations
+                .iter()
+                .map(|destination| {
+                    if *destination == origin {
+                        return Some(Duration::zero());
+                    }
+                    arrivals
+                        .get(destination)
+                        .map(|arrival_at| *arrival_at - departure_at)
+                })
+                .collect()
+        })
+        .collect()
 }
 
 // Find the stop in walking range. The stops are sorted by time to destination
-fn find_stops_in_time_range(
-    data_storage: &DataStorage,
+fn find_stops_in_time_range<'a>(
+    data_storage: &'a DataStorage,
+    stop_index: &StopIndex,
     origin_point_latitude: f64,
     origin_point_longitude: f64,
     departure_at: NaiveDateTime,
     time_limit: Duration,
-) -> Vec<&Stop> {
-    let mut stops = data_storage
-        .stops()
-        .entries()
+    max_access_walk: f64,
+) -> Vec<&'a Stop> {
+    // The index distances are in LV95 and slightly differ from the haversine distance used for
+    // the walking time, the margin makes sure no stop in range is missed.
+    let radius = time_to_distance(time_limit, WALKING_SPEED_IN_KILOMETERS_PER_HOUR) * 1.1 + 100.0;
+    // The access walk is checked on the index distance, as by plan_journey_from_coord.
+    let radius = radius.min(max_access_walk);
+    let mut stops = stop_index
+        .within_radius(origin_point_latitude, origin_point_longitude, radius)
         .into_iter()
-        .filter(|stop| stop.wgs84_coordinates().is_some())
+        .filter_map(|stop_id| data_storage.stops().find(stop_id))
         .filter(|stop| {
             adjust_departure_at(
                 departure_at,
@@ -152,7 +676,6 @@ fn find_stops_in_time_range(
             .num_minutes()
                 > 0
         })
-        // The stop list cannot be empty.
         .collect::<Vec<_>>();
     stops.sort_by(|lhs, rhs| {
         adjust_departure_at(
@@ -179,11 +702,23 @@ fn find_stops_in_time_range(
     stops
 }
 
-/// Given a starting point (long/lat) find the Routes given a time limit.
-/// We first find num_starting_points stops that are reachable by foot
+/// Given a starting point (WGS84 latitude/longitude) finds the routes reachable within time_limit.
+///
+/// The num_starting_points stops closest (in walking time) to the origin are used as departure
+/// stops, the walking time to each of them being deducted from the time limit. The routes of all
+/// departure stops are merged: each stop is reached by its earliest arriving route, whichever
+/// departure stop it comes from.
+/// The last route of the result is a placeholder representing the origin point itself.
+/// See find_reachable_stops_within_time_limit for max_transfer_walk.
+/// The stops farther than max_access_walk meters from the origin are not departure stops, without
+/// any RError::NoStopWithinAccessRange is returned.
+/// The departure stops are searched in stop_index, which must be built from the stops of hrdf.
+/// The label, e.g. a request id, is included in the log messages.
+/// The departure date and time must be within the timetable period.
 #[allow(clippy::too_many_arguments)]
 pub fn compute_routes_from_origin(
     hrdf: &Hrdf,
+    stop_index: &StopIndex,
     origin_point_latitude: f64,
     origin_point_longitude: f64,
     departure_at: NaiveDateTime,
@@ -191,24 +726,44 @@ pub fn compute_routes_from_origin(
     num_starting_points: usize,
     num_threads: usize,
     max_num_explorable_connections: i32,
+    max_transfer_walk: Option<Duration>,
+    max_access_walk: f64,
+    label: Option<&str>,
     verbose: bool,
-) -> Vec<Route> {
+) -> RResult<Vec<Route>> {
+    if stop_index
+        .within_radius(
+            origin_point_latitude,
+            origin_point_longitude,
+            max_access_walk,
+        )
+        .is_empty()
+    {
+        return Err(RError::NoStopWithinAccessRange(
+            origin_point_latitude,
+            origin_point_longitude,
+            max_access_walk,
+        ));
+    }
+
     // Create a list of stops close enough to be of interest
-    // We limit ourselves to the 10 closest. It may not be the best choice but otherwise the
-    // computation becomes very slow due to the combinatory nature of the problem
+    // We limit ourselves to the num_starting_points closest. It may not be the best choice but
+    // otherwise the computation becomes very slow due to the combinatory nature of the problem
     let departure_stops = find_stops_in_time_range(
         hrdf.data_storage(),
+        stop_index,
         origin_point_latitude,
         origin_point_longitude,
         departure_at,
         time_limit,
+        max_access_walk,
     )
     .into_iter()
     .take(num_starting_points)
     .collect::<Vec<_>>();
 
     // then go over all these stops to compute each attainable route
-    let mut routes = departure_stops
+    let routes = departure_stops
         .par()
         .num_threads(num_threads)
         .flat_map(|departure_stop| {
@@ -222,25 +777,29 @@ pub fn compute_routes_from_origin(
             );
             if verbose {
                 log::info!(
-                    "Departure stop : {:?}, Adjusted departure at : {:?}, Adjusted time limit : {:?}",
+                    "{}Departure stop : {:?}, Adjusted departure at : {:?}, Adjusted time limit : {:?}",
+                    log_prefix(label),
                     departure_stop,
                     adjusted_departure_at,
                     adjusted_time_limit
                 );
             }
 
-            let local_routes: Vec<_> = find_reachable_stops_within_time_limit(
+            let local_routes: Vec<_> = find_reachable_stops_from_origins(
                 hrdf,
-                departure_stop.id(),
+                &[departure_stop.id()],
                 adjusted_departure_at,
                 adjusted_time_limit,
-        max_num_explorable_connections,
+                max_num_explorable_connections,
+                max_transfer_walk,
+                label,
                 verbose,
             );
 
             local_routes
         })
         .collect::<Vec<_>>();
+    let mut routes = keep_earliest_route_per_stop(routes);
 
     // A false route is created to represent the point of origin in the results.
     let (easting, northing) = wgs84_to_lv95(origin_point_latitude, origin_point_longitude);
@@ -262,5 +821,207 @@ pub fn compute_routes_from_origin(
         )],
     );
     routes.push(route);
+    Ok(routes)
+}
+
+/// Writes the routes, e.g. those of compute_routes_from_origin, as JSON so that they can be
+/// analysed or compared later without computing them again.
+pub fn write_routes(routes: &[Route], path: &Path) -> RResult<()> {
+    let file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(file, routes)?;
+    Ok(())
+}
+
+/// Reads the routes written by write_routes.
+pub fn read_routes(path: &Path) -> RResult<Vec<Route>> {
+    let file = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// Keeps the earliest arriving route of each arrival stop, sorted by arrival stop id so that the
+/// result doesn't depend on the order of the hash map.
+fn keep_earliest_route_per_stop(routes: Vec<Route>) -> Vec<Route> {
+    let mut earliest_routes: FxHashMap<i32, Route> = FxHashMap::default();
+    for route in routes {
+        let Some(arrival_stop_id) = route.sections().last().map(|s| s.arrival_stop_id()) else {
+            continue;
+        };
+        if earliest_routes
+            .get(&arrival_stop_id)
+            .is_none_or(|earliest| route.arrival_at() < earliest.arrival_at())
+        {
+            earliest_routes.insert(arrival_stop_id, route);
+        }
+    }
+    let mut routes: Vec<_> = earliest_routes.into_values().collect();
+    routes.sort_by_key(Route::arrival_stop_id);
     routes
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use test_log::test;
+
+    use super::*;
+
+    #[test]
+    fn test_write_and_read_routes() {
+        let departure_at =
+            NaiveDateTime::parse_from_str("2025-06-15 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let arrival_at = departure_at + Duration::minutes(65);
+        // The arrival of the route is the one of its last vehicle section, before walking.
+        let routes = vec![
+            Route::new(
+                departure_at,
+                arrival_at - Duration::minutes(5),
+                vec![
+                    RouteSection::new(
+                        Some(1),
+                        8503000,
+                        Some(Coordinates::new(
+                            CoordinateSystem::LV95,
+                            2683211.0,
+                            1248094.0,
+                        )),
+                        None,
+                        8507000,
+                        None,
+                        None,
+                        Some(departure_at),
+                        Some(arrival_at - Duration::minutes(5)),
+                        None,
+                        Transport::Train,
+                    ),
+                    RouteSection::new(
+                        None,
+                        8507000,
+                        None,
+                        None,
+                        8507100,
+                        None,
+                        None,
+                        None,
+                        None,
+                        Some(5),
+                        Transport::Walk,
+                    ),
+                ],
+            ),
+            Route::new(departure_at, departure_at, Vec::new()),
+        ];
+
+        let path = env::temp_dir().join("hrdf_routing_engine_routes_test.json");
+        write_routes(&routes, &path).unwrap();
+        let read = read_routes(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(&read).unwrap(),
+            serde_json::to_value(&routes).unwrap()
+        );
+        assert_eq!(read[0].arrival_at(), arrival_at);
+        assert_eq!(read[0].sections().len(), 2);
+        assert!(read_routes(&path.with_extension("missing")).is_err());
+    }
+
+    #[test]
+    fn test_keep_earliest_route_per_stop() {
+        let departure_at =
+            NaiveDateTime::parse_from_str("2025-06-15 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let walk_to = |stop_id, minutes| {
+            Route::new(
+                departure_at,
+                departure_at,
+                vec![RouteSection::new(
+                    None,
+                    8503000,
+                    None,
+                    None,
+                    stop_id,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(i16::try_from(minutes).unwrap()),
+                    Transport::Walk,
+                )],
+            )
+        };
+        // The routes of two departure stops, both reaching the stops 1 and 2. A route ending with a
+        // walk arrives after walking.
+        let routes = vec![
+            walk_to(2, 15),
+            walk_to(1, 20),
+            walk_to(3, 30),
+            walk_to(1, 10),
+            walk_to(2, 25),
+        ];
+
+        let arrivals: Vec<_> = keep_earliest_route_per_stop(routes)
+            .iter()
+            .map(|route| (route.arrival_stop_id().unwrap(), route.arrival_at()))
+            .collect();
+        assert_eq!(
+            arrivals,
+            [
+                (1, departure_at + Duration::minutes(10)),
+                (2, departure_at + Duration::minutes(15)),
+                (3, departure_at + Duration::minutes(30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paginate_reachable_stops() {
+        let departure_at =
+            NaiveDateTime::parse_from_str("2025-06-15 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let walk_to = |stop_id, minutes| {
+            Route::new(
+                departure_at,
+                departure_at + Duration::minutes(minutes),
+                vec![RouteSection::new(
+                    None,
+                    8503000,
+                    None,
+                    None,
+                    stop_id,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(i16::try_from(minutes).unwrap()),
+                    Transport::Walk,
+                )],
+            )
+        };
+        let routes = vec![
+            walk_to(3, 30),
+            walk_to(1, 10),
+            walk_to(4, 20),
+            walk_to(2, 10),
+            walk_to(5, 40),
+        ];
+        let arrival_stop_ids = |page: &ReachableStopsPage| {
+            page.routes()
+                .iter()
+                .map(|route| route.arrival_stop_id().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let page = paginate_reachable_stops(routes.clone(), 0, Some(2));
+        assert_eq!(page.total(), 5);
+        assert_eq!(arrival_stop_ids(&page), [1, 2]);
+
+        let page = paginate_reachable_stops(routes.clone(), 2, Some(2));
+        assert_eq!(page.offset(), 2);
+        assert_eq!(arrival_stop_ids(&page), [4, 3]);
+
+        let page = paginate_reachable_stops(routes.clone(), 4, None);
+        assert_eq!(arrival_stop_ids(&page), [5]);
+        assert!(
+            paginate_reachable_stops(routes, 10, Some(2))
+                .routes()
+                .is_empty()
+        );
+    }
+}
@@ -1,17 +1,30 @@
-use chrono::{Duration, NaiveDate, NaiveDateTime};
-use hrdf_parser::{DataStorage, Journey, Model, TransportType, timetable_end_date};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use hrdf_parser::{DataStorage, Journey, Model, timetable_end_date};
 use rustc_hash::FxHashSet;
 
 use crate::utils::{
     add_1_day, add_minutes_to_date_time, count_days_between_two_dates, create_time,
 };
 
-use super::{models::Route, utils::get_routes_to_ignore};
+use super::{
+    models::Route,
+    restrictions::BoardingRestrictions,
+    utils::{can_board_at, get_routes_to_ignore},
+};
+
+/// Default look-ahead window when searching the next departures from a stop.
+pub const DEFAULT_DEPARTURE_WINDOW: Duration = Duration::hours(4);
+
+/// Default start of the service day, the departures before it bridge the night.
+pub const DEFAULT_SERVICE_DAY_START: NaiveTime = NaiveTime::from_hms_opt(8, 0, 0).unwrap();
 
 pub fn get_connections(
     data_storage: &DataStorage,
     route: &Route,
     journeys_to_ignore: &FxHashSet<i32>,
+    departure_window: Duration,
+    service_day_start: NaiveTime,
+    restrictions: Option<&BoardingRestrictions>,
 ) -> Vec<Route> {
     next_departures(
         data_storage,
@@ -19,6 +32,9 @@ pub fn get_connections(
         route.arrival_at(),
         Some(get_routes_to_ignore(data_storage, route)),
         route.last_section().journey_id(),
+        departure_window,
+        service_day_start,
+        restrictions,
     )
     .into_iter()
     // A journey is removed if it has already been explored at a lower connection level.
@@ -29,79 +45,34 @@ pub fn get_connections(
             journey.id(),
             journey_departure_at.date(),
             true,
+            restrictions,
         )
     })
     .collect()
 }
 
-pub fn next_departures(
-    data_storage: &DataStorage,
+/// Returns the journeys departing from the stop within the look-ahead window, see
+/// departures_within_window, keeping the first departure to each terminus which leaves enough
+/// time to change from the previous journey.
+#[allow(clippy::too_many_arguments)]
+pub fn next_departures<'a>(
+    data_storage: &'a DataStorage,
     departure_stop_id: i32,
     departure_at: NaiveDateTime,
     routes_to_ignore: Option<FxHashSet<u64>>,
     previous_journey_id: Option<i32>,
-) -> Vec<(&Journey, NaiveDateTime)> {
-    fn get_journeys(
-        data_storage: &DataStorage,
-        date: NaiveDate,
-        stop_id: i32,
-    ) -> (Vec<(&Journey, NaiveDateTime)>, NaiveDateTime) {
-        let mut max_departure_at = NaiveDateTime::new(date, create_time(0, 0));
-
-        let journeys = get_operating_journeys(data_storage, date, stop_id)
-            .into_iter()
-            .filter(|journey| {
-                !journey.is_last_stop(stop_id, true).unwrap()
-                    && journey.departure_at_of(stop_id, date).is_ok()
-            })
-            .map(|journey| {
-                let journey_departure_at = journey.departure_at_of(stop_id, date).unwrap();
-                if journey_departure_at > max_departure_at {
-                    max_departure_at = journey_departure_at;
-                }
-                (journey, journey_departure_at)
-            })
-            .collect();
-        (journeys, max_departure_at)
-    }
-
-    let (journeys_1, mut max_depearture_at_journeys_1_adjusted) =
-        get_journeys(data_storage, departure_at.date(), departure_stop_id);
-    max_depearture_at_journeys_1_adjusted = max_depearture_at_journeys_1_adjusted
-        .checked_add_signed(Duration::hours(-4))
-        .unwrap();
-
-    let (journeys_2, max_departure_at) = if departure_at > max_depearture_at_journeys_1_adjusted {
-        // The journeys of the next day are also loaded.
-        // The maximum departure time is 08:00 the next day.
-        let departure_date = add_1_day(departure_at.date());
-        let (journeys, _) = get_journeys(data_storage, departure_date, departure_stop_id);
-        let max_departure_at = NaiveDateTime::new(departure_date, create_time(8, 0));
-
-        (journeys, max_departure_at)
-    } else {
-        let max_departure_at = if departure_at.time() < create_time(8, 0) {
-            // The maximum departure time is 08:00.
-            NaiveDateTime::new(departure_at.date(), create_time(8, 0))
-        } else {
-            // The maximum departure time is 4 hours later.
-            departure_at.checked_add_signed(Duration::hours(4)).unwrap()
-        };
-
-        (Vec::new(), max_departure_at)
-    };
-
-    let mut journeys: Vec<(&Journey, NaiveDateTime)> = [journeys_1, journeys_2]
-        .concat()
-        .into_iter()
-        .filter(|&(_, journey_departure_at)| {
-            // Journeys that depart too early or too late are ignored.
-            journey_departure_at >= departure_at && journey_departure_at <= max_departure_at
-        })
-        .collect();
-
-    // Journeys are sorted by ascending departure time, allowing them to be filtered correctly afterwards.
-    journeys.sort_by_key(|(_, journey_departure_at)| *journey_departure_at);
+    departure_window: Duration,
+    service_day_start: NaiveTime,
+    restrictions: Option<&BoardingRestrictions>,
+) -> Vec<(&'a Journey, NaiveDateTime)> {
+    let journeys = departures_within_window(
+        data_storage,
+        departure_stop_id,
+        departure_at,
+        departure_window,
+        service_day_start,
+        restrictions,
+    );
 
     let mut routes_to_ignore = routes_to_ignore.unwrap_or_default();
 
@@ -157,6 +128,141 @@ pub fn next_departures(
         .collect()
 }
 
+/// Returns the journeys which can be boarded at the stop within the look-ahead window, see
+/// departure_window_end, with their departure date and time, sorted by departure.
+///
+/// get_operating_journeys intersects the bit fields of the stop with those of a single day, the
+/// day the journeys start. A journey running past midnight is therefore only found from its
+/// starting day: until the last departure of the journeys of the previous day, they are also
+/// loaded, so that their departures after midnight are found, and when the window reaches the next
+/// day its journeys are loaded too, with the validity of that next day. Neither is loaded beyond the
+/// timetable period.
+pub(crate) fn departures_within_window<'a>(
+    data_storage: &'a DataStorage,
+    departure_stop_id: i32,
+    departure_at: NaiveDateTime,
+    departure_window: Duration,
+    service_day_start: NaiveTime,
+    restrictions: Option<&BoardingRestrictions>,
+) -> Vec<(&'a Journey, NaiveDateTime)> {
+    fn get_journeys<'a>(
+        data_storage: &'a DataStorage,
+        date: NaiveDate,
+        stop_id: i32,
+        restrictions: Option<&BoardingRestrictions>,
+    ) -> (Vec<(&'a Journey, NaiveDateTime)>, NaiveDateTime) {
+        let mut max_departure_at = NaiveDateTime::new(date, create_time(0, 0));
+
+        let journeys = get_operating_journeys(data_storage, date, stop_id)
+            .into_iter()
+            .filter(|journey| {
+                !journey.is_last_stop(stop_id, true).unwrap()
+                    && can_board_at(journey, stop_id, restrictions)
+                    && journey.departure_at_of(stop_id, date).is_ok()
+            })
+            .map(|journey| {
+                let journey_departure_at = journey.departure_at_of(stop_id, date).unwrap();
+                if journey_departure_at > max_departure_at {
+                    max_departure_at = journey_departure_at;
+                }
+                (journey, journey_departure_at)
+            })
+            .collect();
+        (journeys, max_departure_at)
+    }
+
+    let journeys_0 = match departure_at.date().pred_opt() {
+        Some(previous_date)
+            if data_storage
+                .bit_fields_by_day()
+                .contains_key(&previous_date) =>
+        {
+            // The journeys of the previous day still running after midnight are kept, whatever
+            // the start of the service day.
+            let (journeys, max_departure_at_journeys_0) =
+                get_journeys(data_storage, previous_date, departure_stop_id, restrictions);
+            if departure_at <= max_departure_at_journeys_0 {
+                journeys
+            } else {
+                Vec::new()
+            }
+        }
+        _ => Vec::new(),
+    };
+
+    let (journeys_1, max_departure_at_journeys_1) = get_journeys(
+        data_storage,
+        departure_at.date(),
+        departure_stop_id,
+        restrictions,
+    );
+
+    let (load_next_day, max_departure_at) = departure_window_end(
+        departure_at,
+        max_departure_at_journeys_1,
+        departure_window,
+        service_day_start,
+    );
+
+    let next_date = add_1_day(departure_at.date());
+    let journeys_2 = if load_next_day && data_storage.bit_fields_by_day().contains_key(&next_date) {
+        // The journeys of the next day are also loaded, with their validity on that day.
+        let (journeys, _) = get_journeys(data_storage, next_date, departure_stop_id, restrictions);
+        journeys
+    } else {
+        Vec::new()
+    };
+
+    let mut journeys: Vec<(&Journey, NaiveDateTime)> = [journeys_0, journeys_1, journeys_2]
+        .concat()
+        .into_iter()
+        .filter(|&(_, journey_departure_at)| {
+            // Journeys that depart too early or too late are ignored.
+            journey_departure_at >= departure_at && journey_departure_at <= max_departure_at
+        })
+        .collect();
+
+    // Journeys are sorted by ascending departure time, allowing them to be filtered correctly afterwards.
+    journeys.sort_by_key(|(_, journey_departure_at)| *journey_departure_at);
+    journeys
+}
+
+/// Computes the latest departure time considered when looking for the next departures.
+/// Returns whether the journeys of the next day must be loaded as well, and the latest departure.
+///
+/// The journeys of the next day are loaded when departure_at is less than departure_window before
+/// the last departure of the day (max_departure_at_of_day). Departures until service_day_start
+/// (08:00 by default, the next day when it is loaded) are always considered to bridge the night.
+/// Before service_day_start, only a window wider than DEFAULT_DEPARTURE_WINDOW extends past it.
+/// With a continuous service, e.g. a metro running all night, an earlier service_day_start keeps
+/// the window from reaching needlessly far, midnight disabling the bridging.
+fn departure_window_end(
+    departure_at: NaiveDateTime,
+    max_departure_at_of_day: NaiveDateTime,
+    departure_window: Duration,
+    service_day_start: NaiveTime,
+) -> (bool, NaiveDateTime) {
+    let window_end = departure_at.checked_add_signed(departure_window).unwrap();
+
+    if departure_at > max_departure_at_of_day - departure_window {
+        // The maximum departure time is at least the start of the next service day.
+        let next_service_day_start =
+            NaiveDateTime::new(add_1_day(departure_at.date()), service_day_start);
+        (true, window_end.max(next_service_day_start))
+    } else if departure_at.time() < service_day_start {
+        // The maximum departure time is the end of the night, unless a wider window is requested.
+        let end_of_night = NaiveDateTime::new(departure_at.date(), service_day_start);
+        if departure_window > DEFAULT_DEPARTURE_WINDOW {
+            (false, window_end.max(end_of_night))
+        } else {
+            (false, end_of_night)
+        }
+    } else {
+        // The maximum departure time is departure_window later.
+        (false, window_end)
+    }
+}
+
 pub fn get_operating_journeys(
     data_storage: &DataStorage,
     date: NaiveDate,
@@ -209,6 +315,9 @@ fn has_through_service(
     })
 }
 
+/// Minimum time in minutes to change from journey_id_1 to journey_id_2 at the stop. The first
+/// exchange time found applies: journey pair, administrations at the stop, stop, administrations
+/// (global) and the default exchange time.
 pub fn get_exchange_time(
     data_storage: &DataStorage,
     stop_id: i32,
@@ -263,10 +372,12 @@ pub fn get_exchange_time(
             exchange_time,
             journey_1
                 .transport_type(data_storage)
-                .unwrap_or_else(|_| panic!("Error: {journey_1:?} does not have a TransportType.")),
+                .unwrap_or_else(|_| panic!("Error: {journey_1:?} does not have a TransportType."))
+                .designation(),
             journey_2
                 .transport_type(data_storage)
-                .unwrap_or_else(|_| panic!("Error: {journey_2:?} does not have a TransportType.")),
+                .unwrap_or_else(|_| panic!("Error: {journey_2:?} does not have a TransportType."))
+                .designation(),
         );
     }
 
@@ -290,10 +401,12 @@ pub fn get_exchange_time(
         data_storage.default_exchange_time(),
         journey_1
             .transport_type(data_storage)
-            .unwrap_or_else(|_| panic!("Error: {journey_1:?} does not have a TransportType.")),
+            .unwrap_or_else(|_| panic!("Error: {journey_1:?} does not have a TransportType."))
+            .designation(),
         journey_2
             .transport_type(data_storage)
-            .unwrap_or_else(|_| panic!("Error: {journey_2:?} does not have a TransportType.")),
+            .unwrap_or_else(|_| panic!("Error: {journey_2:?} does not have a TransportType."))
+            .designation(),
     )
 }
 
@@ -344,14 +457,377 @@ fn exchange_time_journey_pair(
     None
 }
 
+/// The exchange time is given as (IC to IC, other transfers), the transport types by their
+/// designation.
 fn exchange_time_at_stop(
     exchange_time: (i16, i16),
-    transport_type_1: &TransportType,
-    transport_type_2: &TransportType,
+    designation_1: &str,
+    designation_2: &str,
 ) -> i16 {
-    if transport_type_1.designation() == "IC" && transport_type_2.designation() == "IC" {
+    if designation_1 == "IC" && designation_2 == "IC" {
         exchange_time.0
     } else {
         exchange_time.1
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hrdf_parser::Hrdf;
+    use test_log::test;
+
+    use chrono::Timelike;
+
+    use super::*;
+    use crate::utils::{create_date, create_date_time, fixture_hrdf};
+
+    #[test]
+    fn test_departure_window_end_default_daytime() {
+        let departure_at = create_date_time(2025, 6, 15, 9, 0);
+        let last_departure = create_date_time(2025, 6, 15, 23, 30);
+
+        let (load_next_day, max_departure_at) = departure_window_end(
+            departure_at,
+            last_departure,
+            DEFAULT_DEPARTURE_WINDOW,
+            DEFAULT_SERVICE_DAY_START,
+        );
+
+        assert!(!load_next_day);
+        assert_eq!(max_departure_at, create_date_time(2025, 6, 15, 13, 0));
+    }
+
+    #[test]
+    fn test_departure_window_end_default_early_morning() {
+        let departure_at = create_date_time(2025, 6, 15, 2, 30);
+        let last_departure = create_date_time(2025, 6, 15, 23, 30);
+
+        let (load_next_day, max_departure_at) = departure_window_end(
+            departure_at,
+            last_departure,
+            DEFAULT_DEPARTURE_WINDOW,
+            DEFAULT_SERVICE_DAY_START,
+        );
+
+        assert!(!load_next_day);
+        assert_eq!(max_departure_at, create_date_time(2025, 6, 15, 8, 0));
+
+        let departure_at = create_date_time(2025, 6, 15, 5, 59);
+        let (_, max_departure_at) = departure_window_end(
+            departure_at,
+            last_departure,
+            DEFAULT_DEPARTURE_WINDOW,
+            DEFAULT_SERVICE_DAY_START,
+        );
+        assert_eq!(max_departure_at, create_date_time(2025, 6, 15, 8, 0));
+
+        let (_, max_departure_at) = departure_window_end(
+            departure_at,
+            last_departure,
+            Duration::hours(6),
+            DEFAULT_SERVICE_DAY_START,
+        );
+        assert_eq!(max_departure_at, create_date_time(2025, 6, 15, 11, 59));
+    }
+
+    #[test]
+    fn test_departure_window_end_default_late_evening() {
+        let departure_at = create_date_time(2025, 6, 15, 21, 0);
+        let last_departure = create_date_time(2025, 6, 15, 23, 30);
+
+        let (load_next_day, max_departure_at) = departure_window_end(
+            departure_at,
+            last_departure,
+            DEFAULT_DEPARTURE_WINDOW,
+            DEFAULT_SERVICE_DAY_START,
+        );
+
+        assert!(load_next_day);
+        assert_eq!(max_departure_at, create_date_time(2025, 6, 16, 8, 0));
+    }
+
+    #[test]
+    fn test_departure_window_end_sparse_service() {
+        // Rural stop served at 07:00, 13:00 and 19:00 (6h headways).
+        let departure_at = create_date_time(2025, 6, 15, 7, 30);
+        let last_departure = create_date_time(2025, 6, 15, 19, 0);
+        let next_trip = create_date_time(2025, 6, 15, 13, 0);
+
+        let (_, max_departure_at) = departure_window_end(
+            departure_at,
+            last_departure,
+            DEFAULT_DEPARTURE_WINDOW,
+            DEFAULT_SERVICE_DAY_START,
+        );
+        assert!(
+            max_departure_at < next_trip,
+            "The default window misses the trip"
+        );
+
+        let (load_next_day, max_departure_at) = departure_window_end(
+            departure_at,
+            last_departure,
+            Duration::hours(8),
+            DEFAULT_SERVICE_DAY_START,
+        );
+        assert!(!load_next_day);
+        assert!(
+            max_departure_at >= next_trip,
+            "The larger window finds the trip"
+        );
+    }
+
+    #[test]
+    fn test_departure_window_end_crossing_midnight() {
+        // The window reaches past the last departure of the day, the next day must be loaded.
+        let departure_at = create_date_time(2025, 6, 15, 14, 0);
+        let last_departure = create_date_time(2025, 6, 15, 19, 0);
+
+        let (load_next_day, max_departure_at) = departure_window_end(
+            departure_at,
+            last_departure,
+            Duration::hours(12),
+            DEFAULT_SERVICE_DAY_START,
+        );
+
+        assert!(load_next_day);
+        assert_eq!(max_departure_at, create_date_time(2025, 6, 16, 8, 0));
+
+        let (load_next_day, max_departure_at) = departure_window_end(
+            departure_at,
+            last_departure,
+            Duration::hours(20),
+            DEFAULT_SERVICE_DAY_START,
+        );
+
+        assert!(load_next_day);
+        assert_eq!(max_departure_at, create_date_time(2025, 6, 16, 10, 0));
+    }
+
+    #[test]
+    fn test_departure_window_end_continuous_service() {
+        // The service day starts at midnight, the night is not bridged.
+        let service_day_start = create_time(0, 0);
+        let last_departure = create_date_time(2025, 6, 15, 23, 58);
+
+        let (load_next_day, max_departure_at) = departure_window_end(
+            create_date_time(2025, 6, 15, 2, 30),
+            last_departure,
+            DEFAULT_DEPARTURE_WINDOW,
+            service_day_start,
+        );
+        assert!(!load_next_day);
+        assert_eq!(max_departure_at, create_date_time(2025, 6, 15, 6, 30));
+
+        let (load_next_day, max_departure_at) = departure_window_end(
+            create_date_time(2025, 6, 15, 23, 0),
+            last_departure,
+            DEFAULT_DEPARTURE_WINDOW,
+            service_day_start,
+        );
+        assert!(load_next_day);
+        assert_eq!(max_departure_at, create_date_time(2025, 6, 16, 3, 0));
+    }
+
+    /// A journey starting on a day and departing from a stop after 03:00 the next day must be
+    /// found from that next day, whose own journeys do not include it.
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_next_departures_overnight() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let data_storage = hrdf.data_storage();
+        // A Friday, with night services.
+        let date = create_date(2025, 6, 13);
+        let next_date = add_1_day(date);
+
+        // Zürich HB, Zürich Stadelhofen, Bern, Basel SBB, Luzern and Winterthur.
+        let (stop_id, journey, departure_at) =
+            [8503000, 8503003, 8507000, 8500010, 8505000, 8506000]
+                .into_iter()
+                .flat_map(|stop_id| {
+                    get_operating_journeys(data_storage, date, stop_id)
+                        .into_iter()
+                        .filter(move |journey| {
+                            !journey.is_last_stop(stop_id, true).unwrap()
+                                && can_board_at(journey, stop_id, None)
+                        })
+                        .filter_map(move |journey| {
+                            let departure_at = journey.departure_at_of(stop_id, date).ok()?;
+                            (departure_at.date() == next_date && departure_at.hour() >= 3)
+                                .then_some((stop_id, journey, departure_at))
+                        })
+                })
+                .min_by_key(|&(_, _, departure_at)| departure_at)
+                .unwrap();
+        assert!(departure_at.time() < DEFAULT_SERVICE_DAY_START);
+
+        let departures = next_departures(
+            data_storage,
+            stop_id,
+            departure_at - Duration::minutes(30),
+            None,
+            None,
+            DEFAULT_DEPARTURE_WINDOW,
+            DEFAULT_SERVICE_DAY_START,
+            None,
+        );
+        // Only the first journey to each terminus is kept.
+        let hash = journey.hash_route(stop_id).unwrap();
+        assert!(
+            departures.iter().any(|&(other, other_departure_at)| {
+                other.hash_route(stop_id).unwrap() == hash && other_departure_at <= departure_at
+            }),
+            "Journey {} departing from {stop_id} at {departure_at} not found",
+            journey.id()
+        );
+    }
+
+    /// The journeys of the next day are those running on that day: a journey running on Friday
+    /// but not on Saturday is not offered on Saturday morning to a departure on Friday evening.
+    /// On the last day of the timetable, the next day is not loaded.
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_next_departures_next_day_validity() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let data_storage = hrdf.data_storage();
+        let friday = create_date(2025, 6, 13);
+        let saturday = add_1_day(friday);
+
+        // Zürich HB, Bern, Basel SBB and Luzern.
+        for stop_id in [8503000, 8507000, 8500010, 8505000] {
+            let saturday_journey_ids: FxHashSet<_> =
+                get_operating_journeys(data_storage, saturday, stop_id)
+                    .into_iter()
+                    .map(|journey| journey.id())
+                    .collect();
+            let friday_journeys = get_operating_journeys(data_storage, friday, stop_id);
+            // The test is only meaningful with journeys running on weekdays only.
+            assert!(
+                friday_journeys
+                    .iter()
+                    .any(|journey| !saturday_journey_ids.contains(&journey.id())),
+                "No weekday-only journey at {stop_id}"
+            );
+
+            let departure_at = NaiveDateTime::new(friday, create_time(23, 0));
+            let departures = next_departures(
+                data_storage,
+                stop_id,
+                departure_at,
+                None,
+                None,
+                DEFAULT_DEPARTURE_WINDOW,
+                DEFAULT_SERVICE_DAY_START,
+                None,
+            );
+            for (journey, journey_departure_at) in departures {
+                if journey_departure_at.date() != saturday
+                    || saturday_journey_ids.contains(&journey.id())
+                {
+                    continue;
+                }
+                // Otherwise it must be a journey of Friday running past midnight.
+                assert!(
+                    friday_journeys
+                        .iter()
+                        .any(|other| other.id() == journey.id()),
+                    "Journey {} departing from {stop_id} at {journey_departure_at} does not run on {saturday}",
+                    journey.id()
+                );
+                assert_eq!(
+                    journey.departure_at_of(stop_id, friday).unwrap(),
+                    journey_departure_at
+                );
+            }
+        }
+
+        let last_date = timetable_end_date(data_storage.timetable_metadata()).unwrap();
+        let departures = next_departures(
+            data_storage,
+            8503000,
+            NaiveDateTime::new(last_date, create_time(23, 0)),
+            None,
+            None,
+            DEFAULT_DEPARTURE_WINDOW,
+            DEFAULT_SERVICE_DAY_START,
+            None,
+        );
+        assert!(
+            departures
+                .iter()
+                .all(|&(_, departure_at)| departure_at.date() <= add_1_day(last_date))
+        );
+    }
+
+    /// SN 501 leaves Golf at 00:11 the day after it starts. It is found at 00:05 even when the
+    /// service day starts at midnight.
+    #[test]
+    fn test_next_departures_after_midnight_with_continuous_service() {
+        let hrdf = fixture_hrdf();
+        let departures = next_departures(
+            hrdf.data_storage(),
+            8500007,
+            create_date_time(2025, 6, 14, 0, 5),
+            None,
+            None,
+            DEFAULT_DEPARTURE_WINDOW,
+            create_time(0, 0),
+            None,
+        );
+        assert_eq!(departures.len(), 1);
+        let (journey, departure_at) = departures[0];
+        assert_eq!(journey.id(), 9);
+        assert_eq!(departure_at, create_date_time(2025, 6, 14, 0, 11));
+    }
+
+    #[test]
+    fn test_exchange_time_at_stop() {
+        assert_eq!(exchange_time_at_stop((3, 5), "IC", "IC"), 3);
+        assert_eq!(exchange_time_at_stop((3, 5), "IC", "IR"), 5);
+        assert_eq!(exchange_time_at_stop((3, 5), "B", "IC"), 5);
+        assert_eq!(exchange_time_at_stop((3, 5), "S", "S"), 5);
+    }
+
+    #[test]
+    fn test_exchange_time_resolution_order() {
+        let hrdf = fixture_hrdf();
+        let data_storage = hrdf.data_storage();
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        // The journeys of the fixture: IC 101 (1), IC 102 (2), IC 105 (3), B 201 (4), B 203 (5)
+        // and R 301 (6), the IC and R of SBB, the B of PAG.
+        let (charlie, delta) = (8500003, 8500004);
+
+        for (stop_id, journey_id_1, journey_id_2, expected) in [
+            // Journey pair, preferred over the administrations at the stop.
+            (charlie, 1, 4, 1),
+            // Administrations at the stop, preferred over the stop.
+            (charlie, 3, 5, 3),
+            // Stop, preferred over the global administrations (4 from PAG to SBB).
+            (charlie, 4, 1, 5),
+            (charlie, 2, 3, 2),
+            // Global administrations, preferred over the default.
+            (delta, 4, 6, 4),
+            // Default
+            (delta, 6, 4, 6),
+            (delta, 1, 3, 3),
+        ] {
+            assert_eq!(
+                get_exchange_time(
+                    data_storage,
+                    stop_id,
+                    journey_id_1,
+                    journey_id_2,
+                    departure_at
+                ),
+                expected,
+                "stop: {stop_id}, journeys: {journey_id_1} - {journey_id_2}"
+            );
+        }
+    }
+}
@@ -1,16 +1,25 @@
-use chrono::NaiveDateTime;
+use std::time::Instant;
+
+use chrono::{Duration, NaiveDateTime, NaiveTime};
 use hrdf_parser::DataStorage;
 use rustc_hash::{FxHashMap, FxHashSet};
 
-use crate::utils::add_minutes_to_date_time;
+use crate::utils::{add_minutes_to_date_time, log_prefix};
 
 use super::{
     connections::next_departures,
     exploration::explore_routes,
-    models::{Route, RouteResult, RouteSection, RoutingAlgorithmArgs, RoutingAlgorithmMode},
-    utils::{RouteQueue, get_stop_connections},
+    models::{
+        ComparisonCriterion, ComparisonTrace, Route, RouteResult, RouteSection,
+        RoutingAlgorithmArgs, RoutingAlgorithmMode,
+    },
+    restrictions::BoardingRestrictions,
+    utils::{RouteQueue, can_alight_at, date_time_at_position, get_walkable_stop_connections},
 };
 
+/// The routes which lost against the solution at each stop.
+pub type ComparisonTraces = FxHashMap<i32, Vec<ComparisonTrace>>;
+
 pub fn compute_routing(
     data_storage: &DataStorage,
     departure_stop_id: i32,
@@ -19,9 +28,40 @@ pub fn compute_routing(
     verbose: bool,
     args: RoutingAlgorithmArgs,
 ) -> FxHashMap<i32, RouteResult> {
-    let mut routes = create_initial_routes(data_storage, departure_stop_id, departure_at);
+    compute_routing_with_traces(
+        data_storage,
+        departure_stop_id,
+        departure_at,
+        max_num_explorable_connections,
+        verbose,
+        args,
+    )
+    .0
+}
+
+/// Same as compute_routing, the traces are only recorded if args.explain() is set.
+pub fn compute_routing_with_traces(
+    data_storage: &DataStorage,
+    departure_stop_id: i32,
+    departure_at: NaiveDateTime,
+    max_num_explorable_connections: i32,
+    verbose: bool,
+    args: RoutingAlgorithmArgs,
+) -> (FxHashMap<i32, RouteResult>, ComparisonTraces) {
+    let start_time = Instant::now();
+    let prefix = log_prefix(args.label());
+    let mut routes = create_initial_routes(
+        data_storage,
+        departure_stop_id,
+        departure_at,
+        args.departure_window(),
+        args.service_day_start(),
+        args.max_transfer_walk(),
+        args.boarding_restrictions(),
+    );
     let mut earliest_arrival_by_stop_id = FxHashMap::default();
     let mut solutions = FxHashMap::default();
+    let mut traces = args.explain().then(FxHashMap::default);
 
     let mut journeys_to_ignore = routes
         .iter_routes()
@@ -30,28 +70,46 @@ pub fn compute_routing(
 
     for i in 0..max_num_explorable_connections {
         if verbose {
-            log::info!("For connection {i}, routes length: {}", routes.len());
+            log::info!(
+                "{prefix}For connection {i}, routes length: {}",
+                routes.len()
+            );
         }
 
         let can_continue_exploration: Box<dyn FnMut(&Route) -> bool> = match args.mode() {
-            RoutingAlgorithmMode::SolveFromDepartureStopToArrivalStop => Box::new(|route| {
+            RoutingAlgorithmMode::ToArrivalStop => Box::new(|route| {
                 can_continue_exploration_one_to_one(
                     data_storage,
                     route,
                     &mut solutions,
+                    &mut traces,
                     args.arrival_stop_id(),
+                    args.transfer_penalty(),
+                    args.boarding_restrictions(),
+                )
+            }),
+            RoutingAlgorithmMode::ToAnyArrivalStop => Box::new(|route| {
+                can_continue_exploration_one_to_any(
+                    data_storage,
+                    route,
+                    &mut solutions,
+                    &mut traces,
+                    args.arrival_stop_ids(),
+                    args.transfer_penalty(),
+                    args.boarding_restrictions(),
+                )
+            }),
+            RoutingAlgorithmMode::ToReachableStops => Box::new(|route| {
+                can_continue_exploration_one_to_many(
+                    data_storage,
+                    route,
+                    &mut solutions,
+                    &mut traces,
+                    args.time_limit(),
+                    args.transfer_penalty(),
+                    args.boarding_restrictions(),
                 )
             }),
-            RoutingAlgorithmMode::SolveFromDepartureStopToReachableArrivalStops => {
-                Box::new(|route| {
-                    can_continue_exploration_one_to_many(
-                        data_storage,
-                        route,
-                        &mut solutions,
-                        args.time_limit(),
-                    )
-                })
-            }
         };
 
         let new_routes = explore_routes(
@@ -59,6 +117,10 @@ pub fn compute_routing(
             routes,
             &mut journeys_to_ignore,
             &mut earliest_arrival_by_stop_id,
+            args.departure_window(),
+            args.service_day_start(),
+            args.max_transfer_walk(),
+            args.boarding_restrictions(),
             can_continue_exploration,
         );
 
@@ -69,35 +131,61 @@ pub fn compute_routing(
         routes = new_routes;
     }
 
-    solutions
+    // Logged in any case, at debug level unless verbose, to profile the requests of the service.
+    log::log!(
+        if verbose {
+            log::Level::Info
+        } else {
+            log::Level::Debug
+        },
+        "{prefix}Time for the routing from {departure_stop_id} : {:.2?}",
+        start_time.elapsed()
+    );
+    let solutions = solutions
         .into_iter()
         .map(|(k, v)| (k, v.to_route_result(data_storage)))
-        .collect()
+        .collect();
+    (solutions, traces.unwrap_or_default())
 }
 
 pub fn create_initial_routes(
     data_storage: &DataStorage,
     departure_stop_id: i32,
     departure_at: NaiveDateTime,
+    departure_window: Duration,
+    service_day_start: NaiveTime,
+    max_transfer_walk: Option<Duration>,
+    restrictions: Option<&BoardingRestrictions>,
 ) -> RouteQueue {
     let mut routes = RouteQueue::new();
 
-    for (journey, journey_departure_at) in
-        next_departures(data_storage, departure_stop_id, departure_at, None, None)
-    {
+    for (journey, journey_departure_at) in next_departures(
+        data_storage,
+        departure_stop_id,
+        departure_at,
+        None,
+        None,
+        departure_window,
+        service_day_start,
+        restrictions,
+    ) {
         if let Some((section, mut visited_stops)) = RouteSection::find_next(
             data_storage,
             journey,
             departure_stop_id,
+            None,
             journey_departure_at.date(),
             true,
+            restrictions,
         ) {
             visited_stops.insert(departure_stop_id);
             routes.push(Route::new(vec![section], visited_stops));
         }
     }
 
-    if let Some(stop_connections) = get_stop_connections(data_storage, departure_stop_id) {
+    if let Some(stop_connections) =
+        get_walkable_stop_connections(data_storage, departure_stop_id, max_transfer_walk)
+    {
         stop_connections.iter().for_each(|stop_connection| {
             let mut visited_stops = FxHashSet::default();
             visited_stops.insert(stop_connection.stop_id_1());
@@ -121,22 +209,81 @@ fn can_continue_exploration_one_to_one(
     data_storage: &DataStorage,
     route: &Route,
     solutions: &mut FxHashMap<i32, Route>,
+    traces: &mut Option<ComparisonTraces>,
     arrival_stop_id: i32,
+    transfer_penalty: Duration,
+    restrictions: Option<&BoardingRestrictions>,
 ) -> bool {
     if !route.visited_stops().contains(&arrival_stop_id) {
         let solution = solutions.get(&arrival_stop_id);
-        return can_improve_solution(route, &solution);
+        return can_improve_solution(route, &solution, transfer_penalty);
     }
 
-    let solution = solutions.get(&arrival_stop_id);
     let candidate = if route.last_section().journey_id().is_none() {
-        route.clone()
+        Some(route.clone())
     } else {
-        update_arrival_stop(data_storage, route.clone(), arrival_stop_id)
+        position_on_last_section(data_storage, route, arrival_stop_id).and_then(|position| {
+            update_arrival_stop(data_storage, route.clone(), position, restrictions)
+        })
     };
+    let Some(candidate) = candidate else {
+        // The journey goes through the arrival stop without letting the passengers alight.
+        let solution = solutions.get(&arrival_stop_id);
+        return can_improve_solution(route, &solution, transfer_penalty);
+    };
+    keep_best_solution(
+        data_storage,
+        candidate,
+        arrival_stop_id,
+        solutions,
+        traces,
+        transfer_penalty,
+    );
 
-    if is_improving_solution(data_storage, &candidate, &solution) {
-        solutions.insert(arrival_stop_id, candidate);
+    false
+}
+
+/// Same as can_continue_exploration_one_to_one, a route reaching any of the arrival stops being a
+/// solution. The other routes are only explored while they can beat the earliest solution.
+fn can_continue_exploration_one_to_any(
+    data_storage: &DataStorage,
+    route: &Route,
+    solutions: &mut FxHashMap<i32, Route>,
+    traces: &mut Option<ComparisonTraces>,
+    arrival_stop_ids: &[i32],
+    transfer_penalty: Duration,
+    restrictions: Option<&BoardingRestrictions>,
+) -> bool {
+    let reached_stop_ids: Vec<_> = arrival_stop_ids
+        .iter()
+        .copied()
+        .filter(|arrival_stop_id| route.visited_stops().contains(arrival_stop_id))
+        .collect();
+    if reached_stop_ids.is_empty() {
+        let solution = solutions
+            .values()
+            .min_by_key(|solution| solution.arrival_at());
+        return can_improve_solution(route, &solution, transfer_penalty);
+    }
+
+    for arrival_stop_id in reached_stop_ids {
+        let candidate = if route.last_section().journey_id().is_none() {
+            Some(route.clone())
+        } else {
+            position_on_last_section(data_storage, route, arrival_stop_id).and_then(|position| {
+                update_arrival_stop(data_storage, route.clone(), position, restrictions)
+            })
+        };
+        if let Some(candidate) = candidate {
+            keep_best_solution(
+                data_storage,
+                candidate,
+                arrival_stop_id,
+                solutions,
+                traces,
+                transfer_penalty,
+            );
+        }
     }
 
     false
@@ -146,112 +293,211 @@ fn can_continue_exploration_one_to_many(
     data_storage: &DataStorage,
     route: &Route,
     solutions: &mut FxHashMap<i32, Route>,
+    traces: &mut Option<ComparisonTraces>,
     time_limit: NaiveDateTime,
+    transfer_penalty: Duration,
+    restrictions: Option<&BoardingRestrictions>,
 ) -> bool {
     fn evaluate_candidate(
         data_storage: &DataStorage,
         candidate: Route,
         solutions: &mut FxHashMap<i32, Route>,
+        traces: &mut Option<ComparisonTraces>,
         time_limit: NaiveDateTime,
+        transfer_penalty: Duration,
     ) {
         if candidate.arrival_at() > time_limit {
             return;
         }
 
         let arrival_stop_id = candidate.arrival_stop_id();
-        let solution = solutions.get(&arrival_stop_id);
-
-        if is_improving_solution(data_storage, &candidate, &solution) {
-            solutions.insert(arrival_stop_id, candidate);
-        }
+        keep_best_solution(
+            data_storage,
+            candidate,
+            arrival_stop_id,
+            solutions,
+            traces,
+            transfer_penalty,
+        );
     }
 
     if route.last_section().journey_id().is_none() {
-        evaluate_candidate(data_storage, route.clone(), solutions, time_limit);
+        evaluate_candidate(
+            data_storage,
+            route.clone(),
+            solutions,
+            traces,
+            time_limit,
+            transfer_penalty,
+        );
     } else {
-        let last_section = route.last_section();
-        let journey = last_section.journey(data_storage).unwrap();
+        let (departure_position, arrival_position) = route_positions(route.last_section());
 
-        for route_entry in journey.route_section(
-            last_section.departure_stop_id(),
-            last_section.arrival_stop_id(),
-        ) {
-            let candidate = update_arrival_stop(data_storage, route.clone(), route_entry.stop_id());
-            evaluate_candidate(data_storage, candidate, solutions, time_limit);
+        for position in departure_position + 1..=arrival_position {
+            if let Some(candidate) =
+                update_arrival_stop(data_storage, route.clone(), position, restrictions)
+            {
+                evaluate_candidate(
+                    data_storage,
+                    candidate,
+                    solutions,
+                    traces,
+                    time_limit,
+                    transfer_penalty,
+                );
+            }
         }
     }
 
     route.arrival_at() < time_limit
 }
 
+/// The positions of the departure and arrival stops of a section having a journey.
+fn route_positions(section: &RouteSection) -> (usize, usize) {
+    section
+        .departure_position()
+        .zip(section.arrival_position())
+        .unwrap_or_else(|| panic!("No route positions on {section:?}"))
+}
+
+/// The first position of the stop on the journey route after the departure of the last section,
+/// None if the last section doesn't go through it.
+/// Do not call this function if route.last_section().journey_id() is None.
+fn position_on_last_section(
+    data_storage: &DataStorage,
+    route: &Route,
+    stop_id: i32,
+) -> Option<usize> {
+    let last_section = route.last_section();
+    let (departure_position, arrival_position) = route_positions(last_section);
+    let journey = last_section.journey(data_storage).unwrap();
+    (departure_position + 1..=arrival_position)
+        .find(|&position| journey.route()[position].stop_id() == stop_id)
+}
+
+/// Ends the last section of the route at the stop at arrival_position on the journey route, None
+/// if the journey can't be alighted from there (see can_alight_at).
 /// Do not call this function if route.last_section().journey_id() is None.
 fn update_arrival_stop(
     data_storage: &DataStorage,
     mut route: Route,
-    arrival_stop_id: i32,
-) -> Route {
+    arrival_position: usize,
+    restrictions: Option<&BoardingRestrictions>,
+) -> Option<Route> {
     let last_section = route.last_section();
 
     let journey = last_section.journey(data_storage).unwrap();
-    let arrival_at = journey
-        .arrival_at_of_with_origin(
-            arrival_stop_id,
+    if !can_alight_at(journey, arrival_position, restrictions) {
+        return None;
+    }
+    let arrival_stop_id = journey.route()[arrival_position].stop_id();
+    let arrival_at = date_time_at_position(
+        journey,
+        arrival_position,
+        true,
+        last_section.arrival_at().date(),
+        route_positions(last_section).1,
+        true,
+    )
+    .unwrap_or_else(|| {
+        panic!(
+            "Arrival at not found for {arrival_stop_id}, {}, and {}",
             last_section.arrival_at().date(),
-            false,
-            last_section.arrival_stop_id(),
+            last_section.arrival_stop_id()
         )
-        .unwrap_or_else(|_| {
-            panic!(
-                "Arrival at not found for {arrival_stop_id}, {}, and {}",
-                last_section.arrival_at().date(),
-                last_section.arrival_stop_id()
-            )
-        });
+    });
 
     let last_section = route.last_section_mut();
     last_section.set_arrival_stop_id(arrival_stop_id);
     last_section.set_arrival_at(arrival_at);
+    last_section.set_arrival_position(Some(arrival_position));
 
-    route
+    Some(route)
 }
 
-fn can_improve_solution(route: &Route, solution: &Option<&Route>) -> bool {
+/// The route can still beat the solution while it arrives no later than the effective arrival of
+/// the solution (see effective_arrival_at), its own penalty being at least zero.
+fn can_improve_solution(
+    route: &Route,
+    solution: &Option<&Route>,
+    transfer_penalty: Duration,
+) -> bool {
     solution
         .as_ref()
-        .is_none_or(|sol| route.arrival_at() <= sol.arrival_at())
+        .is_none_or(|sol| route.arrival_at() <= effective_arrival_at(sol, transfer_penalty))
 }
 
-fn is_improving_solution(
+/// The arrival time plus the transfer penalty once per connection.
+fn effective_arrival_at(route: &Route, transfer_penalty: Duration) -> NaiveDateTime {
+    route.arrival_at() + transfer_penalty * route.count_connections() as i32
+}
+
+/// Replaces the solution at the stop by the candidate if it is better. The losing route is added
+/// to the traces, if they are recorded.
+fn keep_best_solution(
     data_storage: &DataStorage,
+    candidate: Route,
+    arrival_stop_id: i32,
+    solutions: &mut FxHashMap<i32, Route>,
+    traces: &mut Option<ComparisonTraces>,
+    transfer_penalty: Duration,
+) {
+    let solution = solutions.get(&arrival_stop_id);
+    let (is_improving, criterion) = is_improving_solution(&candidate, &solution, transfer_penalty);
+
+    if let (Some(traces), Some(criterion)) = (traces, criterion) {
+        let loser = if is_improving {
+            solution
+        } else {
+            Some(&candidate)
+        };
+        if let Some(loser) = loser {
+            traces
+                .entry(arrival_stop_id)
+                .or_default()
+                .push(ComparisonTrace::new(
+                    loser.to_route_result(data_storage),
+                    criterion,
+                ));
+        }
+    }
+
+    if is_improving {
+        solutions.insert(arrival_stop_id, candidate);
+    }
+}
+
+/// Also returns the criterion which decided, None for the first solution found.
+fn is_improving_solution(
     candidate: &Route,
     solution: &Option<&Route>,
-) -> bool {
-    fn count_stops(data_storage: &DataStorage, section: &RouteSection) -> usize {
-        section
-            .journey(data_storage)
-            .unwrap()
-            .count_stops(section.departure_stop_id(), section.arrival_stop_id())
+    transfer_penalty: Duration,
+) -> (bool, Option<ComparisonCriterion>) {
+    fn count_stops(section: &RouteSection) -> usize {
+        let (departure_position, arrival_position) = route_positions(section);
+        arrival_position - departure_position + 1
     }
 
-    if candidate.sections().len() == 1 && candidate.last_section().journey_id().is_none() {
-        // If the candidate contains only a walking trip, it is not a valid solution.
-        return false;
+    if !candidate.has_vehicle_section() {
+        // If the candidate contains only walking trips, it is not a valid solution.
+        return (false, Some(ComparisonCriterion::NoVehicleSection));
     }
 
     if solution.is_none() {
         // If this is the first solution found, then we keep the candidate as the solution.
-        return true;
+        return (true, None);
     }
 
     let solution = solution.unwrap();
 
     // A variable suffixed with 1 will always correspond to the candiate, suffixed with 2 will correspond to the solution.
-    let t1 = candidate.arrival_at();
-    let t2 = solution.arrival_at();
+    // The arrival times include the transfer penalty, they are the actual ones when it is zero.
+    let t1 = effective_arrival_at(candidate, transfer_penalty);
+    let t2 = effective_arrival_at(solution, transfer_penalty);
 
     if t1 != t2 {
         // If the candidate arrives earlier than the solution, then it is a better solution.
-        return t1 < t2;
+        return (t1 < t2, Some(ComparisonCriterion::ArrivalTime));
     }
 
     let connection_count_1 = candidate.count_connections();
@@ -259,7 +505,10 @@ fn is_improving_solution(
 
     if connection_count_1 != connection_count_2 {
         // If the candidate requires fewer connections, then it is a better solution.
-        return connection_count_1 < connection_count_2;
+        return (
+            connection_count_1 < connection_count_2,
+            Some(ComparisonCriterion::ConnectionCount),
+        );
     }
 
     let sections_1 = candidate.sections_having_journey();
@@ -267,15 +516,156 @@ fn is_improving_solution(
 
     // Compare each connection.
     for i in 0..connection_count_1 {
-        let stop_count_1 = count_stops(data_storage, sections_1[i]);
-        let stop_count_2 = count_stops(data_storage, sections_2[i]);
+        let stop_count_1 = count_stops(sections_1[i]);
+        let stop_count_2 = count_stops(sections_2[i]);
 
         if stop_count_1 != stop_count_2 {
             // If the candidate crosses more stops than the solution, then it is a better solution.
-            return stop_count_1 > stop_count_2;
+            return (
+                stop_count_1 > stop_count_2,
+                Some(ComparisonCriterion::StopCount),
+            );
         }
     }
 
     // The current solution is better than the candidate.
-    false
+    (false, Some(ComparisonCriterion::Tie))
+}
+
+#[cfg(test)]
+mod tests {
+    use hrdf_parser::{Hrdf, Model};
+    use test_log::test;
+
+    use crate::{
+        routing::{
+            connections::{DEFAULT_DEPARTURE_WINDOW, DEFAULT_SERVICE_DAY_START},
+            utils::can_board_at,
+        },
+        utils::{create_date_time, fixture_boarding_restrictions, fixture_hrdf},
+    };
+
+    use super::*;
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_traces_explain_the_solution() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let data_storage = hrdf.data_storage();
+        let departure_at = create_date_time(2025, 6, 2, 8, 0);
+        // Zürich HB => Bern
+        let (departure_stop_id, arrival_stop_id) = (8503000, 8507000);
+        let args =
+            || RoutingAlgorithmArgs::solve_from_departure_stop_to_arrival_stop(arrival_stop_id);
+
+        let (solutions, traces) = compute_routing_with_traces(
+            data_storage,
+            departure_stop_id,
+            departure_at,
+            10,
+            false,
+            args(),
+        );
+        assert!(traces.is_empty());
+
+        let (explained_solutions, traces) = compute_routing_with_traces(
+            data_storage,
+            departure_stop_id,
+            departure_at,
+            10,
+            false,
+            args().with_explain(true),
+        );
+        let route = &explained_solutions[&arrival_stop_id];
+        assert_eq!(route.arrival_at(), solutions[&arrival_stop_id].arrival_at());
+
+        let traces = &traces[&arrival_stop_id];
+        assert!(!traces.is_empty());
+        // Apart from the walking only routes, no loser arrives earlier than the solution.
+        assert!(
+            traces
+                .iter()
+                .filter(|trace| trace.criterion() != ComparisonCriterion::NoVehicleSection)
+                .all(|trace| trace.loser().arrival_at() >= route.arrival_at())
+        );
+    }
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_max_transfer_walk_excludes_long_footpaths() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let data_storage = hrdf.data_storage();
+        let departure_at = create_date_time(2025, 6, 15, 12, 10);
+
+        let longest = data_storage
+            .stop_connections()
+            .entries()
+            .into_iter()
+            .max_by_key(|stop_connection| stop_connection.duration())
+            .unwrap();
+        let walking_durations = |max_transfer_walk| {
+            create_initial_routes(
+                data_storage,
+                longest.stop_id_1(),
+                departure_at,
+                DEFAULT_DEPARTURE_WINDOW,
+                DEFAULT_SERVICE_DAY_START,
+                max_transfer_walk,
+                None,
+            )
+            .iter_routes()
+            .filter(|route| route.last_section().journey_id().is_none())
+            .map(|route| route.last_section().duration().unwrap())
+            .collect::<Vec<_>>()
+        };
+
+        assert!(walking_durations(None).contains(&longest.duration()));
+        let max_transfer_walk = longest.duration() - 1;
+        assert!(
+            walking_durations(Some(Duration::minutes(max_transfer_walk.into())))
+                .iter()
+                .all(|&duration| duration <= max_transfer_walk)
+        );
+    }
+
+    #[test]
+    fn test_board_only_stop_is_not_an_arrival() {
+        let hrdf = fixture_hrdf();
+        let data_storage = hrdf.data_storage();
+        let restrictions = fixture_boarding_restrictions();
+
+        // IC 101 (1) Alpha - Bravo - Charlie can be boarded at Bravo but not left there, IC 102
+        // (2) Charlie - Bravo - Alpha the other way around.
+        let (alpha, bravo, charlie) = (8500001, 8500002, 8500003);
+        let journey = data_storage.journeys().find(1).unwrap();
+        assert!(can_board_at(journey, bravo, Some(&restrictions)));
+        let journey_2 = data_storage.journeys().find(2).unwrap();
+        assert!(!can_board_at(journey_2, bravo, Some(&restrictions)));
+        assert!(can_board_at(journey_2, bravo, None));
+
+        let route = Route::new(
+            vec![
+                RouteSection::new(
+                    Some(journey.id()),
+                    alpha,
+                    charlie,
+                    create_date_time(2025, 6, 2, 8, 12),
+                    None,
+                )
+                .with_positions(0, 2),
+            ],
+            FxHashSet::from_iter([alpha, bravo, charlie]),
+        );
+        // Bravo is the stop at position 1.
+        assert!(update_arrival_stop(data_storage, route.clone(), 1, Some(&restrictions)).is_none());
+        let route = update_arrival_stop(data_storage, route, 1, None).unwrap();
+        assert_eq!(route.arrival_stop_id(), bravo);
+        assert_eq!(route.arrival_at(), create_date_time(2025, 6, 2, 8, 5));
+    }
 }
@@ -11,8 +11,9 @@ impl RouteResult {
             let journey = section.journey(data_storage);
 
             if journey.is_none() {
-                let stop = data_storage.stops().find(section.arrival_stop_id()).unwrap_or_else(|| panic!("Stop {:?} not found.", section.arrival_stop_id()));
-                println!("Approx. {}-minute walk to {}", section.duration().unwrap(), stop.name());
+                // The arrival of the walk to a point (see plan_journey_to_coord) is not a stop.
+                let name = data_storage.stops().find(section.arrival_stop_id()).map_or("the destination", |stop| stop.name());
+                println!("Approx. {}-minute walk to {}", section.duration().unwrap(), name);
                 continue;
             }
 
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDateTime, NaiveTime};
 use hrdf_parser::DataStorage;
 use rustc_hash::{FxHashMap, FxHashSet};
 
@@ -9,14 +9,20 @@ use crate::utils::add_minutes_to_date_time;
 use super::{
     connections::get_connections,
     models::{Route, RouteSection},
-    utils::{RouteQueue, clone_update_route, get_stop_connections},
+    restrictions::BoardingRestrictions,
+    utils::{RouteQueue, clone_update_route, get_walkable_stop_connections},
 };
 
+#[allow(clippy::too_many_arguments)]
 pub fn explore_routes<F>(
     data_storage: &DataStorage,
     mut routes: RouteQueue,
     journeys_to_ignore: &mut FxHashSet<i32>,
     earliest_arrival_by_stop_id: &mut FxHashMap<i32, NaiveDateTime>,
+    departure_window: Duration,
+    service_day_start: NaiveTime,
+    max_transfer_walk: Option<Duration>,
+    restrictions: Option<&BoardingRestrictions>,
     mut can_continue_exploration: F,
 ) -> RouteQueue
 where
@@ -31,12 +37,24 @@ where
         }
 
         if route.last_section().departure_stop_id() == route.last_section().arrival_stop_id() {
-            // Some journeys start and end at the same stop, so it's not possible to know whether the journey has reached its last stop.
-            // The above condition, however, lets us know that the journey is about to loop.
+            // The journey came back to the stop where it was boarded (e.g. a circular line).
+            // Nothing new is reached at this stop, but the journey may go on. It is followed by
+            // position along its route, so it cannot loop forever.
+            explore_last_route_section_more_if_possible(
+                data_storage,
+                &route,
+                restrictions,
+                &mut routes,
+            );
             continue;
         }
 
-        explore_last_route_section_more_if_possible(data_storage, &route, &mut routes);
+        explore_last_route_section_more_if_possible(
+            data_storage,
+            &route,
+            restrictions,
+            &mut routes,
+        );
 
         if !can_explore_connections(data_storage, &route, earliest_arrival_by_stop_id) {
             // In some cases there are stops appearing multiple times in a Journey
@@ -53,8 +71,16 @@ where
             continue;
         }
 
-        explore_nearby_stops(data_storage, &route, &mut routes);
-        explore_connections(data_storage, &route, journeys_to_ignore, &mut new_routes);
+        explore_nearby_stops(data_storage, &route, max_transfer_walk, &mut routes);
+        explore_connections(
+            data_storage,
+            &route,
+            journeys_to_ignore,
+            departure_window,
+            service_day_start,
+            restrictions,
+            &mut new_routes,
+        );
     }
 
     // All new journeys are recorded as not available for the next connection level.
@@ -70,6 +96,7 @@ where
 fn explore_last_route_section_more_if_possible(
     data_storage: &DataStorage,
     route: &Route,
+    restrictions: Option<&BoardingRestrictions>,
     routes: &mut RouteQueue,
 ) {
     let Some(journey_id) = route.last_section().journey_id() else {
@@ -77,7 +104,13 @@ fn explore_last_route_section_more_if_possible(
     };
 
     // The next section (tronçon dans ce cas) is visited if possible.
-    let new_route = route.extend(data_storage, journey_id, route.arrival_at().date(), false);
+    let new_route = route.extend(
+        data_storage,
+        journey_id,
+        route.arrival_at().date(),
+        false,
+        restrictions,
+    );
 
     if let Some(rou) = new_route {
         routes.push(rou);
@@ -125,19 +158,34 @@ fn explore_connections(
     data_storage: &DataStorage,
     route: &Route,
     journeys_to_ignore: &FxHashSet<i32>,
+    departure_window: Duration,
+    service_day_start: NaiveTime,
+    restrictions: Option<&BoardingRestrictions>,
     new_routes: &mut RouteQueue,
 ) {
-    for route in get_connections(data_storage, route, journeys_to_ignore) {
+    for route in get_connections(
+        data_storage,
+        route,
+        journeys_to_ignore,
+        departure_window,
+        service_day_start,
+        restrictions,
+    ) {
         new_routes.push(route);
     }
 }
 
-fn explore_nearby_stops(data_storage: &DataStorage, route: &Route, routes: &mut RouteQueue) {
+fn explore_nearby_stops(
+    data_storage: &DataStorage,
+    route: &Route,
+    max_transfer_walk: Option<Duration>,
+    routes: &mut RouteQueue,
+) {
     if route.last_section().journey_id().is_none() {
         // No walking between 2 stops, after walking between 2 stops just before.
         return;
     }
-    match get_stop_connections(data_storage, route.arrival_stop_id()) {
+    match get_walkable_stop_connections(data_storage, route.arrival_stop_id(), max_transfer_walk) {
         Some(stop_connections) => stop_connections,
         None => return,
     }
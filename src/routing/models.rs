@@ -1,8 +1,18 @@
-use chrono::{Duration, NaiveDateTime, TimeDelta};
+use chrono::{Duration, NaiveDateTime, NaiveTime, TimeDelta};
 use hrdf_parser::{Coordinates, DataStorage, Journey, TransportType};
 use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
-use std::hash::{Hash, Hasher};
+use std::{
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use crate::{isochrone::utils::haversine_distance, local_time::elapsed_between, utils::add_1_day};
+
+use super::{
+    connections::{DEFAULT_DEPARTURE_WINDOW, DEFAULT_SERVICE_DAY_START},
+    restrictions::BoardingRestrictions,
+};
 
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub struct RouteSection {
@@ -11,6 +21,10 @@ pub struct RouteSection {
     arrival_stop_id: i32,
     arrival_at: NaiveDateTime,
     duration: Option<i16>,
+    // Positions of the departure and arrival stops in the journey route, a stop can appear more
+    // than once on it.
+    departure_position: Option<usize>,
+    arrival_position: Option<usize>,
 }
 
 impl RouteSection {
@@ -27,9 +41,17 @@ impl RouteSection {
             arrival_stop_id,
             arrival_at,
             duration,
+            departure_position: None,
+            arrival_position: None,
         }
     }
 
+    pub fn with_positions(mut self, departure_position: usize, arrival_position: usize) -> Self {
+        self.departure_position = Some(departure_position);
+        self.arrival_position = Some(arrival_position);
+        self
+    }
+
     // Getters/Setters
 
     pub fn journey_id(&self) -> Option<i32> {
@@ -60,6 +82,18 @@ impl RouteSection {
         self.duration
     }
 
+    pub fn departure_position(&self) -> Option<usize> {
+        self.departure_position
+    }
+
+    pub fn arrival_position(&self) -> Option<usize> {
+        self.arrival_position
+    }
+
+    pub fn set_arrival_position(&mut self, value: Option<usize>) {
+        self.arrival_position = value;
+    }
+
     // Functions
 
     // pub fn journey<'a>(&'a self, data_storage: &'a DataStorage) -> Option<&Journey> {
@@ -146,18 +180,43 @@ impl Route {
     pub fn count_connections(&self) -> usize {
         self.sections_having_journey().len()
     }
+
+    /// A journey must contain at least one vehicle section, a route made of walking sections only
+    /// is not a valid journey.
+    pub fn has_vehicle_section(&self) -> bool {
+        any_vehicle_section(self.sections.iter().map(RouteSection::journey_id))
+    }
 }
 
+/// Whether one of the sections, given by their journey id, is not a walk.
+fn any_vehicle_section(journey_ids: impl IntoIterator<Item = Option<i32>>) -> bool {
+    journey_ids
+        .into_iter()
+        .any(|journey_id| journey_id.is_some())
+}
+
+/// Where the routing goes from the departure stop, the variants reading as
+/// RoutingAlgorithmMode::ToArrivalStop.
+#[allow(clippy::enum_variant_names)]
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum RoutingAlgorithmMode {
-    SolveFromDepartureStopToArrivalStop,
-    SolveFromDepartureStopToReachableArrivalStops,
+    ToArrivalStop,
+    ToAnyArrivalStop,
+    ToReachableStops,
 }
 
 pub struct RoutingAlgorithmArgs {
     mode: RoutingAlgorithmMode,
     arrival_stop_id: Option<i32>,
+    arrival_stop_ids: Vec<i32>,
     time_limit: Option<NaiveDateTime>,
+    departure_window: Duration,
+    service_day_start: NaiveTime,
+    max_transfer_walk: Option<Duration>,
+    transfer_penalty: Duration,
+    boarding_restrictions: Option<Arc<BoardingRestrictions>>,
+    label: Option<String>,
+    explain: bool,
 }
 
 impl RoutingAlgorithmArgs {
@@ -169,21 +228,37 @@ impl RoutingAlgorithmArgs {
         Self {
             mode,
             arrival_stop_id,
+            arrival_stop_ids: Vec::new(),
             time_limit,
+            departure_window: DEFAULT_DEPARTURE_WINDOW,
+            service_day_start: DEFAULT_SERVICE_DAY_START,
+            max_transfer_walk: None,
+            transfer_penalty: Duration::zero(),
+            boarding_restrictions: None,
+            label: None,
+            explain: false,
         }
     }
 
     pub fn solve_from_departure_stop_to_arrival_stop(arrival_stop_id: i32) -> Self {
         Self::new(
-            RoutingAlgorithmMode::SolveFromDepartureStopToArrivalStop,
+            RoutingAlgorithmMode::ToArrivalStop,
             Some(arrival_stop_id),
             None,
         )
     }
 
+    /// Reaching any of the arrival stops is a solution, e.g. the platforms of a station.
+    pub fn solve_from_departure_stop_to_any_arrival_stop(arrival_stop_ids: Vec<i32>) -> Self {
+        Self {
+            arrival_stop_ids,
+            ..Self::new(RoutingAlgorithmMode::ToAnyArrivalStop, None, None)
+        }
+    }
+
     pub fn solve_from_departure_stop_to_reachable_arrival_stops(time_limit: NaiveDateTime) -> Self {
         Self::new(
-            RoutingAlgorithmMode::SolveFromDepartureStopToReachableArrivalStops,
+            RoutingAlgorithmMode::ToReachableStops,
             None,
             Some(time_limit),
         )
@@ -200,10 +275,180 @@ impl RoutingAlgorithmArgs {
         self.arrival_stop_id.unwrap()
     }
 
+    /// Empty unless the mode is ToAnyArrivalStop.
+    pub fn arrival_stop_ids(&self) -> &[i32] {
+        &self.arrival_stop_ids
+    }
+
     /// Do not call this function if you are not sure that time_limit is not None.
     pub fn time_limit(&self) -> NaiveDateTime {
         self.time_limit.unwrap()
     }
+
+    /// Look-ahead window when searching the next departures from a stop.
+    pub fn departure_window(&self) -> Duration {
+        self.departure_window
+    }
+
+    pub fn with_departure_window(mut self, value: Duration) -> Self {
+        self.departure_window = value;
+        self
+    }
+
+    /// The departures before it bridge the night, see next_departures. 08:00 by default.
+    pub fn service_day_start(&self) -> NaiveTime {
+        self.service_day_start
+    }
+
+    pub fn with_service_day_start(mut self, value: NaiveTime) -> Self {
+        self.service_day_start = value;
+        self
+    }
+
+    /// Footpaths between two stops taking longer are not used, None means no limit.
+    pub fn max_transfer_walk(&self) -> Option<Duration> {
+        self.max_transfer_walk
+    }
+
+    pub fn with_max_transfer_walk(mut self, value: Option<Duration>) -> Self {
+        self.max_transfer_walk = value;
+        self
+    }
+
+    /// Added to the arrival time once per connection when comparing two routes, so that a route
+    /// with one connection less wins if it arrives at most this much later. Zero by default.
+    pub fn transfer_penalty(&self) -> Duration {
+        self.transfer_penalty
+    }
+
+    pub fn with_transfer_penalty(mut self, value: Duration) -> Self {
+        self.transfer_penalty = value;
+        self
+    }
+
+    /// The board-only and alight-only stops, only the first and last stops of the journeys are
+    /// restricted when None.
+    pub fn boarding_restrictions(&self) -> Option<&BoardingRestrictions> {
+        self.boarding_restrictions.as_deref()
+    }
+
+    pub fn with_boarding_restrictions(mut self, value: Option<Arc<BoardingRestrictions>>) -> Self {
+        self.boarding_restrictions = value;
+        self
+    }
+
+    /// Included in the log messages, to tell the computations apart.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn with_label(mut self, value: Option<String>) -> Self {
+        self.label = value;
+        self
+    }
+
+    /// Records the routes losing against the solutions, off by default as it slows down the routing.
+    pub fn explain(&self) -> bool {
+        self.explain
+    }
+
+    pub fn with_explain(mut self, value: bool) -> Self {
+        self.explain = value;
+        self
+    }
+}
+
+/// What decided the comparison between a candidate route and the current solution, in the order
+/// in which the criteria are applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ComparisonCriterion {
+    /// The candidate only walks, it is not a valid journey.
+    NoVehicleSection,
+    /// The earliest arrival wins.
+    ArrivalTime,
+    /// On equal arrival times, the fewest connections win.
+    ConnectionCount,
+    /// On equal connections, the connection crossing the most stops wins, e.g. staying longer in
+    /// the same train.
+    StopCount,
+    /// Nothing differs, the current solution is kept.
+    Tie,
+}
+
+/// A route which lost against the solution at the same stop, and why.
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonTrace {
+    loser: RouteResult,
+    criterion: ComparisonCriterion,
+}
+
+impl ComparisonTrace {
+    pub fn new(loser: RouteResult, criterion: ComparisonCriterion) -> Self {
+        Self { loser, criterion }
+    }
+
+    pub fn loser(&self) -> &RouteResult {
+        &self.loser
+    }
+
+    pub fn criterion(&self) -> ComparisonCriterion {
+        self.criterion
+    }
+}
+
+impl std::fmt::Display for ComparisonTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Route departing at {} and arriving at {} with {} changes lost on {:?}",
+            self.loser.departure_at(),
+            self.loser.arrival_at(),
+            self.loser.number_changes(),
+            self.criterion
+        )
+    }
+}
+
+/// The result of a journey planning when walking is an option.
+#[derive(Debug, Clone, Serialize)]
+pub enum JourneyPlan {
+    /// The journey uses at least one vehicle.
+    Transit(RouteResult),
+    /// The arrival stop is reached the earliest by walking, duration is in minutes.
+    WalkOnly { duration: i16 },
+}
+
+/// A page of the stops reachable from a departure, the earliest arrival first.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReachableStopsPage {
+    total: usize,
+    offset: usize,
+    routes: Vec<RouteResult>,
+}
+
+impl ReachableStopsPage {
+    pub fn new(total: usize, offset: usize, routes: Vec<RouteResult>) -> Self {
+        Self {
+            total,
+            offset,
+            routes,
+        }
+    }
+
+    /// The number of reachable stops, all pages together.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The position of the first route of the page among all the reachable stops.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// One route per stop, the route arriving the earliest.
+    pub fn routes(&self) -> &[RouteResult] {
+        &self.routes
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -266,16 +511,73 @@ impl RouteResult {
         &self.sections
     }
 
-    pub fn number_changes(&self) -> usize {
-        if !self.sections().is_empty() {
-            self.sections()
-                .iter()
-                .filter(|s| !s.is_walking_trip())
-                .count()
-                - 1
-        } else {
-            0
+    // Functions
+
+    /// Sets the occupancy of the vehicle sections from the source, the walking sections have
+    /// none.
+    pub fn with_occupancy(mut self, source: &impl OccupancySource) -> Self {
+        for section in &mut self.sections {
+            if let Some(journey_id) = section.journey_id {
+                section.occupancy = source.occupancy(journey_id, section.departure_stop_id);
+            }
         }
+        self
+    }
+
+    /// Shifts the departure and arrival times of the vehicle sections by the delays given by the
+    /// source, the sections having a delay being marked as realtime. The connections are not
+    /// checked again: a delayed section may arrive after the departure of the next one.
+    pub fn with_delays(mut self, source: &impl DelaySource) -> Self {
+        for section in &mut self.sections {
+            let Some(journey_id) = section.journey_id else {
+                continue;
+            };
+            let departure_delay = source.delay(journey_id, section.departure_stop_id);
+            let arrival_delay = source.delay(journey_id, section.arrival_stop_id);
+            if let Some(delay) = departure_delay {
+                section.departure_at = section.departure_at.map(|at| at + delay);
+            }
+            if let Some(delay) = arrival_delay {
+                section.arrival_at = section.arrival_at.map(|at| at + delay);
+            }
+            section.realtime = departure_delay.is_some() || arrival_delay.is_some();
+        }
+
+        // The times of the route are those of its first and last vehicle sections.
+        let departure_at = self
+            .sections
+            .iter()
+            .find(|s| !s.is_walking_trip())
+            .and_then(|s| s.departure_at);
+        let arrival_at = self
+            .sections
+            .iter()
+            .rfind(|s| !s.is_walking_trip())
+            .and_then(|s| s.arrival_at);
+        self.departure_at = departure_at.unwrap_or(self.departure_at);
+        self.arrival_at = arrival_at.unwrap_or(self.arrival_at);
+        self
+    }
+
+    /// Appends the sections of other to the route. other must depart from the arrival stop of
+    /// self, the waiting time between the two routes is part of the resulting route.
+    pub fn concat(self, other: RouteResult) -> RouteResult {
+        let mut sections = self.sections;
+        sections.extend(other.sections);
+        RouteResult::new(self.departure_at, other.arrival_at, sections)
+    }
+
+    pub fn number_changes(&self) -> usize {
+        self.sections()
+            .iter()
+            .filter(|s| !s.is_walking_trip())
+            .count()
+            .saturating_sub(1)
+    }
+
+    /// See Route::has_vehicle_section.
+    pub fn has_vehicle_section(&self) -> bool {
+        any_vehicle_section(self.sections().iter().map(|s| s.journey_id))
     }
 
     pub fn total_walking_time(&self) -> Duration {
@@ -291,6 +593,11 @@ impl RouteResult {
         self.arrival_at() - self.departure_at()
     }
 
+    /// Same as total_time, but one hour shorter or longer when the clocks change during the route.
+    pub fn total_elapsed_time(&self) -> Duration {
+        elapsed_between(self.departure_at(), self.arrival_at())
+    }
+
     pub fn departure_stop_id(&self) -> Option<i32> {
         self.sections().first().map(|s| s.departure_stop_id)
     }
@@ -322,6 +629,231 @@ impl RouteResult {
             )
         })
     }
+
+    /// Returns one line per section with the WGS84 (latitude, longitude) coordinates of the stops
+    /// served, to be drawn on a map. Walking sections are a straight line between their two stops.
+    /// Stops without coordinates are skipped.
+    pub fn leg_polylines(&self, data_storage: &DataStorage) -> Vec<Vec<(f64, f64)>> {
+        let wgs84_of = |stop_id: i32| {
+            data_storage
+                .stops()
+                .find(stop_id)
+                .and_then(|stop| stop.wgs84_coordinates())
+                .and_then(|c| Some((c.latitude()?, c.longitude()?)))
+        };
+
+        self.sections()
+            .iter()
+            .map(|section| {
+                let intermediate_stops = section
+                    .intermediate_stops(data_storage)
+                    .into_iter()
+                    .map(|(stop_id, _)| stop_id);
+
+                std::iter::once(section.departure_stop_id())
+                    .chain(intermediate_stops)
+                    .chain(std::iter::once(section.arrival_stop_id()))
+                    .filter_map(&wgs84_of)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Total distance in meters, the sum of the great-circle distances between the consecutive
+    /// stops of each section.
+    pub fn total_distance_meters(&self, data_storage: &DataStorage) -> f64 {
+        self.leg_polylines(data_storage)
+            .iter()
+            .flat_map(|line| line.windows(2))
+            .map(|pair| haversine_distance(pair[0].0, pair[0].1, pair[1].0, pair[1].1) * 1000.0)
+            .sum()
+    }
+
+    /// Average speed in km/h over the whole route, the waiting and walking times included, see
+    /// total_elapsed_time. Returns None if the route takes no time.
+    pub fn average_speed_kmh(&self, data_storage: &DataStorage) -> Option<f64> {
+        let hours = self.total_elapsed_time().num_minutes() as f64 / 60.0;
+        (hours > 0.0).then(|| self.total_distance_meters(data_storage) / 1000.0 / hours)
+    }
+
+    /// Compares the routes section by section, e.g. to check that a change of the engine doesn't
+    /// change its results. The times (and the durations of the walking sections) may differ by
+    /// up to tolerance.
+    pub fn diff(&self, other: &RouteResult, tolerance: Duration) -> RouteDiff {
+        let mut differences = Vec::new();
+        if self.sections.len() != other.sections.len() {
+            differences.push(SectionDifference::SectionCount {
+                lhs: self.sections.len(),
+                rhs: other.sections.len(),
+            });
+        }
+
+        let differ = |lhs: Option<NaiveDateTime>, rhs: Option<NaiveDateTime>| match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) => (lhs - rhs).abs() > tolerance,
+            (lhs, rhs) => lhs != rhs,
+        };
+        let duration = |minutes: Option<i16>| minutes.map(|m| Duration::minutes(m.into()));
+        for (section, (lhs, rhs)) in self.sections.iter().zip(&other.sections).enumerate() {
+            if lhs.departure_stop_id != rhs.departure_stop_id {
+                differences.push(SectionDifference::DepartureStop {
+                    section,
+                    lhs: lhs.departure_stop_id,
+                    rhs: rhs.departure_stop_id,
+                });
+            }
+            if lhs.arrival_stop_id != rhs.arrival_stop_id {
+                differences.push(SectionDifference::ArrivalStop {
+                    section,
+                    lhs: lhs.arrival_stop_id,
+                    rhs: rhs.arrival_stop_id,
+                });
+            }
+            if lhs.transport != rhs.transport {
+                differences.push(SectionDifference::Transport {
+                    section,
+                    lhs: lhs.transport,
+                    rhs: rhs.transport,
+                });
+            }
+            if differ(lhs.departure_at, rhs.departure_at) {
+                differences.push(SectionDifference::DepartureAt {
+                    section,
+                    lhs: lhs.departure_at,
+                    rhs: rhs.departure_at,
+                });
+            }
+            if differ(lhs.arrival_at, rhs.arrival_at) {
+                differences.push(SectionDifference::ArrivalAt {
+                    section,
+                    lhs: lhs.arrival_at,
+                    rhs: rhs.arrival_at,
+                });
+            }
+            let duration_differs = match (duration(lhs.duration), duration(rhs.duration)) {
+                (Some(lhs), Some(rhs)) => (lhs - rhs).abs() > tolerance,
+                (lhs, rhs) => lhs != rhs,
+            };
+            if duration_differs {
+                differences.push(SectionDifference::Duration {
+                    section,
+                    lhs: lhs.duration,
+                    rhs: rhs.duration,
+                });
+            }
+        }
+        RouteDiff { differences }
+    }
+}
+
+/// A difference between two routes, see RouteResult::diff. The sections are given by their
+/// index in the routes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SectionDifference {
+    SectionCount {
+        lhs: usize,
+        rhs: usize,
+    },
+    DepartureStop {
+        section: usize,
+        lhs: i32,
+        rhs: i32,
+    },
+    ArrivalStop {
+        section: usize,
+        lhs: i32,
+        rhs: i32,
+    },
+    Transport {
+        section: usize,
+        lhs: Transport,
+        rhs: Transport,
+    },
+    DepartureAt {
+        section: usize,
+        lhs: Option<NaiveDateTime>,
+        rhs: Option<NaiveDateTime>,
+    },
+    ArrivalAt {
+        section: usize,
+        lhs: Option<NaiveDateTime>,
+        rhs: Option<NaiveDateTime>,
+    },
+    /// The duration of a walking section, in minutes.
+    Duration {
+        section: usize,
+        lhs: Option<i16>,
+        rhs: Option<i16>,
+    },
+}
+
+/// The differences between two routes, see RouteResult::diff.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RouteDiff {
+    differences: Vec<SectionDifference>,
+}
+
+impl RouteDiff {
+    /// Whether the routes are the same within the tolerance.
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    pub fn differences(&self) -> &[SectionDifference] {
+        &self.differences
+    }
+}
+
+impl std::fmt::Display for RouteDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for difference in &self.differences {
+            writeln!(f, "{difference:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// How crowded the vehicle of a section is. HRDF has no occupancy, it comes from an external
+/// source (see OccupancySource).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OccupancyLevel {
+    Low,
+    Medium,
+    High,
+    Full,
+}
+
+/// An external source of occupancy, e.g. realtime data, queried for the vehicle sections of a
+/// journey by journey id and departure stop id.
+pub trait OccupancySource {
+    fn occupancy(&self, journey_id: i32, stop_id: i32) -> Option<OccupancyLevel>;
+}
+
+impl<F: Fn(i32, i32) -> Option<OccupancyLevel>> OccupancySource for F {
+    fn occupancy(&self, journey_id: i32, stop_id: i32) -> Option<OccupancyLevel> {
+        self(journey_id, stop_id)
+    }
+}
+
+/// An external source of realtime delays, queried for the departure and the arrival stops of the
+/// vehicle sections of a journey by journey id and stop id.
+pub trait DelaySource {
+    fn delay(&self, journey_id: i32, stop_id: i32) -> Option<Duration>;
+}
+
+impl<F: Fn(i32, i32) -> Option<Duration>> DelaySource for F {
+    fn delay(&self, journey_id: i32, stop_id: i32) -> Option<Duration> {
+        self(journey_id, stop_id)
+    }
+}
+
+/// The source of a timetable without delays.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoDelays;
+
+impl DelaySource for NoDelays {
+    fn delay(&self, _journey_id: i32, _stop_id: i32) -> Option<Duration> {
+        None
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Copy, Clone)]
@@ -337,6 +869,13 @@ pub struct RouteSectionResult {
     arrival_at: Option<NaiveDateTime>,
     duration: Option<i16>,
     transport: Transport,
+    #[serde(default)]
+    occupancy: Option<OccupancyLevel>,
+    #[serde(default)]
+    realtime: bool,
+    // Positions of the departure and arrival stops in the journey route, see RouteSection.
+    #[serde(skip)]
+    route_positions: Option<(usize, usize)>,
 }
 
 impl RouteSectionResult {
@@ -366,9 +905,17 @@ impl RouteSectionResult {
             arrival_at,
             duration,
             transport,
+            occupancy: None,
+            realtime: false,
+            route_positions: None,
         }
     }
 
+    pub fn with_route_positions(mut self, route_positions: Option<(usize, usize)>) -> Self {
+        self.route_positions = route_positions;
+        self
+    }
+
     // Getters/Setters
 
     pub fn departure_stop_id(&self) -> i32 {
@@ -431,12 +978,79 @@ impl RouteSectionResult {
         self.journey_id.is_none()
     }
 
+    /// The real time the section takes, see elapsed_between: the walking duration of a walking
+    /// section, from the departure to the arrival otherwise.
+    pub fn elapsed_time(&self) -> Option<Duration> {
+        match (self.departure_at, self.arrival_at) {
+            (Some(departure_at), Some(arrival_at)) if !self.is_walking_trip() => {
+                Some(elapsed_between(departure_at, arrival_at))
+            }
+            _ => self
+                .duration
+                .map(|minutes| Duration::minutes(minutes.into())),
+        }
+    }
+
+    /// Returns the stops served between the departure and the arrival stops of the section with
+    /// their arrival time. Walking sections have no intermediate stops.
+    pub fn intermediate_stops(&self, data_storage: &DataStorage) -> Vec<(i32, NaiveDateTime)> {
+        let (Some(journey), Some(departure_at)) = (self.journey(data_storage), self.departure_at)
+        else {
+            return Vec::new();
+        };
+
+        let route_entries: Vec<_> = match self.route_positions {
+            Some((departure_position, arrival_position)) => journey.route()
+                [departure_position + 1..arrival_position]
+                .iter()
+                .collect(),
+            // Deserialized results don't have the positions, the first passages are used.
+            None => journey
+                .route()
+                .iter()
+                .skip_while(|route_entry| route_entry.stop_id() != self.departure_stop_id)
+                .skip(1)
+                .take_while(|route_entry| route_entry.stop_id() != self.arrival_stop_id)
+                .collect(),
+        };
+
+        // The route entries only contain times, the date is deduced from the previous stop.
+        let mut previous_at = departure_at;
+        route_entries
+            .into_iter()
+            .filter_map(|route_entry| {
+                let time = route_entry
+                    .arrival_time()
+                    .as_ref()
+                    .or(route_entry.departure_time().as_ref())
+                    .copied()?;
+                let mut passing_at = NaiveDateTime::new(previous_at.date(), time);
+                if passing_at < previous_at {
+                    // The journey went past midnight.
+                    passing_at = NaiveDateTime::new(add_1_day(previous_at.date()), time);
+                }
+                previous_at = passing_at;
+                Some((route_entry.stop_id(), passing_at))
+            })
+            .collect()
+    }
+
     pub fn transport(&self) -> &Transport {
         &self.transport
     }
+
+    /// None unless it was given by an OccupancySource, see RouteResult::with_occupancy.
+    pub fn occupancy(&self) -> Option<OccupancyLevel> {
+        self.occupancy
+    }
+
+    /// Whether the times were shifted by a DelaySource, see RouteResult::with_delays.
+    pub fn is_realtime(&self) -> bool {
+        self.realtime
+    }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Transport {
     Boat,
     Bus,
@@ -503,6 +1117,144 @@ mod tests {
         )
     }
 
+    /// A train, a walk and a bus, the bus arriving at bus_arrival.
+    fn sample_route(bus_arrival: &str, walk: i16) -> RouteResult {
+        let sections = vec![
+            create_test_section(
+                Some(1),
+                8503000,
+                8507000,
+                "2025-06-15 10:00:00",
+                "2025-06-15 11:00:00",
+                None,
+                Transport::Train,
+            ),
+            create_test_section(None, 8507000, 8507100, "", "", Some(walk), Transport::Walk),
+            create_test_section(
+                Some(2),
+                8507100,
+                8508000,
+                "2025-06-15 11:10:00",
+                bus_arrival,
+                None,
+                Transport::Bus,
+            ),
+        ];
+        let dep_at = sections[0].departure_at().unwrap();
+        let arr_at = sections[2].arrival_at().unwrap();
+        RouteResult::new(dep_at, arr_at, sections)
+    }
+
+    #[test]
+    fn test_route_result_with_occupancy() {
+        let route = sample_route("2025-06-15 11:30:00", 5);
+        assert!(route.sections().iter().all(|s| s.occupancy().is_none()));
+
+        let route = route.with_occupancy(&|journey_id: i32, stop_id: i32| {
+            (journey_id == 1 && stop_id == 8503000).then_some(OccupancyLevel::High)
+        });
+        let occupancies: Vec<_> = route.sections().iter().map(|s| s.occupancy()).collect();
+        assert_eq!(occupancies, [Some(OccupancyLevel::High), None, None]);
+    }
+
+    #[test]
+    fn test_route_result_with_delays() {
+        let route = sample_route("2025-06-15 11:30:00", 5);
+        let (dep_at, arr_at) = (route.departure_at(), route.arrival_at());
+
+        let unchanged = route.clone().with_delays(&NoDelays);
+        assert_eq!(unchanged.arrival_at(), arr_at);
+        assert!(unchanged.sections().iter().all(|s| !s.is_realtime()));
+
+        // The bus arrives 3 minutes late.
+        let route = route.with_delays(&|journey_id: i32, stop_id: i32| {
+            (journey_id == 2 && stop_id == 8508000).then_some(Duration::minutes(3))
+        });
+        assert_eq!(route.departure_at(), dep_at);
+        assert_eq!(route.arrival_at(), arr_at + Duration::minutes(3));
+        let realtime: Vec<_> = route.sections().iter().map(|s| s.is_realtime()).collect();
+        assert_eq!(realtime, [false, false, true]);
+        assert_eq!(
+            route.sections()[2]
+                .departure_at()
+                .unwrap()
+                .format("%H:%M")
+                .to_string(),
+            "11:10"
+        );
+    }
+
+    #[test]
+    fn test_route_result_diff() {
+        let reference = sample_route("2025-06-15 11:30:00", 5);
+
+        assert!(reference.diff(&reference, Duration::zero()).is_empty());
+        let later = sample_route("2025-06-15 11:31:00", 5);
+        assert!(later.diff(&reference, Duration::minutes(1)).is_empty());
+        let diff = later.diff(&reference, Duration::zero());
+        assert_eq!(
+            diff.differences(),
+            [SectionDifference::ArrivalAt {
+                section: 2,
+                lhs: later.sections()[2].arrival_at(),
+                rhs: reference.sections()[2].arrival_at(),
+            }]
+        );
+
+        let longer_walk = sample_route("2025-06-15 11:30:00", 8);
+        assert_eq!(
+            longer_walk
+                .diff(&reference, Duration::minutes(1))
+                .differences(),
+            [SectionDifference::Duration {
+                section: 1,
+                lhs: Some(8),
+                rhs: Some(5),
+            }]
+        );
+
+        let direct = RouteResult::new(
+            reference.departure_at(),
+            reference.arrival_at(),
+            reference.sections()[..1].to_vec(),
+        );
+        assert!(
+            direct
+                .diff(&reference, Duration::zero())
+                .differences()
+                .contains(&SectionDifference::SectionCount { lhs: 1, rhs: 3 })
+        );
+    }
+
+    #[test]
+    fn test_route_section_result_elapsed_time_across_clock_changes() {
+        // Spring forward, 2025-03-30 02:00 => 03:00.
+        let section = create_test_section(
+            Some(1),
+            8503000,
+            8507000,
+            "2025-03-30 01:30:00",
+            "2025-03-30 03:30:00",
+            None,
+            Transport::Train,
+        );
+        assert_eq!(section.elapsed_time(), Some(Duration::hours(1)));
+        // Fall back, 2025-10-26 03:00 => 02:00.
+        let section = create_test_section(
+            Some(1),
+            8503000,
+            8507000,
+            "2025-10-26 01:30:00",
+            "2025-10-26 03:30:00",
+            None,
+            Transport::Train,
+        );
+        assert_eq!(section.elapsed_time(), Some(Duration::hours(3)));
+
+        let walk = create_test_section(None, 8507000, 8507100, "", "", Some(5), Transport::Walk);
+        assert_eq!(walk.elapsed_time(), Some(Duration::minutes(5)));
+    }
+
     #[test]
     fn test_route_result_total_time() {
         let sections = vec![create_test_section(
@@ -878,6 +1630,48 @@ mod tests {
         assert_eq!(route.arrival_stop_id(), Some(8507001));
     }
 
+    #[test]
+    fn test_route_result_concat() {
+        let first = RouteResult::new(
+            NaiveDateTime::parse_from_str("2025-06-15 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            NaiveDateTime::parse_from_str("2025-06-15 10:45:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            vec![create_test_section(
+                Some(1),
+                8501008,
+                8501120,
+                "2025-06-15 10:00:00",
+                "2025-06-15 10:45:00",
+                None,
+                Transport::Train,
+            )],
+        );
+        let second = RouteResult::new(
+            NaiveDateTime::parse_from_str("2025-06-15 11:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            NaiveDateTime::parse_from_str("2025-06-15 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            vec![
+                create_test_section(
+                    Some(2),
+                    8501120,
+                    8501506,
+                    "2025-06-15 11:00:00",
+                    "2025-06-15 12:00:00",
+                    None,
+                    Transport::Train,
+                ),
+                create_test_section(None, 8501506, 8501507, "", "", Some(4), Transport::Walk),
+            ],
+        );
+
+        let route = first.concat(second);
+
+        assert_eq!(route.sections().len(), 3);
+        assert_eq!(route.departure_stop_id(), Some(8501008));
+        assert_eq!(route.arrival_stop_id(), Some(8501507));
+        // The 15 minutes waiting at the via stop are part of the route
+        assert_eq!(route.total_time().num_minutes(), 124);
+        assert_eq!(route.number_changes(), 1);
+    }
+
     #[test]
     fn test_route_result_total_time_with_walking() {
         let sections = vec![
@@ -903,4 +1697,32 @@ mod tests {
         // Total time should account for walking: (11:03) - (09:55) = 68 minutes
         assert_eq!(route.total_time().num_minutes(), 68);
     }
+
+    #[test]
+    fn test_walking_only_route_has_no_vehicle_section() {
+        let sections = vec![create_test_section(
+            None,
+            8503000,
+            8503001,
+            "",
+            "",
+            Some(5),
+            Transport::Walk,
+        )];
+
+        let dep_at =
+            NaiveDateTime::parse_from_str("2025-06-15 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let route = RouteResult::new(dep_at, dep_at, sections);
+
+        assert!(!route.has_vehicle_section());
+        assert_eq!(route.number_changes(), 0);
+
+        let mut visited_stops = FxHashSet::default();
+        visited_stops.insert(8503000);
+        visited_stops.insert(8503001);
+        let walk = RouteSection::new(None, 8503000, 8503001, dep_at, Some(5));
+        let ride = RouteSection::new(Some(1), 8503001, 8507000, dep_at, None);
+        assert!(!Route::new(vec![walk.clone()], visited_stops.clone()).has_vehicle_section());
+        assert!(Route::new(vec![walk, ride], visited_stops).has_vehicle_section());
+    }
 }
@@ -0,0 +1,34 @@
+use chrono::TimeDelta;
+use hrdf_parser::DataStorage;
+use ojp_rs::{SimplifiedLeg, SimplifiedTrip};
+
+use super::models::RouteResult;
+
+impl RouteResult {
+    /// Converts the route into an OJP-shaped trip.
+    /// Walking sections have no timetable, their times are deduced from the previous section.
+    pub fn to_ojp_trip(&self, data_storage: &DataStorage) -> SimplifiedTrip {
+        let mut prev_arr_time = self.departure_at();
+        let legs = self
+            .sections()
+            .iter()
+            .map(|s| {
+                let departure_time = s.departure_at().unwrap_or(prev_arr_time);
+                let arrival_time = s.arrival_at().unwrap_or(
+                    prev_arr_time + TimeDelta::minutes(s.duration().unwrap_or(0) as i64),
+                );
+                prev_arr_time = arrival_time;
+                SimplifiedLeg::new(
+                    s.departure_stop_id(),
+                    s.departure_stop_name(data_storage),
+                    s.arrival_stop_id(),
+                    s.arrival_stop_name(data_storage),
+                    departure_time,
+                    arrival_time,
+                    format!("{:?}", s.transport()),
+                )
+            })
+            .collect::<Vec<_>>();
+        SimplifiedTrip::new(legs)
+    }
+}
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveDateTime, NaiveTime};
+use hrdf_parser::Hrdf;
+
+use super::{
+    JourneyPlan, Route,
+    connections::{DEFAULT_DEPARTURE_WINDOW, DEFAULT_SERVICE_DAY_START},
+    core::compute_routing,
+    models::RoutingAlgorithmArgs,
+    restrictions::BoardingRestrictions,
+    walking_duration,
+};
+
+/// Builder of a journey from a stop to another, planned with the same algorithm as plan_journey.
+/// The departure stop, the arrival stop and the departure date and time are required: plan is
+/// only available once from, to and at have been called.
+///
+/// ```ignore
+/// let route = JourneyQuery::new(&hrdf)
+///     .from(8587057)
+///     .to(8501689)
+///     .at(departure_at)
+///     .plan();
+/// ```
+#[derive(Clone)]
+pub struct JourneyQuery<'a, F = (), T = (), A = ()> {
+    hrdf: &'a Hrdf,
+    departure_stop_id: F,
+    arrival_stop_id: T,
+    departure_at: A,
+    max_num_explorable_connections: i32,
+    transfer_penalty: Duration,
+    departure_window: Duration,
+    service_day_start: NaiveTime,
+    max_transfer_walk: Option<Duration>,
+    boarding_restrictions: Option<Arc<BoardingRestrictions>>,
+    label: Option<&'a str>,
+    verbose: bool,
+}
+
+impl<'a> JourneyQuery<'a> {
+    pub fn new(hrdf: &'a Hrdf) -> Self {
+        Self {
+            hrdf,
+            departure_stop_id: (),
+            arrival_stop_id: (),
+            departure_at: (),
+            max_num_explorable_connections: 10,
+            transfer_penalty: Duration::zero(),
+            departure_window: DEFAULT_DEPARTURE_WINDOW,
+            service_day_start: DEFAULT_SERVICE_DAY_START,
+            max_transfer_walk: None,
+            boarding_restrictions: None,
+            label: None,
+            verbose: false,
+        }
+    }
+}
+
+impl<'a, F, T, A> JourneyQuery<'a, F, T, A> {
+    pub fn from(self, departure_stop_id: i32) -> JourneyQuery<'a, i32, T, A> {
+        JourneyQuery {
+            hrdf: self.hrdf,
+            departure_stop_id,
+            arrival_stop_id: self.arrival_stop_id,
+            departure_at: self.departure_at,
+            max_num_explorable_connections: self.max_num_explorable_connections,
+            transfer_penalty: self.transfer_penalty,
+            departure_window: self.departure_window,
+            service_day_start: self.service_day_start,
+            max_transfer_walk: self.max_transfer_walk,
+            boarding_restrictions: self.boarding_restrictions,
+            label: self.label,
+            verbose: self.verbose,
+        }
+    }
+
+    pub fn to(self, arrival_stop_id: i32) -> JourneyQuery<'a, F, i32, A> {
+        JourneyQuery {
+            hrdf: self.hrdf,
+            departure_stop_id: self.departure_stop_id,
+            arrival_stop_id,
+            departure_at: self.departure_at,
+            max_num_explorable_connections: self.max_num_explorable_connections,
+            transfer_penalty: self.transfer_penalty,
+            departure_window: self.departure_window,
+            service_day_start: self.service_day_start,
+            max_transfer_walk: self.max_transfer_walk,
+            boarding_restrictions: self.boarding_restrictions,
+            label: self.label,
+            verbose: self.verbose,
+        }
+    }
+
+    /// The departure date and time must be within the timetable period.
+    pub fn at(self, departure_at: NaiveDateTime) -> JourneyQuery<'a, F, T, NaiveDateTime> {
+        JourneyQuery {
+            hrdf: self.hrdf,
+            departure_stop_id: self.departure_stop_id,
+            arrival_stop_id: self.arrival_stop_id,
+            departure_at,
+            max_num_explorable_connections: self.max_num_explorable_connections,
+            transfer_penalty: self.transfer_penalty,
+            departure_window: self.departure_window,
+            service_day_start: self.service_day_start,
+            max_transfer_walk: self.max_transfer_walk,
+            boarding_restrictions: self.boarding_restrictions,
+            label: self.label,
+            verbose: self.verbose,
+        }
+    }
+
+    /// 10 by default.
+    pub fn max_num_explorable_connections(mut self, max_num_explorable_connections: i32) -> Self {
+        self.max_num_explorable_connections = max_num_explorable_connections;
+        self
+    }
+
+    /// Zero by default, see plan_journey_with_transfer_penalty.
+    pub fn transfer_penalty(mut self, transfer_penalty: Duration) -> Self {
+        self.transfer_penalty = transfer_penalty;
+        self
+    }
+
+    /// 4 hours by default: how long after the departure time the first departures from the
+    /// departure stop are searched, e.g. a longer window for a stop served a few times a day.
+    pub fn departure_window(mut self, departure_window: Duration) -> Self {
+        self.departure_window = departure_window;
+        self
+    }
+
+    /// 08:00 by default: until then, the departures are searched until 08:00 to bridge the night.
+    /// An earlier time suits a continuous service, e.g. a metro running all night. The journeys of
+    /// the previous day still running are included in any case.
+    pub fn service_day_start(mut self, service_day_start: NaiveTime) -> Self {
+        self.service_day_start = service_day_start;
+        self
+    }
+
+    /// None (no limit) by default: the footpaths between two stops taking longer to walk are not
+    /// used, see find_reachable_stops_within_time_limit.
+    pub fn max_transfer_walk(mut self, max_transfer_walk: Option<Duration>) -> Self {
+        self.max_transfer_walk = max_transfer_walk;
+        self
+    }
+
+    /// Not given by default: only the first and last stops of the journeys are restricted, see
+    /// BoardingRestrictions.
+    pub fn boarding_restrictions(
+        mut self,
+        boarding_restrictions: Arc<BoardingRestrictions>,
+    ) -> Self {
+        self.boarding_restrictions = Some(boarding_restrictions);
+        self
+    }
+
+    /// Included in the log messages, e.g. the id of the request of the service.
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+}
+
+impl JourneyQuery<'_, i32, i32, NaiveDateTime> {
+    pub fn plan(&self) -> Option<Route> {
+        let route = compute_routing(
+            self.hrdf.data_storage(),
+            self.departure_stop_id,
+            self.departure_at,
+            self.max_num_explorable_connections,
+            self.verbose,
+            RoutingAlgorithmArgs::solve_from_departure_stop_to_arrival_stop(self.arrival_stop_id)
+                .with_transfer_penalty(self.transfer_penalty)
+                .with_departure_window(self.departure_window)
+                .with_service_day_start(self.service_day_start)
+                .with_max_transfer_walk(self.max_transfer_walk)
+                .with_boarding_restrictions(self.boarding_restrictions.clone())
+                .with_label(self.label.map(String::from)),
+        )
+        .remove(&self.arrival_stop_id);
+
+        if self.verbose
+            && let Some(route) = &route
+        {
+            println!();
+            route.print(self.hrdf.data_storage());
+        }
+
+        route
+    }
+
+    /// Same as plan, walking being considered as by plan_journey_or_walk.
+    pub fn plan_or_walk(&self) -> Option<JourneyPlan> {
+        let walking_duration = walking_duration(
+            self.hrdf.data_storage(),
+            self.departure_stop_id,
+            self.arrival_stop_id,
+        );
+        if walking_duration == Some(0) {
+            return Some(JourneyPlan::WalkOnly { duration: 0 });
+        }
+
+        match (self.plan(), walking_duration) {
+            (Some(route), Some(duration))
+                if route.arrival_at() < self.departure_at + Duration::minutes(duration.into()) =>
+            {
+                Some(JourneyPlan::Transit(route))
+            }
+            (_, Some(duration)) => Some(JourneyPlan::WalkOnly { duration }),
+            (route, None) => route.map(JourneyPlan::Transit),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::{fs::read_to_string, path::Path};
+
+use rustc_hash::FxHashSet;
+
+use crate::error::RResult;
+
+/// The stops of the journeys where the passengers can't board or alight. FPLAN marks them with a
+/// negative departure or arrival time, hrdf_parser drops the sign: they are read from FPLAN again.
+#[derive(Debug, Default, Clone)]
+pub struct BoardingRestrictions {
+    /// (journey id, position on the route)
+    no_boarding: FxHashSet<(i32, usize)>,
+    no_alighting: FxHashSet<(i32, usize)>,
+}
+
+impl BoardingRestrictions {
+    /// Reads the FPLAN file of the timetable the Hrdf was built from. The journeys are numbered as
+    /// in hrdf_parser, from 1 in the order of their *Z lines.
+    pub fn from_fplan(path: &Path) -> RResult<Self> {
+        Ok(Self::parse(&read_to_string(path)?))
+    }
+
+    fn parse(fplan: &str) -> Self {
+        let mut restrictions = Self::default();
+        let mut journey_id = 0;
+        let mut position = 0;
+
+        for line in fplan.lines().filter(|line| !line.trim().is_empty()) {
+            if line.starts_with("*Z") {
+                journey_id += 1;
+                position = 0;
+            } else if !line.starts_with('*') {
+                // The arrival time is in the columns 30 to 35, the departure time in 37 to 42.
+                let is_negative = |range| {
+                    line.get(range)
+                        .is_some_and(|t: &str| t.trim().starts_with('-'))
+                };
+                if is_negative(29..35) {
+                    restrictions.no_alighting.insert((journey_id, position));
+                }
+                if is_negative(36..42) {
+                    restrictions.no_boarding.insert((journey_id, position));
+                }
+                position += 1;
+            }
+        }
+        restrictions
+    }
+
+    pub fn can_board(&self, journey_id: i32, position: usize) -> bool {
+        !self.no_boarding.contains(&(journey_id, position))
+    }
+
+    pub fn can_alight(&self, journey_id: i32, position: usize) -> bool {
+        !self.no_alighting.contains(&(journey_id, position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_log::test;
+
+    use crate::utils::fixture_boarding_restrictions;
+
+    #[test]
+    fn test_from_fplan() {
+        let restrictions = fixture_boarding_restrictions();
+
+        // IC 101 (1) can't be left at Bravo, IC 102 (2) can't be boarded there.
+        assert!(!restrictions.can_alight(1, 1));
+        assert!(restrictions.can_board(1, 1));
+        assert!(restrictions.can_alight(2, 1));
+        assert!(!restrictions.can_board(2, 1));
+        // R 301 (6) at Golf
+        assert!(restrictions.can_alight(6, 1));
+        assert!(restrictions.can_board(6, 1));
+    }
+}
@@ -6,7 +6,8 @@ use crate::routing::models::Transport;
 
 use super::{
     models::{Route, RouteResult, RouteSection, RouteSectionResult},
-    utils::clone_update_route,
+    restrictions::BoardingRestrictions,
+    utils::{clone_update_route, date_time_at_position},
 };
 
 impl Route {
@@ -16,6 +17,7 @@ impl Route {
         journey_id: i32,
         date: NaiveDate,
         is_departure_date: bool,
+        restrictions: Option<&BoardingRestrictions>,
     ) -> Option<Route> {
         let journey = data_storage
             .journeys()
@@ -23,21 +25,34 @@ impl Route {
             // .expect(format!("Jounrey {journey_id} not found").as_str());
             .unwrap_or_else(|| panic!("Journey {:?} not found.", journey_id));
 
-        if journey
-            .is_last_stop(self.arrival_stop_id(), false)
-            .unwrap_or_else(|_| panic!("Unable to get last stop for {}", self.arrival_stop_id()))
-        {
+        let is_same_journey = self.last_section().journey_id() == Some(journey_id);
+        // The position is only meaningful on the same journey.
+        let departure_position = if is_same_journey {
+            self.last_section().arrival_position()
+        } else {
+            None
+        };
+
+        let is_last_stop = match departure_position {
+            Some(position) => position + 1 == journey.route().len(),
+            None => journey
+                .is_last_stop(self.arrival_stop_id(), false)
+                .unwrap_or_else(|_| {
+                    panic!("Unable to get last stop for {}", self.arrival_stop_id())
+                }),
+        };
+        if is_last_stop {
             return None;
         }
 
-        let is_same_journey = self.last_section().journey_id() == Some(journey_id);
-
         RouteSection::find_next(
             data_storage,
             journey,
             self.arrival_stop_id(),
+            departure_position,
             date,
             is_departure_date,
+            restrictions,
         )
         .and_then(|(new_section, new_visited_stops)| {
             if self.has_visited_any_stops(&new_visited_stops)
@@ -54,6 +69,7 @@ impl Route {
                     let last_section = cloned_sections.last_mut().unwrap();
                     last_section.set_arrival_stop_id(new_section.arrival_stop_id());
                     last_section.set_arrival_at(new_section.arrival_at());
+                    last_section.set_arrival_position(new_section.arrival_position());
                 } else {
                     cloned_sections.push(new_section);
                 }
@@ -90,40 +106,46 @@ impl Route {
 }
 
 impl RouteSection {
+    /// Finds the next stop of the journey usable for an exchange after the departure stop.
+    /// The departure position must be given when it is known, i.e. when continuing on the same
+    /// journey, otherwise the first occurrence of the departure stop on the journey is used. This
+    /// matters for the journeys going through the same stop more than once (e.g. circular lines).
     pub fn find_next(
         data_storage: &DataStorage,
         journey: &Journey,
         departure_stop_id: i32,
+        departure_position: Option<usize>,
         date: NaiveDate,
         is_departure_date: bool,
+        restrictions: Option<&BoardingRestrictions>,
     ) -> Option<(RouteSection, FxHashSet<i32>)> {
-        let mut route_iter = journey.route().iter();
-
-        for route_entry in route_iter.by_ref() {
-            if route_entry.stop_id() == departure_stop_id {
-                break;
-            }
-        }
+        let route = journey.route();
+        let departure_position = departure_position.or_else(|| {
+            route
+                .iter()
+                .position(|route_entry| route_entry.stop_id() == departure_stop_id)
+        })?;
 
         let mut visited_stops = FxHashSet::default();
 
-        for route_entry in route_iter.by_ref() {
+        for (position, route_entry) in route.iter().enumerate().skip(departure_position + 1) {
             let stop = route_entry
                 .stop(data_storage)
                 .unwrap_or_else(|_| panic!("Missing stop on route entry: {route_entry:?}"));
             visited_stops.insert(stop.id());
 
-            if stop.can_be_used_as_exchange_point()
-                || journey
-                    .is_last_stop(stop.id(), false)
-                    .unwrap_or_else(|_| panic!("Invalid stop {}", stop.id()))
-            {
-                let arrival_at = journey.arrival_at_of_with_origin(
-                    stop.id(),
+            // The passengers can't change at a stop where they can't alight.
+            let can_alight = route_entry.arrival_time().is_some()
+                && restrictions.is_none_or(|r| r.can_alight(journey.id(), position));
+            if (stop.can_be_used_as_exchange_point() && can_alight) || position + 1 == route.len() {
+                let arrival_at = date_time_at_position(
+                    journey,
+                    position,
+                    true,
                     date,
-                    is_departure_date,
-                    departure_stop_id,
-                ).unwrap_or_else(|_| panic!("No arrival date for stop id: {}, date: {date}, is_departure_date: {is_departure_date}, departure_stop_id: {departure_stop_id}", stop.id()));
+                    departure_position,
+                    !is_departure_date,
+                ).unwrap_or_else(|| panic!("No arrival date for stop id: {}, date: {date}, is_departure_date: {is_departure_date}, departure_stop_id: {departure_stop_id}", stop.id()));
 
                 return Some((
                     RouteSection::new(
@@ -132,7 +154,8 @@ impl RouteSection {
                         stop.id(),
                         arrival_at,
                         None,
-                    ),
+                    )
+                    .with_positions(departure_position, position),
                     visited_stops,
                 ));
             }
@@ -151,24 +174,27 @@ impl RouteSection {
             .find(self.arrival_stop_id())
             .unwrap_or_else(|| panic!("Arrival stop {} not found.", self.arrival_stop_id()));
 
-        let (departure_at, arrival_at) = if self.journey_id().is_some() {
-            let departure_at = self
-                .journey(data_storage)
-                .unwrap()
-                .departure_at_of_with_origin(
+        let route_positions = self.departure_position().zip(self.arrival_position());
+        let (departure_at, arrival_at) = if let Some(journey) = self.journey(data_storage) {
+            let (departure_position, arrival_position) = route_positions.unwrap_or_else(|| {
+                panic!("No route positions on journey {}", journey.id());
+            });
+            let departure_at = date_time_at_position(
+                journey,
+                departure_position,
+                false,
+                self.arrival_at().date(),
+                arrival_position,
+                true,
+            )
+            .unwrap_or_else(|| {
+                panic!(
+                    "No departure at from {}, at date {}, and origin {}",
                     departure_stop.id(),
                     self.arrival_at().date(),
-                    false,
-                    arrival_stop.id(),
+                    arrival_stop.id()
                 )
-                .unwrap_or_else(|_| {
-                    panic!(
-                        "No departure at from {}, at date {}, and origin {}",
-                        departure_stop.id(),
-                        self.arrival_at().date(),
-                        arrival_stop.id()
-                    )
-                });
+            });
             (Some(departure_at), Some(self.arrival_at()))
         } else {
             (None, None)
@@ -195,5 +221,116 @@ impl RouteSection {
             self.duration(),
             transport,
         )
+        .with_route_positions(route_positions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hrdf_parser::Hrdf;
+    use test_log::test;
+
+    use crate::{
+        plan_journey,
+        utils::{create_date, create_date_time, fixture_boarding_restrictions, fixture_hrdf},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_find_next_skips_no_alighting_stops() {
+        let hrdf = fixture_hrdf();
+        let data_storage = hrdf.data_storage();
+        let restrictions = fixture_boarding_restrictions();
+        // IC 101 (1) Alpha - Bravo - Charlie, which can't be left at Bravo.
+        let journey = data_storage.journeys().find(1).unwrap();
+        let find_next = |restrictions| {
+            let (section, _) = RouteSection::find_next(
+                data_storage,
+                journey,
+                8500001,
+                None,
+                create_date(2025, 6, 2),
+                true,
+                restrictions,
+            )
+            .unwrap();
+            section.arrival_stop_id()
+        };
+
+        assert_eq!(find_next(None), 8500002);
+        assert_eq!(find_next(Some(&restrictions)), 8500003);
+    }
+
+    #[test]
+    fn test_circular_line_arrives_at_the_second_passage() {
+        let hrdf = fixture_hrdf();
+        // B 401 Alpha 09:00 - Hotel 09:05 - India 09:10 - Alpha 09:15 - Hotel 09:20 goes through
+        // Alpha and Hotel twice: from India, Hotel is reached on the second passage.
+        let route = plan_journey(
+            &hrdf,
+            8500009,
+            8500008,
+            create_date_time(2025, 6, 2, 9, 8),
+            10,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(route.departure_at(), create_date_time(2025, 6, 2, 9, 11));
+        assert_eq!(route.arrival_at(), create_date_time(2025, 6, 2, 9, 20));
+        let section = &route.sections()[0];
+        assert_eq!(
+            section.intermediate_stops(hrdf.data_storage()),
+            vec![(8500001, create_date_time(2025, 6, 2, 9, 15))]
+        );
+    }
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_find_next_follows_circular_line() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let data_storage = hrdf.data_storage();
+
+        // A circular line starts and ends at the same stop.
+        let journey = data_storage
+            .journeys()
+            .entries()
+            .into_iter()
+            .find(|journey| {
+                let route = journey.route();
+                route.len() > 2
+                    && route.first().map(|entry| entry.stop_id())
+                        == route.last().map(|entry| entry.stop_id())
+            })
+            .expect("No circular line found");
+        let route = journey.route();
+        let first_stop_id = route[0].stop_id();
+
+        // Following the journey by position must go around the loop exactly once, the search by
+        // stop id would restart from the first stop when coming back to it.
+        let mut departure = (first_stop_id, 0);
+        let mut num_sections = 0;
+        while let Some((section, _)) = RouteSection::find_next(
+            data_storage,
+            journey,
+            departure.0,
+            Some(departure.1),
+            create_date(2025, 6, 2),
+            true,
+            None,
+        ) {
+            let arrival_position = section.arrival_position().unwrap();
+            assert!(arrival_position > departure.1);
+            assert_eq!(route[arrival_position].stop_id(), section.arrival_stop_id());
+            departure = (section.arrival_stop_id(), arrival_position);
+            num_sections += 1;
+        }
+
+        assert!(num_sections > 0);
+        assert_eq!(departure, (first_stop_id, route.len() - 1));
     }
 }
@@ -1,11 +1,14 @@
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 
-use chrono::NaiveDateTime;
-use hrdf_parser::{DataStorage, StopConnection};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use hrdf_parser::{DataStorage, Journey, Model, StopConnection};
 use rustc_hash::FxHashSet;
 
-use super::models::{Route, RouteSection};
+use super::{
+    models::{Route, RouteSection},
+    restrictions::BoardingRestrictions,
+};
 
 #[derive(Debug)]
 struct RouteHeapItem {
@@ -104,6 +107,91 @@ pub fn get_stop_connections(
         })
 }
 
+/// Returns the stop connections of the stop which can be walked within max_transfer_walk, all of
+/// them if it is None.
+pub fn get_walkable_stop_connections(
+    data_storage: &DataStorage,
+    stop_id: i32,
+    max_transfer_walk: Option<Duration>,
+) -> Option<Vec<&StopConnection>> {
+    let mut stop_connections = get_stop_connections(data_storage, stop_id)?;
+    if let Some(max_transfer_walk) = max_transfer_walk {
+        stop_connections.retain(|stop_connection| {
+            Duration::minutes(stop_connection.duration().into()) <= max_transfer_walk
+        });
+    }
+    Some(stop_connections)
+}
+
+/// HRDF leaves out the departure time at the last stop of a journey and the arrival time at its
+/// first stop. The other stops where a journey can't be boarded or alighted from are only known
+/// from the restrictions, when given.
+pub fn can_board_at(
+    journey: &Journey,
+    stop_id: i32,
+    restrictions: Option<&BoardingRestrictions>,
+) -> bool {
+    journey
+        .route()
+        .iter()
+        .enumerate()
+        .any(|(position, route_entry)| {
+            route_entry.stop_id() == stop_id
+                && route_entry.departure_time().is_some()
+                && restrictions.is_none_or(|r| r.can_board(journey.id(), position))
+        })
+}
+
+/// See can_board_at. The stop is given by its position on the journey route.
+pub fn can_alight_at(
+    journey: &Journey,
+    position: usize,
+    restrictions: Option<&BoardingRestrictions>,
+) -> bool {
+    journey.route().get(position).is_some_and(|route_entry| {
+        route_entry.arrival_time().is_some()
+            && restrictions.is_none_or(|r| r.can_alight(journey.id(), position))
+    })
+}
+
+/// The arrival (or departure) time of the journey at a position of its route, and whether it is
+/// on the day after the first departure.
+fn time_at_position(
+    journey: &Journey,
+    position: usize,
+    is_arrival: bool,
+) -> Option<(NaiveTime, bool)> {
+    let route_entry = journey.route().get(position)?;
+    let time = if is_arrival {
+        *route_entry.arrival_time()
+    } else {
+        *route_entry.departure_time()
+    }?;
+    let first_departure_time = (*journey.route().first()?.departure_time())?;
+    Some((time, time < first_departure_time))
+}
+
+/// Same as Journey::arrival_at_of_with_origin and Journey::departure_at_of_with_origin, the stops
+/// being given by their position on the route: a circular line goes through a stop more than
+/// once. The date is the one of the origin, at its arrival time if is_origin_arrival.
+pub fn date_time_at_position(
+    journey: &Journey,
+    position: usize,
+    is_arrival: bool,
+    date: NaiveDate,
+    origin_position: usize,
+    is_origin_arrival: bool,
+) -> Option<NaiveDateTime> {
+    let (time, is_next_day) = time_at_position(journey, position, is_arrival)?;
+    let (_, origin_is_next_day) = time_at_position(journey, origin_position, is_origin_arrival)?;
+    let date = match (is_next_day, origin_is_next_day) {
+        (true, false) => date.succ_opt()?,
+        (false, true) => date.pred_opt()?,
+        _ => date,
+    };
+    Some(NaiveDateTime::new(date, time))
+}
+
 pub fn get_routes_to_ignore(data_storage: &DataStorage, route: &Route) -> FxHashSet<u64> {
     route
         .sections()
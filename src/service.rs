@@ -1,34 +1,121 @@
 use std::{
     net::{Ipv4Addr, SocketAddr},
     str::FromStr,
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Instant,
 };
 
-use axum::{Json, Router, extract::Query, http::StatusCode, routing::get};
+use axum::{
+    Json, Router,
+    extract::Query,
+    http::{StatusCode, header::CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    routing::get,
+};
 use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
 use geo::MultiPolygon;
 use hrdf_parser::{Hrdf, timetable_end_date, timetable_start_date};
 use serde::{Deserialize, Serialize};
 use tower_http::cors::{Any, CorsLayer};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
 use crate::{
-    IsochroneArgs,
+    DEFAULT_MAX_ACCESS_WALK_IN_METERS, IsochroneArgs, IsochroneConfig, JourneyPlan, JourneyQuery,
+    RError, RResult, Route, StopIndex, WALKING_SPEED_IN_KILOMETERS_PER_HOUR,
     isochrone::{self, IsochroneDisplayMode, IsochroneMap},
+    routing,
+    stop_search::resolve_stop,
+    timetable::{self, DepartureBoardEntry},
 };
 
+/// OpenAPI description of the endpoints, served at /openapi.json.
+#[derive(OpenApi)]
+#[openapi(paths(
+    metadata,
+    served_days,
+    headway,
+    departures,
+    search_stops,
+    nearest_stops,
+    compute_isochrones,
+    compute_isochrones_from_geojson,
+    plan_journey,
+    reachable_stops
+))]
+struct ApiDoc;
+
+/// Error response whose body tells which parameter was wrong, e.g.
+/// `{ "error": "display_mode must be circles or contour_line" }`.
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiError {
+    #[serde(skip)]
+    status: StatusCode,
+    error: String,
+}
+
+impl ApiError {
+    fn bad_request(error: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            error: error.into(),
+        }
+    }
+
+    fn not_found(error: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            error: error.into(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
+
+/// Counts the computation requests. Their id is given in the log messages, so that the messages
+/// of a single request can be found.
+static LAST_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+fn next_request_id() -> String {
+    format!(
+        "request {}",
+        LAST_REQUEST_ID.fetch_add(1, Ordering::Relaxed) + 1
+    )
+}
+
 pub async fn run_service(
     hrdf: Arc<Hrdf>,
     num_threads: usize,
     excluded_polygons: MultiPolygon,
     ip_addr: Ipv4Addr,
     port: u16,
-) {
+) -> RResult<()> {
     log::info!("Starting the server...");
+    timetable::warn_dangling_stop_ids(&hrdf);
 
+    // Kept for the lifetime of the service, next to the Hrdf it indexes.
+    let stop_index = Arc::new(StopIndex::new(hrdf.data_storage()));
+    let stop_index_1 = Arc::clone(&stop_index);
+    let stop_index_2 = Arc::clone(&stop_index);
     let hrdf_1 = Arc::clone(&hrdf);
     let hrdf_2 = Arc::clone(&hrdf);
+    let hrdf_3 = Arc::clone(&hrdf);
+    let hrdf_4 = Arc::clone(&hrdf);
+    let hrdf_5 = Arc::clone(&hrdf);
+    let hrdf_6 = Arc::clone(&hrdf);
+    let hrdf_7 = Arc::clone(&hrdf);
+    let hrdf_8 = Arc::clone(&hrdf);
+    let hrdf_9 = Arc::clone(&hrdf);
+    let hrdf_10 = Arc::clone(&hrdf);
     let cors = CorsLayer::new().allow_methods(Any).allow_origin(Any);
     let excluded_polygons = Arc::new(excluded_polygons);
+    let excluded_polygons_2 = Arc::clone(&excluded_polygons);
 
     #[rustfmt::skip]
     let app = Router::new()
@@ -36,25 +123,64 @@ pub async fn run_service(
             "/metadata",
             get(move || metadata(Arc::clone(&hrdf_1))),
         )
+        .route(
+            "/served_days",
+            get(move |params| served_days(Arc::clone(&hrdf_4), params)),
+        )
+        .route(
+            "/headway",
+            get(move |params| headway(Arc::clone(&hrdf_8), params)),
+        )
+        .route(
+            "/departures",
+            get(move |params| departures(Arc::clone(&hrdf_9), params)),
+        )
+        .route(
+            "/stops",
+            get(move |params| search_stops(Arc::clone(&hrdf_5), params)),
+        )
+        .route(
+            "/nearest_stops",
+            get(move |params| nearest_stops(Arc::clone(&hrdf_6), Arc::clone(&stop_index), params)),
+        )
         .route(
             "/isochrones",
-            get(move |params| compute_isochrones(Arc::clone(&hrdf_2), num_threads, Arc::clone(&excluded_polygons), params)),
+            get(move |params| compute_isochrones(Arc::clone(&hrdf_2), Arc::clone(&stop_index_1), num_threads, Arc::clone(&excluded_polygons), params))
+                .post(move |params, body| compute_isochrones_from_geojson(Arc::clone(&hrdf_7), Arc::clone(&stop_index_2), num_threads, Arc::clone(&excluded_polygons_2), params, body)),
+        )
+        .route(
+            "/journey",
+            get(move |params| plan_journey(Arc::clone(&hrdf_3), params)),
+        )
+        .route(
+            "/reachable_stops",
+            get(move |params| reachable_stops(Arc::clone(&hrdf_10), params)),
+        )
+        .route(
+            "/openapi.json",
+            get(|| async { Json(ApiDoc::openapi()) }),
         )
         .layer(cors);
     let address = SocketAddr::from((ip_addr, port));
-    let listener = tokio::net::TcpListener::bind(address).await.unwrap();
+    let listener = tokio::net::TcpListener::bind(address).await?;
 
     log::info!("Listening on {ip_addr}:{port}...");
 
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app).await?;
+    Ok(())
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct MetadataResponse {
     start_date: NaiveDate,
     end_date: NaiveDate,
 }
 
+#[utoipa::path(
+    get,
+    path = "/metadata",
+    responses((status = 200, description = "Validity period of the timetable", body = MetadataResponse))
+)]
 async fn metadata(hrdf: Arc<Hrdf>) -> Json<MetadataResponse> {
     Json(MetadataResponse {
         start_date: timetable_start_date(hrdf.data_storage().timetable_metadata()).unwrap(),
@@ -62,78 +188,1161 @@ async fn metadata(hrdf: Arc<Hrdf>) -> Json<MetadataResponse> {
     })
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ServedDaysRequest {
+    stop_id: i32,
+    year: i32,
+    month: u32,
+}
+
+#[utoipa::path(
+    get,
+    path = "/served_days",
+    params(ServedDaysRequest),
+    responses(
+        (status = 200, description = "Days of the month on which the stop is served", body = Vec<NaiveDate>),
+        (status = 400, description = "Invalid month")
+    )
+)]
+async fn served_days(
+    hrdf: Arc<Hrdf>,
+    Query(params): Query<ServedDaysRequest>,
+) -> Result<Json<Vec<NaiveDate>>, StatusCode> {
+    if !(1..=12).contains(&params.month) {
+        // The month is incorrect.
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok(Json(timetable::served_days_for_stop(
+        &hrdf,
+        params.stop_id,
+        params.year,
+        params.month,
+    )))
+}
+
+/// The whole day when from and to are absent.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct HeadwayRequest {
+    stop_id: i32,
+    date: NaiveDate,
+    from: Option<NaiveTime>,
+    to: Option<NaiveTime>,
+}
+
+/// The headways are absent with less than two departures.
+#[derive(Debug, Serialize, ToSchema)]
+struct HeadwayResponse {
+    count: usize,
+    min_minutes: Option<i64>,
+    max_minutes: Option<i64>,
+    mean_minutes: Option<f64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/headway",
+    params(HeadwayRequest),
+    responses(
+        (status = 200, description = "Time between two consecutive departures from the stop", body = HeadwayResponse),
+        (status = 400, description = "Invalid parameter", body = ApiError)
+    )
+)]
+async fn headway(
+    hrdf: Arc<Hrdf>,
+    Query(params): Query<HeadwayRequest>,
+) -> Result<Json<HeadwayResponse>, ApiError> {
+    if hrdf.data_storage().stops().find(params.stop_id).is_none() {
+        return Err(ApiError::bad_request(format!(
+            "stop {} does not exist",
+            params.stop_id
+        )));
+    }
+    if !timetable::is_date_within_timetable(&hrdf, params.date) {
+        return Err(ApiError::bad_request(
+            "date must be within the timetable period",
+        ));
+    }
+    let from = params.from.unwrap_or(NaiveTime::MIN);
+    let stats = match params.to {
+        Some(to) if from >= to => return Err(ApiError::bad_request("from must be before to")),
+        Some(to) => timetable::headway_stats(&hrdf, params.stop_id, params.date, from..to),
+        // Until the end of the day, included.
+        None => timetable::headway_stats(&hrdf, params.stop_id, params.date, from..),
+    };
+    Ok(Json(HeadwayResponse {
+        count: stats.count,
+        min_minutes: stats.min.map(|min| min.num_minutes()),
+        max_minutes: stats.max.map(|max| max.num_minutes()),
+        mean_minutes: stats.mean.map(|mean| mean.num_seconds() as f64 / 60.0),
+    }))
+}
+
+/// Default and maximum number of departures returned by /departures.
+const DEFAULT_NUM_DEPARTURES: usize = 10;
+const MAX_NUM_DEPARTURES: usize = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DeparturesRequest {
+    stop_id: i32,
+    date: NaiveDate,
+    time: NaiveTime,
+    n: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/departures",
+    params(DeparturesRequest),
+    responses(
+        (status = 200, description = "Next departures from the stop, the earliest first", body = Vec<DepartureBoardEntry>),
+        (status = 400, description = "Invalid parameter", body = ApiError)
+    )
+)]
+async fn departures(
+    hrdf: Arc<Hrdf>,
+    Query(params): Query<DeparturesRequest>,
+) -> Result<Json<Vec<DepartureBoardEntry>>, ApiError> {
+    if hrdf.data_storage().stops().find(params.stop_id).is_none() {
+        return Err(ApiError::bad_request(format!(
+            "stop {} does not exist",
+            params.stop_id
+        )));
+    }
+    if !timetable::is_date_within_timetable(&hrdf, params.date) {
+        return Err(ApiError::bad_request(
+            "date must be within the timetable period",
+        ));
+    }
+    let n = params
+        .n
+        .unwrap_or(DEFAULT_NUM_DEPARTURES)
+        .min(MAX_NUM_DEPARTURES);
+
+    let from = NaiveDateTime::new(params.date, params.time);
+    Ok(Json(timetable::departure_board(
+        &hrdf,
+        params.stop_id,
+        from,
+        n,
+        None,
+    )))
+}
+
+/// Default and maximum number of stops returned by /stops and /nearest_stops.
+const DEFAULT_NUM_STOPS: usize = 10;
+const MAX_NUM_STOPS: usize = 50;
+/// Longer queries are truncated.
+const MAX_QUERY_LEN: usize = 100;
+
+#[derive(Debug, Serialize, ToSchema)]
+struct StopResponse {
+    id: i32,
+    name: String,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    /// Distance in meters to the requested point, only for /nearest_stops.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distance: Option<f64>,
+    /// Whether a journey can be changed at the stop.
+    is_exchange_point: bool,
+}
+
+impl StopResponse {
+    fn new(hrdf: &Hrdf, stop_id: i32, distance: Option<f64>) -> Option<Self> {
+        let stop = hrdf.data_storage().stops().find(stop_id)?;
+        let (latitude, longitude) = stop
+            .wgs84_coordinates()
+            .map_or((None, None), |coord| (coord.latitude(), coord.longitude()));
+        Some(Self {
+            id: stop_id,
+            name: String::from(stop.name()),
+            latitude,
+            longitude,
+            distance,
+            is_exchange_point: stop.can_be_used_as_exchange_point(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SearchStopsRequest {
+    q: String,
+    n: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/stops",
+    params(SearchStopsRequest),
+    responses(
+        (status = 200, description = "Stops matching the name, the best match first", body = Vec<StopResponse>),
+        (status = 400, description = "Empty query")
+    )
+)]
+async fn search_stops(
+    hrdf: Arc<Hrdf>,
+    Query(params): Query<SearchStopsRequest>,
+) -> Result<Json<Vec<StopResponse>>, StatusCode> {
+    let query: String = params
+        .q
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_QUERY_LEN)
+        .collect();
+    if query.trim().is_empty() {
+        // There is nothing to search.
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let n = params.n.unwrap_or(DEFAULT_NUM_STOPS).min(MAX_NUM_STOPS);
+
+    Ok(Json(
+        resolve_stop(&hrdf, &query)
+            .into_iter()
+            .take(n)
+            .filter_map(|(stop_id, _, _)| StopResponse::new(&hrdf, stop_id, None))
+            .collect(),
+    ))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct NearestStopsRequest {
+    lat: f64,
+    lon: f64,
+    n: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/nearest_stops",
+    params(NearestStopsRequest),
+    responses(
+        (status = 200, description = "Stops closest to the point, the closest first", body = Vec<StopResponse>),
+        (status = 400, description = "Invalid coordinates")
+    )
+)]
+async fn nearest_stops(
+    hrdf: Arc<Hrdf>,
+    stop_index: Arc<StopIndex>,
+    Query(params): Query<NearestStopsRequest>,
+) -> Result<Json<Vec<StopResponse>>, StatusCode> {
+    if !(-90.0..=90.0).contains(&params.lat) || !(-180.0..=180.0).contains(&params.lon) {
+        // The coordinates are incorrect.
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let n = params.n.unwrap_or(DEFAULT_NUM_STOPS).min(MAX_NUM_STOPS);
+
+    Ok(Json(
+        stop_index
+            .nearest(params.lat, params.lon, n)
+            .into_iter()
+            .filter_map(|(stop_id, distance)| StopResponse::new(&hrdf, stop_id, Some(distance)))
+            .collect(),
+    ))
+}
+
+/// Query parameters of the /isochrones endpoint. The origin point is required by GET, POST takes
+/// it as a GeoJSON body instead.
+/// arrive_by interprets departure_date and departure_time as the arrival date and time at the
+/// origin point, with or without find_optimal. The reverse isochrones are not available yet: 501
+/// is returned whenever arrive_by is set, find_optimal included.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct ComputeIsochronesRequest {
-    origin_point_latitude: f64,
-    origin_point_longitude: f64,
+    origin_point_latitude: Option<f64>,
+    origin_point_longitude: Option<f64>,
     departure_date: NaiveDate,
     departure_time: NaiveTime,
     time_limit: u32,
-    isochrone_interval: u32,
+    isochrone_interval: Option<u32>,
+    /// Replaces isochrone_interval (in minutes) when given, e.g. for fine-grained animations.
+    isochrone_interval_seconds: Option<u32>,
+    /// Number of isochrones, the interval is then time_limit divided by num_bands, which must divide
+    /// it. Exactly one of isochrone_interval, isochrone_interval_seconds and num_bands must be given.
+    num_bands: Option<usize>,
     display_mode: String,
     find_optimal: bool,
+    #[serde(default)]
+    arrive_by: bool,
+    /// Maximum walking time of a footpath between two stops in minutes, no limit if absent. 400 is
+    /// returned when negative.
+    max_transfer_walk: Option<i64>,
+    /// Maximum walking distance in meters to the departure stops, 1500 if absent. 400 is returned
+    /// when no stop is within it.
+    max_access_walk: Option<f64>,
+    /// Comma separated stop id prefixes of the countries to include, all of them if absent.
+    countries: Option<String>,
+    /// Adds the area reachable only thanks to the public transport to the response.
+    #[serde(default)]
+    transit_contribution: bool,
+    /// Rounds the time to reach each stop up to the next isochrone, for crisp bands.
+    #[serde(default)]
+    snap_to_interval: bool,
+    /// Subtracts the lakes from the isochrones, true by default. false keeps the areas across a
+    /// lake reachable by boat.
+    apply_excluded_polygons: Option<bool>,
+    /// Simplifies the polygons, no vertex moving by more than the tolerance in meters.
+    simplify_tolerance: Option<f64>,
+    /// json (default) or geojson, the latter returns a FeatureCollection with one feature per
+    /// isochrone.
+    format: Option<String>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/isochrones",
+    params(ComputeIsochronesRequest),
+    responses(
+        (status = 200, description = "Isochrones from the origin point", content(
+            (IsochroneMap = "application/json"),
+            (Object = "application/geo+json")
+        )),
+        (status = 400, description = "Invalid parameter", body = ApiError),
+        (status = 501, description = "Arrive by isochrones are not supported", body = ApiError)
+    )
+)]
 async fn compute_isochrones(
     hrdf: Arc<Hrdf>,
+    stop_index: Arc<StopIndex>,
     num_threads: usize,
     excluded_polygons: Arc<MultiPolygon>,
     Query(params): Query<ComputeIsochronesRequest>,
-) -> Result<Json<IsochroneMap>, StatusCode> {
-    // The coordinates are not checked but should be.
+) -> Result<Response, ApiError> {
+    let origin = params
+        .origin_point_latitude
+        .zip(params.origin_point_longitude)
+        .ok_or_else(|| {
+            ApiError::bad_request("origin_point_latitude and origin_point_longitude are required")
+        })?;
+    isochrones(
+        hrdf,
+        stop_index,
+        num_threads,
+        excluded_polygons,
+        params,
+        origin,
+    )
+    .await
+}
 
+#[utoipa::path(
+    post,
+    path = "/isochrones",
+    params(ComputeIsochronesRequest),
+    request_body(
+        content = String,
+        description = "GeoJSON Point, or Feature whose geometry is a Point, e.g. copied from a GIS",
+        content_type = "application/geo+json"
+    ),
+    responses(
+        (status = 200, description = "Isochrones from the point", content(
+            (IsochroneMap = "application/json"),
+            (Object = "application/geo+json")
+        )),
+        (status = 400, description = "Invalid parameter or not a GeoJSON Point", body = ApiError),
+        (status = 501, description = "Arrive by isochrones are not supported", body = ApiError)
+    )
+)]
+async fn compute_isochrones_from_geojson(
+    hrdf: Arc<Hrdf>,
+    stop_index: Arc<StopIndex>,
+    num_threads: usize,
+    excluded_polygons: Arc<MultiPolygon>,
+    Query(params): Query<ComputeIsochronesRequest>,
+    body: String,
+) -> Result<Response, ApiError> {
+    let origin = isochrone::origin_from_geojson(&body)
+        .map_err(|e| ApiError::bad_request(format!("the body must be a GeoJSON Point: {e}")))?;
+    isochrones(
+        hrdf,
+        stop_index,
+        num_threads,
+        excluded_polygons,
+        params,
+        origin,
+    )
+    .await
+}
+
+/// Computes the isochrones from the WGS84 (latitude, longitude) origin.
+async fn isochrones(
+    hrdf: Arc<Hrdf>,
+    stop_index: Arc<StopIndex>,
+    num_threads: usize,
+    excluded_polygons: Arc<MultiPolygon>,
+    params: ComputeIsochronesRequest,
+    (latitude, longitude): (f64, f64),
+) -> Result<Response, ApiError> {
     let max_num_explorable_connections = 10;
     let num_starting_points = 5;
     let start_date = timetable_start_date(hrdf.data_storage().timetable_metadata()).unwrap();
     let end_date = timetable_end_date(hrdf.data_storage().timetable_metadata()).unwrap();
 
     if params.departure_date < start_date || params.departure_date > end_date {
-        // The departure date is outside the possible dates for the timetable.
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::bad_request(format!(
+            "departure_date must be between {start_date} and {end_date}"
+        )));
     }
 
-    if params.time_limit % params.isochrone_interval != 0 {
-        // The result of dividing time_limit with isochrone_interval must be an integer.
-        return Err(StatusCode::BAD_REQUEST);
+    if !(-90.0..=90.0).contains(&latitude) {
+        return Err(ApiError::bad_request(
+            "origin_point_latitude must be between -90 and 90",
+        ));
+    }
+    if !(-180.0..=180.0).contains(&longitude) {
+        return Err(ApiError::bad_request(
+            "origin_point_longitude must be between -180 and 180",
+        ));
+    }
+    if params
+        .simplify_tolerance
+        .is_some_and(|tolerance| tolerance.is_nan() || tolerance < 0.0)
+    {
+        return Err(ApiError::bad_request("simplify_tolerance must be positive"));
+    }
+    if params
+        .max_access_walk
+        .is_some_and(|max_access_walk| max_access_walk.is_nan() || max_access_walk < 0.0)
+    {
+        return Err(ApiError::bad_request("max_access_walk must be positive"));
+    }
+    if params.max_transfer_walk.is_some_and(|minutes| minutes < 0) {
+        return Err(ApiError::bad_request("max_transfer_walk must be positive"));
+    }
+
+    let isochrone_interval = match (
+        params.isochrone_interval,
+        params.isochrone_interval_seconds,
+        params.num_bands,
+    ) {
+        (None, Some(seconds), None) => Duration::seconds(seconds.into()),
+        (Some(minutes), None, None) => Duration::minutes(minutes.into()),
+        // Replaced by time_limit / num_bands.
+        (None, None, Some(_)) => Duration::zero(),
+        _ => {
+            return Err(ApiError::bad_request(
+                "exactly one of isochrone_interval, isochrone_interval_seconds and num_bands must be given",
+            ));
+        }
+    };
+    if params.num_bands.is_none() && isochrone_interval <= Duration::zero() {
+        // It does not need to divide time_limit.
+        return Err(ApiError::bad_request(
+            "isochrone_interval must be greater than 0",
+        ));
+    }
+    if isochrone::check_num_bands(
+        Duration::minutes(params.time_limit.into()),
+        params.num_bands,
+    )
+    .is_err()
+    {
+        return Err(ApiError::bad_request(
+            "num_bands must be greater than 0 and divide time_limit",
+        ));
     }
 
     if !["circles", "contour_line"].contains(&params.display_mode.as_str()) {
-        // The display mode is incorrect.
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::bad_request(
+            "display_mode must be circles or contour_line",
+        ));
+    }
+
+    let as_geojson = match params.format.as_deref() {
+        None | Some("json") => false,
+        Some("geojson") => true,
+        Some(_) => return Err(ApiError::bad_request("format must be json or geojson")),
+    };
+
+    let countries: Vec<_> = params
+        .countries
+        .as_deref()
+        .map(|countries| countries.split(',').map(String::from).collect())
+        .unwrap_or_default();
+    if countries
+        .iter()
+        .any(|country| country.is_empty() || !country.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Err(ApiError::bad_request(
+            "countries must be comma separated numerical prefixes of the stop ids",
+        ));
+    }
+
+    if params.arrive_by {
+        // The reverse (arrive by) isochrone computation is not available yet.
+        log::info!("Arrive by isochrones requested but not supported");
+        return Err(ApiError {
+            status: StatusCode::NOT_IMPLEMENTED,
+            error: String::from("arrive_by is not supported yet"),
+        });
     }
+
     let isochrone_args = IsochroneArgs {
-        latitude: params.origin_point_latitude,
-        longitude: params.origin_point_longitude,
+        latitude,
+        longitude,
         departure_at: NaiveDateTime::new(params.departure_date, params.departure_time),
         time_limit: Duration::minutes(params.time_limit.into()),
-        interval: Duration::minutes(params.isochrone_interval.into()),
+        interval: isochrone_interval,
+        num_bands: params.num_bands,
         max_num_explorable_connections,
         num_starting_points,
+        countries,
+        snap_to_interval: params.snap_to_interval,
+        apply_excluded_polygons: params.apply_excluded_polygons.unwrap_or(true),
         verbose: false,
     };
-    let result = if params.find_optimal {
-        log::info!("Computing Optimal Isochrones for {isochrone_args}");
-        let res = isochrone::compute_optimal_isochrones(
-            &hrdf,
-            &excluded_polygons,
-            isochrone_args,
-            Duration::minutes(30),
-            IsochroneDisplayMode::from_str(&params.display_mode).unwrap(),
-            num_threads,
-        );
-        log::info!("Optimal Computation Successful");
-        res
+    let display_mode = IsochroneDisplayMode::from_str(&params.display_mode).unwrap();
+    let max_transfer_walk = params.max_transfer_walk.map(Duration::minutes);
+    let request_id = next_request_id();
+
+    // The computation is CPU-bound, it runs outside of the async workers so that the server keeps
+    // answering the other requests meanwhile.
+    let result = tokio::task::spawn_blocking(move || {
+        let config = IsochroneConfig::new(&excluded_polygons)
+            .with_delta_time(Duration::minutes(30))
+            .with_display_mode(display_mode)
+            .with_num_threads(num_threads)
+            .with_max_transfer_walk(max_transfer_walk)
+            .with_max_access_walk(
+                params
+                    .max_access_walk
+                    .unwrap_or(DEFAULT_MAX_ACCESS_WALK_IN_METERS),
+            )
+            .with_simplify_tolerance(params.simplify_tolerance)
+            .with_label(Some(&request_id))
+            .with_stop_index(&stop_index);
+        let walking_isochrone_map = params.transit_contribution.then(|| {
+            isochrone::compute_walking_isochrone(
+                isochrone_args.latitude,
+                isochrone_args.longitude,
+                isochrone_args.time_limit,
+                WALKING_SPEED_IN_KILOMETERS_PER_HOUR,
+            )
+        });
+        let start_time = Instant::now();
+        let res = if params.find_optimal {
+            log::info!("[{request_id}] Computing Optimal Isochrones for {isochrone_args}");
+            let res = isochrone::compute_optimal_isochrones(&hrdf, isochrone_args, &config)?;
+            log::info!(
+                "[{request_id}] Optimal Computation Successful in {:.2?}",
+                start_time.elapsed()
+            );
+            res
+        } else {
+            log::info!("[{request_id}] Computing Isochrones for {isochrone_args}");
+            let res = isochrone::compute_isochrones(&hrdf, isochrone_args, &config)?;
+            log::info!(
+                "[{request_id}] Normal Computation Successful in {:.2?}",
+                start_time.elapsed()
+            );
+            res
+        };
+        Ok(match walking_isochrone_map {
+            Some(walking_isochrone_map) => res.with_transit_contribution(&walking_isochrone_map),
+            None => res,
+        })
+    })
+    .await
+    .map_err(|e| {
+        log::error!("Isochrone computation failed: {e}");
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error: String::from("the isochrone computation failed"),
+        }
+    })?
+    .map_err(|e| match e {
+        RError::NoStopWithinAccessRange(..) => ApiError::bad_request(e.to_string()),
+        _ => {
+            log::error!("Isochrone computation failed: {e}");
+            ApiError {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                error: String::from("the isochrone computation failed"),
+            }
+        }
+    })?;
+    if as_geojson {
+        Ok((
+            [(CONTENT_TYPE, "application/geo+json")],
+            Json(result.to_geojson()),
+        )
+            .into_response())
     } else {
-        log::info!("Computing Isochrones for {isochrone_args}");
-        let res = isochrone::compute_isochrones(
+        Ok(Json(result).into_response())
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PlanJourneyRequest {
+    departure_stop_id: Option<i32>,
+    arrival_stop_id: Option<i32>,
+    /// Used when departure_stop_id is missing, resolved to the best matching stop.
+    departure_stop_name: Option<String>,
+    /// Used when arrival_stop_id is missing, resolved to the best matching stop.
+    arrival_stop_name: Option<String>,
+    departure_date: NaiveDate,
+    departure_time: NaiveTime,
+    #[serde(default)]
+    include_polylines: bool,
+    /// Maximum walking time of a footpath between two stops in minutes, no limit if absent.
+    max_transfer_walk: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct PlanJourneyResponse {
+    /// None when walking is the fastest way to the arrival stop.
+    #[schema(value_type = Option<Object>)]
+    route: Option<Route>,
+    /// Walking duration in minutes, only when no transit is needed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    walk_only_duration: Option<i16>,
+    /// Great-circle distance in meters along the stops of the route.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distance_meters: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    average_speed_kmh: Option<f64>,
+    /// WGS84 (latitude, longitude) line of each section, only when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Vec<Vec<Vec<f64>>>>)]
+    polylines: Option<Vec<Vec<(f64, f64)>>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/journey",
+    params(PlanJourneyRequest),
+    responses(
+        (status = 200, description = "Fastest journey, or the walking time if no transit is needed", body = PlanJourneyResponse),
+        (status = 400, description = "Invalid parameters or unknown stop", body = ApiError),
+        (status = 404, description = "No stop matching the name or no route found", body = ApiError)
+    )
+)]
+async fn plan_journey(
+    hrdf: Arc<Hrdf>,
+    Query(params): Query<PlanJourneyRequest>,
+) -> Result<Json<PlanJourneyResponse>, ApiError> {
+    let max_num_explorable_connections = 10;
+    if !timetable::is_date_within_timetable(&hrdf, params.departure_date) {
+        return Err(ApiError::bad_request(
+            "departure_date must be within the timetable period",
+        ));
+    }
+    if params.max_transfer_walk.is_some_and(|minutes| minutes < 0) {
+        return Err(ApiError::bad_request(
+            "max_transfer_walk must not be negative",
+        ));
+    }
+
+    let departure_stop_id = stop_id_or_best_match(
+        &hrdf,
+        params.departure_stop_id,
+        params.departure_stop_name.as_deref(),
+        "departure",
+    )?;
+    let arrival_stop_id = stop_id_or_best_match(
+        &hrdf,
+        params.arrival_stop_id,
+        params.arrival_stop_name.as_deref(),
+        "arrival",
+    )?;
+
+    let departure_at = NaiveDateTime::new(params.departure_date, params.departure_time);
+    let request_id = next_request_id();
+    log::info!(
+        "[{request_id}] Planning journey from {departure_stop_id} to {arrival_stop_id} at {departure_at}"
+    );
+    let start_time = Instant::now();
+    let label = request_id.clone();
+    let routing_hrdf = Arc::clone(&hrdf);
+    let plan = tokio::task::spawn_blocking(move || {
+        JourneyQuery::new(&routing_hrdf)
+            .from(departure_stop_id)
+            .to(arrival_stop_id)
+            .at(departure_at)
+            .max_num_explorable_connections(max_num_explorable_connections)
+            .max_transfer_walk(params.max_transfer_walk.map(Duration::minutes))
+            .label(&label)
+            .plan_or_walk()
+    })
+    .await
+    .map_err(|e| {
+        log::error!("Journey planning failed: {e}");
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error: String::from("the journey planning failed"),
+        }
+    })?;
+    log::info!(
+        "[{request_id}] Journey planned in {:.2?}",
+        start_time.elapsed()
+    );
+    let plan = plan.ok_or_else(|| {
+        ApiError::not_found(format!(
+            "no route found from {departure_stop_id} to {arrival_stop_id} at {departure_at}"
+        ))
+    })?;
+
+    let response = match plan {
+        JourneyPlan::Transit(route) => {
+            let polylines = params
+                .include_polylines
+                .then(|| route.leg_polylines(hrdf.data_storage()));
+            PlanJourneyResponse {
+                distance_meters: Some(route.total_distance_meters(hrdf.data_storage())),
+                average_speed_kmh: route.average_speed_kmh(hrdf.data_storage()),
+                route: Some(route),
+                walk_only_duration: None,
+                polylines,
+            }
+        }
+        JourneyPlan::WalkOnly { duration } => PlanJourneyResponse {
+            route: None,
+            walk_only_duration: Some(duration),
+            distance_meters: None,
+            average_speed_kmh: None,
+            polylines: None,
+        },
+    };
+    Ok(Json(response))
+}
+
+/// Default and maximum number of stops returned by /reachable_stops.
+const DEFAULT_NUM_REACHABLE_STOPS: usize = 100;
+const MAX_NUM_REACHABLE_STOPS: usize = 1000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ReachableStopsRequest {
+    departure_stop_id: i32,
+    departure_date: NaiveDate,
+    departure_time: NaiveTime,
+    /// In minutes.
+    time_limit: u32,
+    /// Maximum walking time of a footpath between two stops in minutes, no limit if absent.
+    max_transfer_walk: Option<i64>,
+    /// Number of stops skipped, the earliest reached first, 0 if absent.
+    offset: Option<usize>,
+    /// Number of stops returned, 100 if absent and at most 1000.
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ReachableStopResponse {
+    #[serde(flatten)]
+    stop: StopResponse,
+    arrival_at: NaiveDateTime,
+    /// In minutes from the departure.
+    duration: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ReachableStopsResponse {
+    /// Number of reachable stops, all pages together.
+    total: usize,
+    offset: usize,
+    stops: Vec<ReachableStopResponse>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/reachable_stops",
+    params(ReachableStopsRequest),
+    responses(
+        (status = 200, description = "Stops reachable within the time limit, the earliest reached first", body = ReachableStopsResponse),
+        (status = 400, description = "Invalid parameter", body = ApiError)
+    )
+)]
+async fn reachable_stops(
+    hrdf: Arc<Hrdf>,
+    Query(params): Query<ReachableStopsRequest>,
+) -> Result<Json<ReachableStopsResponse>, ApiError> {
+    let max_num_explorable_connections = 10;
+    if hrdf
+        .data_storage()
+        .stops()
+        .find(params.departure_stop_id)
+        .is_none()
+    {
+        return Err(ApiError::bad_request(format!(
+            "stop {} does not exist",
+            params.departure_stop_id
+        )));
+    }
+    if !timetable::is_date_within_timetable(&hrdf, params.departure_date) {
+        return Err(ApiError::bad_request(
+            "departure_date must be within the timetable period",
+        ));
+    }
+    let offset = params.offset.unwrap_or(0);
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_NUM_REACHABLE_STOPS)
+        .min(MAX_NUM_REACHABLE_STOPS);
+
+    let departure_at = NaiveDateTime::new(params.departure_date, params.departure_time);
+    let request_id = next_request_id();
+    log::info!(
+        "[{request_id}] Searching the stops reachable from {} at {departure_at} within {} minutes",
+        params.departure_stop_id,
+        params.time_limit
+    );
+    let start_time = Instant::now();
+    let label = request_id.clone();
+    let page = tokio::task::spawn_blocking(move || {
+        let routes = routing::find_reachable_stops_from_origins(
             &hrdf,
-            &excluded_polygons,
-            isochrone_args,
-            IsochroneDisplayMode::from_str(&params.display_mode).unwrap(),
-            num_threads,
-        );
-        log::info!("Normal Computation Successful");
-        res
+            &[params.departure_stop_id],
+            departure_at,
+            Duration::minutes(params.time_limit.into()),
+            max_num_explorable_connections,
+            params.max_transfer_walk.map(Duration::minutes),
+            Some(&label),
+            false,
+        )
+        .into_iter()
+        // Left out before the pagination, so that the total only counts the stops returned.
+        .filter(|route| {
+            route
+                .arrival_stop_id()
+                .is_some_and(|stop_id| StopResponse::new(&hrdf, stop_id, None).is_some())
+        })
+        .collect();
+        let page = routing::paginate_reachable_stops(routes, offset, Some(limit));
+        let stops = page
+            .routes()
+            .iter()
+            .filter_map(|route| {
+                Some(ReachableStopResponse {
+                    stop: StopResponse::new(&hrdf, route.arrival_stop_id()?, None)?,
+                    arrival_at: route.arrival_at(),
+                    duration: (route.arrival_at() - departure_at).num_minutes(),
+                })
+            })
+            .collect();
+        ReachableStopsResponse {
+            total: page.total(),
+            offset: page.offset(),
+            stops,
+        }
+    })
+    .await
+    .map_err(|e| {
+        log::error!("Reachable stops search failed: {e}");
+        ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error: String::from("the reachable stops search failed"),
+        }
+    })?;
+    log::info!(
+        "[{request_id}] {} reachable stops found in {:.2?}",
+        page.total,
+        start_time.elapsed()
+    );
+    Ok(Json(page))
+}
+
+/// Returns the stop id if given, otherwise the id of the stop best matching the name. field is
+/// the prefix of the parameters, departure or arrival.
+fn stop_id_or_best_match(
+    hrdf: &Hrdf,
+    stop_id: Option<i32>,
+    stop_name: Option<&str>,
+    field: &str,
+) -> Result<i32, ApiError> {
+    let stop_id = match (stop_id, stop_name) {
+        (Some(stop_id), _) => stop_id,
+        (None, Some(stop_name)) => resolve_stop(hrdf, stop_name)
+            .first()
+            .map(|(stop_id, _, _)| *stop_id)
+            .ok_or_else(|| ApiError::not_found(format!("no stop matches {stop_name}")))?,
+        (None, None) => {
+            return Err(ApiError::bad_request(format!(
+                "{field}_stop_id or {field}_stop_name is required"
+            )));
+        }
     };
-    Ok(Json(result))
+
+    if hrdf.data_storage().stops().find(stop_id).is_none() {
+        return Err(ApiError::bad_request(format!(
+            "stop {stop_id} does not exist"
+        )));
+    }
+    Ok(stop_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_log::test;
+
+    use crate::utils::{create_date, fixture_hrdf};
+
+    use super::*;
+
+    #[test]
+    fn test_openapi_describes_the_endpoints() {
+        let openapi = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        for path in [
+            "/metadata",
+            "/served_days",
+            "/headway",
+            "/departures",
+            "/stops",
+            "/nearest_stops",
+            "/isochrones",
+            "/journey",
+            "/reachable_stops",
+        ] {
+            assert!(openapi["paths"][path]["get"].is_object(), "{path}");
+        }
+        let parameters = openapi["paths"]["/isochrones"]["get"]["parameters"]
+            .as_array()
+            .unwrap();
+        assert!(
+            parameters
+                .iter()
+                .any(|parameter| parameter["name"] == "transit_contribution")
+        );
+        assert!(openapi["paths"]["/isochrones"]["post"].is_object());
+        assert!(
+            openapi["paths"]["/isochrones"]["get"]["responses"]["200"]["content"]
+                ["application/geo+json"]
+                .is_object()
+        );
+        assert!(openapi["components"]["schemas"]["IsochroneMap"].is_object());
+    }
+
+    #[test(tokio::test)]
+    async fn test_headway_defaults_to_the_whole_day() {
+        let hrdf = Arc::new(fixture_hrdf());
+        let request = |from, to| HeadwayRequest {
+            stop_id: 8500001,
+            date: create_date(2025, 6, 2),
+            from,
+            to,
+        };
+
+        // Alpha departs at 08:00, 08:03, 08:10, 09:00 and 23:50.
+        let Json(response) = headway(Arc::clone(&hrdf), Query(request(None, None)))
+            .await
+            .unwrap();
+        assert_eq!(response.count, 5);
+        assert_eq!(response.min_minutes, Some(3));
+        assert_eq!(response.max_minutes, Some(14 * 60 + 50));
+
+        let from = NaiveTime::from_hms_opt(8, 5, 0);
+        let Json(response) = headway(Arc::clone(&hrdf), Query(request(from, None)))
+            .await
+            .unwrap();
+        assert_eq!(response.count, 3);
+        let to = NaiveTime::from_hms_opt(9, 0, 0);
+        let Json(response) = headway(Arc::clone(&hrdf), Query(request(None, to)))
+            .await
+            .unwrap();
+        assert_eq!(response.count, 3);
+
+        let error = headway(hrdf, Query(request(to, from))).await.unwrap_err();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test(tokio::test)]
+    async fn test_isochrones_without_stop_within_access_range() {
+        let hrdf = Arc::new(fixture_hrdf());
+        let stop_index = Arc::new(StopIndex::new(hrdf.data_storage()));
+        let excluded_polygons = Arc::new(MultiPolygon::new(vec![]));
+        let request = |max_access_walk| ComputeIsochronesRequest {
+            origin_point_latitude: None,
+            origin_point_longitude: None,
+            departure_date: create_date(2025, 6, 2),
+            departure_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            time_limit: 30,
+            isochrone_interval: Some(10),
+            isochrone_interval_seconds: None,
+            num_bands: None,
+            display_mode: String::from("circles"),
+            find_optimal: false,
+            arrive_by: false,
+            max_transfer_walk: None,
+            max_access_walk,
+            countries: None,
+            transit_contribution: false,
+            snap_to_interval: false,
+            apply_excluded_polygons: None,
+            simplify_tolerance: None,
+            format: None,
+        };
+        let isochrones = |max_access_walk, origin| {
+            isochrones(
+                Arc::clone(&hrdf),
+                Arc::clone(&stop_index),
+                1,
+                Arc::clone(&excluded_polygons),
+                request(max_access_walk),
+                origin,
+            )
+        };
+        // Alpha
+        let alpha = (46.951081, 7.438637);
+
+        let response = isochrones(None, alpha).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // Several kilometers from the stops of the fixture.
+        let error = isochrones(None, (46.9, 7.3)).await.unwrap_err();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        for max_access_walk in [-1.0, f64::NAN] {
+            let error = isochrones(Some(max_access_walk), alpha).await.unwrap_err();
+            assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn test_isochrones_invalid_parameters() {
+        let hrdf = Arc::new(fixture_hrdf());
+        let stop_index = Arc::new(StopIndex::new(hrdf.data_storage()));
+        let excluded_polygons = Arc::new(MultiPolygon::new(vec![]));
+        let request = || ComputeIsochronesRequest {
+            origin_point_latitude: None,
+            origin_point_longitude: None,
+            departure_date: create_date(2025, 6, 2),
+            departure_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            time_limit: 30,
+            isochrone_interval: Some(10),
+            isochrone_interval_seconds: None,
+            num_bands: None,
+            display_mode: String::from("circles"),
+            find_optimal: false,
+            arrive_by: false,
+            max_transfer_walk: None,
+            max_access_walk: None,
+            countries: None,
+            transit_contribution: false,
+            snap_to_interval: false,
+            apply_excluded_polygons: None,
+            simplify_tolerance: None,
+            format: None,
+        };
+        let status = |request| async {
+            // Alpha
+            isochrones(
+                Arc::clone(&hrdf),
+                Arc::clone(&stop_index),
+                1,
+                Arc::clone(&excluded_polygons),
+                request,
+                (46.951081, 7.438637),
+            )
+            .await
+            .map_or_else(|error| error.status, |response| response.status())
+        };
+
+        assert_eq!(status(request()).await, StatusCode::OK);
+        let both_intervals = ComputeIsochronesRequest {
+            isochrone_interval_seconds: Some(600),
+            ..request()
+        };
+        assert_eq!(status(both_intervals).await, StatusCode::BAD_REQUEST);
+        let negative_transfer_walk = ComputeIsochronesRequest {
+            max_transfer_walk: Some(-1),
+            ..request()
+        };
+        assert_eq!(
+            status(negative_transfer_walk).await,
+            StatusCode::BAD_REQUEST
+        );
+        for find_optimal in [false, true] {
+            let arrive_by = ComputeIsochronesRequest {
+                arrive_by: true,
+                find_optimal,
+                ..request()
+            };
+            assert_eq!(status(arrive_by).await, StatusCode::NOT_IMPLEMENTED);
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn test_journey_with_max_transfer_walk() {
+        let hrdf = Arc::new(fixture_hrdf());
+        // Alpha to Foxtrot, only reached by the 5 minutes footpath from Echo.
+        let request = |max_transfer_walk| PlanJourneyRequest {
+            departure_stop_id: Some(8500001),
+            arrival_stop_id: Some(8500006),
+            departure_stop_name: None,
+            arrival_stop_name: None,
+            departure_date: create_date(2025, 6, 2),
+            departure_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            include_polylines: false,
+            max_transfer_walk,
+        };
+
+        let Json(response) = plan_journey(Arc::clone(&hrdf), Query(request(Some(5))))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.route.unwrap().arrival_at(),
+            NaiveDateTime::new(
+                create_date(2025, 6, 2),
+                NaiveTime::from_hms_opt(8, 37, 0).unwrap()
+            )
+        );
+        let error = plan_journey(Arc::clone(&hrdf), Query(request(Some(4))))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, StatusCode::NOT_FOUND);
+        assert!(error.error.starts_with("no route found"), "{}", error.error);
+        let error = plan_journey(hrdf, Query(request(Some(-1))))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error, "max_transfer_walk must not be negative");
+    }
+
+    #[test(tokio::test)]
+    async fn test_reachable_stops_pages() {
+        let hrdf = Arc::new(fixture_hrdf());
+        // Alpha
+        let request = |offset, limit| ReachableStopsRequest {
+            departure_stop_id: 8500001,
+            departure_date: create_date(2025, 6, 2),
+            departure_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            time_limit: 60,
+            max_transfer_walk: None,
+            offset,
+            limit,
+        };
+        let stop_ids = |response: &ReachableStopsResponse| {
+            response
+                .stops
+                .iter()
+                .map(|stop| stop.stop.id)
+                .collect::<Vec<_>>()
+        };
+
+        let Json(all) = reachable_stops(Arc::clone(&hrdf), Query(request(None, None)))
+            .await
+            .unwrap();
+        assert!(all.total > 3);
+        assert_eq!(all.total, all.stops.len());
+        assert_eq!(all.offset, 0);
+
+        let Json(page) = reachable_stops(hrdf, Query(request(Some(1), Some(2))))
+            .await
+            .unwrap();
+        assert_eq!(page.total, all.total);
+        assert_eq!(page.offset, 1);
+        assert_eq!(stop_ids(&page), stop_ids(&all)[1..3]);
+    }
+
+    #[test]
+    fn test_api_error_body() {
+        let response = ApiError::bad_request("display_mode must be circles or contour_line");
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            serde_json::json!({ "error": "display_mode must be circles or contour_line" })
+        );
+        assert_eq!(response.into_response().status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -0,0 +1,120 @@
+use hrdf_parser::{DataStorage, Model};
+use kd_tree::{KdPoint, KdTree};
+
+use crate::isochrone::utils::wgs84_to_lv95;
+
+/// A k-d tree of the stops having WGS84 coordinates, used for the spatial queries on the stops.
+/// The distances are computed in LV95 coordinates and given in meters.
+/// Building it takes a moment, it is kept next to the Hrdf it was built from (e.g. by the service)
+/// and passed to the computations.
+#[derive(Debug, Clone)]
+pub struct StopIndex {
+    tree: KdTree<StopPoint>,
+}
+
+impl StopIndex {
+    pub fn new(data_storage: &DataStorage) -> Self {
+        let points = data_storage
+            .stops()
+            .entries()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(position, stop)| {
+                let coord = stop.wgs84_coordinates()?;
+                let (easting, northing) = wgs84_to_lv95(coord.latitude()?, coord.longitude()?);
+                Some(StopPoint {
+                    point: [easting, northing],
+                    stop_id: stop.id(),
+                    position,
+                })
+            })
+            .collect();
+
+        Self {
+            tree: KdTree::build_by_ordered_float(points),
+        }
+    }
+
+    /// Returns the n stops closest to the point with their distance, the closest first.
+    pub fn nearest(&self, latitude: f64, longitude: f64, n: usize) -> Vec<(i32, f64)> {
+        let (easting, northing) = wgs84_to_lv95(latitude, longitude);
+        self.tree
+            .nearests(&[easting, northing], n)
+            .into_iter()
+            .map(|found| (found.item.stop_id, found.squared_distance.sqrt()))
+            .collect()
+    }
+
+    /// Returns the stops within radius meters of the point. The stops are given in the order of
+    /// the DataStorage entries, so that the result is deterministic.
+    pub fn within_radius(&self, latitude: f64, longitude: f64, radius: f64) -> Vec<i32> {
+        let (easting, northing) = wgs84_to_lv95(latitude, longitude);
+        let mut points = self.tree.within_radius(&[easting, northing], radius);
+        points.sort_by_key(|point| point.position);
+        points.into_iter().map(|point| point.stop_id).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StopPoint {
+    point: [f64; 2],
+    stop_id: i32,
+    // Position of the stop in the DataStorage entries.
+    position: usize,
+}
+
+impl KdPoint for StopPoint {
+    type Scalar = f64;
+    type Dim = typenum::U2;
+
+    fn at(&self, k: usize) -> f64 {
+        self.point[k]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hrdf_parser::Hrdf;
+    use test_log::test;
+
+    use super::*;
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_stop_index_queries() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+        let data_storage = hrdf.data_storage();
+        let stop_index = StopIndex::new(data_storage);
+        assert!(!stop_index.is_empty());
+
+        // Genève, gare Cornavin
+        let coord = data_storage
+            .stops()
+            .find(8587057)
+            .unwrap()
+            .wgs84_coordinates()
+            .unwrap();
+        let (latitude, longitude) = (coord.latitude().unwrap(), coord.longitude().unwrap());
+
+        let nearest = stop_index.nearest(latitude, longitude, 5);
+        assert_eq!(nearest.len(), 5);
+        assert!(nearest[0].1 < 1.0);
+        assert!(nearest.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+
+        let within_radius = stop_index.within_radius(latitude, longitude, nearest[4].1 + 1e-6);
+        for (stop_id, _) in nearest.iter() {
+            assert!(within_radius.contains(stop_id));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use hrdf_parser::{Hrdf, Model};
+
+/// Candidates with a lower score are not returned.
+const MIN_SCORE: f64 = 0.6;
+
+/// Returns the stops whose name matches the query, the best match first, with their id, name and
+/// score. The score is between 0 and 1, 1 being an exact match.
+/// The match ignores the case, the accents and the punctuation, and tolerates typos.
+pub fn resolve_stop(hrdf: &Hrdf, query: &str) -> Vec<(i32, String, f64)> {
+    let query = normalize(query);
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut candidates: Vec<_> = hrdf
+        .data_storage()
+        .stops()
+        .entries()
+        .into_iter()
+        .filter_map(|stop| {
+            let score = match_score(&query, &normalize(stop.name()));
+            (score >= MIN_SCORE).then(|| (stop.id(), String::from(stop.name()), score))
+        })
+        .collect();
+
+    // The shortest names first on equal scores, then the ids to keep the order deterministic.
+    candidates.sort_by(
+        |(lhs_id, lhs_name, lhs_score), (rhs_id, rhs_name, rhs_score)| {
+            rhs_score
+                .total_cmp(lhs_score)
+                .then(lhs_name.len().cmp(&rhs_name.len()))
+                .then(lhs_id.cmp(rhs_id))
+        },
+    );
+    candidates
+}
+
+/// Lowercases the text, removes the accents and replaces the punctuation by single spaces.
+fn normalize(text: &str) -> String {
+    let text: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ä' | 'ã' | 'å' => 'a',
+            'ç' => 'c',
+            'è' | 'é' | 'ê' | 'ë' => 'e',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'ñ' => 'n',
+            'ò' | 'ó' | 'ô' | 'ö' | 'õ' => 'o',
+            'ù' | 'ú' | 'û' | 'ü' => 'u',
+            'ÿ' => 'y',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Scores a normalized query against a normalized name. A name containing the whole query scores
+/// at least 0.8, otherwise each word of the query is compared to the closest word of the name.
+fn match_score(query: &str, name: &str) -> f64 {
+    if name == query {
+        return 1.0;
+    }
+    if name.contains(query) {
+        return 0.8 + 0.2 * query.len() as f64 / name.len() as f64;
+    }
+
+    let name_words: Vec<_> = name.split(' ').collect();
+    let query_words: Vec<_> = query.split(' ').collect();
+    let total: f64 = query_words
+        .iter()
+        .map(|query_word| {
+            name_words
+                .iter()
+                .map(|name_word| word_similarity(query_word, name_word))
+                .fold(0.0, f64::max)
+        })
+        .sum();
+    0.8 * total / query_words.len() as f64
+}
+
+/// A word of the query which is the start of a word of the name matches fully, so that the names
+/// can be typed partially.
+fn word_similarity(query_word: &str, name_word: &str) -> f64 {
+    if name_word.starts_with(query_word) {
+        return 1.0;
+    }
+    let max_len = query_word.chars().count().max(name_word.chars().count());
+    1.0 - edit_distance(query_word, name_word) as f64 / max_len as f64
+}
+
+/// Levenshtein distance between the two strings, in characters.
+fn edit_distance(lhs: &str, rhs: &str) -> usize {
+    let rhs: Vec<_> = rhs.chars().collect();
+    let mut previous: Vec<_> = (0..=rhs.len()).collect();
+    let mut current = vec![0; rhs.len() + 1];
+
+    for (i, lhs_char) in lhs.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, rhs_char) in rhs.iter().enumerate() {
+            let substitution = previous[j] + usize::from(lhs_char != *rhs_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[rhs.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use test_log::test;
+
+    use super::*;
+
+    #[test]
+    fn test_normalize_and_score() {
+        assert_eq!(
+            normalize("  Genève,  gare Cornavin "),
+            "geneve gare cornavin"
+        );
+        assert_eq!(edit_distance("cornavin", "cornavn"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(match_score("geneve", "geneve"), 1.0);
+        assert!(match_score("geneve", "geneve gare cornavin") >= 0.8);
+        assert!(match_score("geneve cornavn", "geneve gare cornavin") > MIN_SCORE);
+        assert!(match_score("zurich", "geneve gare cornavin") < MIN_SCORE);
+    }
+
+    #[test(tokio::test)]
+    #[cfg_attr(
+        not(feature = "network-tests"),
+        ignore = "downloads the HRDF timetable"
+    )]
+    async fn test_resolve_stop() {
+        let hrdf = Hrdf::try_from_year(2025, false, None).await.unwrap();
+
+        let candidates = resolve_stop(&hrdf, "geneve cornavin");
+        assert!(candidates.windows(2).all(|pair| pair[0].2 >= pair[1].2));
+        assert!(
+            candidates
+                .iter()
+                .take(5)
+                .any(|(stop_id, _, _)| *stop_id == 8587057)
+        );
+        assert!(resolve_stop(&hrdf, " ,. ").is_empty());
+    }
+}
@@ -0,0 +1,327 @@
+use std::{ops::RangeBounds, path::Path};
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use hrdf_parser::{DataStorage, Hrdf, Model, Version, timetable_end_date, timetable_start_date};
+use rustc_hash::FxHashSet;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    error::RResult,
+    routing::{BoardingRestrictions, Transport, departures_within_window, get_operating_journeys},
+    utils::add_1_day,
+};
+
+/// Number of ids given as sample in the warning about the dangling stop references.
+const NUM_SAMPLE_STOP_IDS: usize = 10;
+
+/// Loads the timetable from the HRDF files of a directory, e.g. an extracted archive or a small
+/// fixture. Hrdf::new only reads archives: the Hrdf, which only wraps the DataStorage, is built
+/// from the serialized DataStorage.
+pub fn hrdf_from_directory(version: Version, path: &Path) -> RResult<Hrdf> {
+    let data_storage = DataStorage::new(version, path)?;
+    Ok(postcard::from_bytes(&postcard::to_stdvec(&data_storage)?)?)
+}
+
+/// Checks whether the date is within the timetable period.
+pub fn is_date_within_timetable(hrdf: &Hrdf, date: NaiveDate) -> bool {
+    let timetable_metadata = hrdf.data_storage().timetable_metadata();
+    match (
+        timetable_start_date(timetable_metadata),
+        timetable_end_date(timetable_metadata),
+    ) {
+        (Ok(start_date), Ok(end_date)) => start_date <= date && date <= end_date,
+        _ => false,
+    }
+}
+
+/// Moves the departure to the closest day of the timetable period, keeping its time, so that a
+/// relative departure such as `now` can be used with any timetable. Logs a warning when it moves.
+pub fn clamp_to_timetable(hrdf: &Hrdf, departure_at: NaiveDateTime) -> NaiveDateTime {
+    let timetable_metadata = hrdf.data_storage().timetable_metadata();
+    let (Ok(start_date), Ok(end_date)) = (
+        timetable_start_date(timetable_metadata),
+        timetable_end_date(timetable_metadata),
+    ) else {
+        return departure_at;
+    };
+
+    let date = departure_at.date().clamp(start_date, end_date);
+    if date != departure_at.date() {
+        log::warn!(
+            "The departure {departure_at} is outside the timetable period ({start_date} - {end_date}), it is moved to {date}"
+        );
+    }
+    date.and_time(departure_at.time())
+}
+
+/// Checks whether a journey can be changed at the stop. The routing only explores the connections
+/// at such stops, a stop which is not one can still be the departure or the arrival of a route.
+/// Unknown stops are not exchange points.
+pub fn is_exchange_point(hrdf: &Hrdf, stop_id: i32) -> bool {
+    hrdf.data_storage()
+        .stops()
+        .find(stop_id)
+        .is_some_and(|stop| stop.can_be_used_as_exchange_point())
+}
+
+/// Returns the station the stop belongs to, e.g. Zürich HB (8503000) for Zürich HB SZU (8503088).
+/// HRDF has no parent stops, the quays of a station are the tracks of a single stop, but some
+/// stations are split into one stop per operator. Such a stop is linked by a footpath to its
+/// station, whose name followed by a space starts the name of the stop.
+/// None if the stop is its own station or is unknown.
+pub fn parent_station(hrdf: &Hrdf, stop_id: i32) -> Option<i32> {
+    let data_storage = hrdf.data_storage();
+    let name = data_storage.stops().find(stop_id)?.name();
+    let stop_connections = data_storage
+        .stop_connections()
+        .resolve_ids(data_storage.stop_connections_by_stop_id().get(&stop_id)?)?;
+
+    stop_connections
+        .into_iter()
+        .filter_map(|stop_connection| {
+            let other_stop_id = if stop_connection.stop_id_1() == stop_id {
+                stop_connection.stop_id_2()
+            } else {
+                stop_connection.stop_id_1()
+            };
+            let other_stop = data_storage.stops().find(other_stop_id)?;
+            name.strip_prefix(other_stop.name())
+                .is_some_and(|suffix| suffix.starts_with(' '))
+                .then(|| (other_stop_id, other_stop.name().len()))
+        })
+        // The shortest name is the station itself, not one of its other stops.
+        .min_by_key(|&(_, name_len)| name_len)
+        .map(|(other_stop_id, _)| other_stop_id)
+}
+
+/// Returns the sorted ids of the stops referenced by the journeys or the stop connections but
+/// missing from the stop table. The routing skips them silently.
+pub fn dangling_stop_ids(hrdf: &Hrdf) -> Vec<i32> {
+    let data_storage = hrdf.data_storage();
+    let stops = data_storage.stops().data();
+
+    let journey_stop_ids = data_storage
+        .journeys()
+        .entries()
+        .into_iter()
+        .flat_map(|journey| {
+            journey
+                .route()
+                .iter()
+                .map(|route_entry| route_entry.stop_id())
+        });
+    let connection_stop_ids = data_storage
+        .stop_connections()
+        .entries()
+        .into_iter()
+        .flat_map(|stop_connection| [stop_connection.stop_id_1(), stop_connection.stop_id_2()]);
+
+    let mut stop_ids: Vec<_> = journey_stop_ids
+        .chain(connection_stop_ids)
+        .filter(|stop_id| !stops.contains_key(stop_id))
+        .collect::<FxHashSet<_>>()
+        .into_iter()
+        .collect();
+    stop_ids.sort_unstable();
+    stop_ids
+}
+
+/// Logs the number of dangling stop references and a sample of their ids, if any, so that they
+/// can be reported to the publisher of the HRDF.
+pub fn warn_dangling_stop_ids(hrdf: &Hrdf) {
+    let stop_ids = dangling_stop_ids(hrdf);
+    if !stop_ids.is_empty() {
+        log::warn!(
+            "{} stops are referenced but missing from the stop table, e.g. {:?}",
+            stop_ids.len(),
+            &stop_ids[..stop_ids.len().min(NUM_SAMPLE_STOP_IDS)]
+        );
+    }
+}
+
+/// Checks whether at least one journey serves the stop on the date.
+/// Days outside the timetable period are never served.
+pub fn is_stop_served_on(hrdf: &Hrdf, stop_id: i32, date: NaiveDate) -> bool {
+    let data_storage = hrdf.data_storage();
+    match (
+        data_storage.bit_fields_by_stop_id().get(&stop_id),
+        data_storage.bit_fields_by_day().get(&date),
+    ) {
+        (Some(bit_fields_of_stop), Some(bit_fields_of_day)) => {
+            !bit_fields_of_stop.is_disjoint(bit_fields_of_day)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the days of the given month on which at least one journey serves the stop.
+/// Days outside the timetable period are never served.
+pub fn served_days_for_stop(hrdf: &Hrdf, stop_id: i32, year: i32, month: u32) -> Vec<NaiveDate> {
+    let Some(first_day) = NaiveDate::from_ymd_opt(year, month, 1) else {
+        return Vec::new();
+    };
+
+    first_day
+        .iter_days()
+        .take_while(|date| date.month() == month)
+        .filter(|&date| is_stop_served_on(hrdf, stop_id, date))
+        .collect()
+}
+
+/// Statistics of the time between two consecutive departures from a stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadwayStats {
+    /// Number of departures.
+    pub count: usize,
+    /// The headways are None with less than two departures.
+    pub min: Option<Duration>,
+    pub max: Option<Duration>,
+    pub mean: Option<Duration>,
+}
+
+impl HeadwayStats {
+    /// The departures must be sorted.
+    fn from_departures(departures: &[NaiveDateTime]) -> Self {
+        let headways: Vec<_> = departures
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect();
+        let mean = (!headways.is_empty()).then(|| {
+            headways.iter().sum::<Duration>() / i32::try_from(headways.len()).unwrap_or(i32::MAX)
+        });
+        Self {
+            count: departures.len(),
+            min: headways.iter().min().copied(),
+            max: headways.iter().max().copied(),
+            mean,
+        }
+    }
+}
+
+/// Computes the headways of the departures from the stop on the date within the window of the
+/// day, whatever their destination, e.g. `from..` until the end of the day. Journeys departing at
+/// the same time count as one departure.
+/// There is no departure on a date outside the timetable period.
+pub fn headway_stats(
+    hrdf: &Hrdf,
+    stop_id: i32,
+    date: NaiveDate,
+    window: impl RangeBounds<NaiveTime>,
+) -> HeadwayStats {
+    if !is_date_within_timetable(hrdf, date) {
+        return HeadwayStats::from_departures(&[]);
+    }
+
+    let mut departures: Vec<_> = get_operating_journeys(hrdf.data_storage(), date, stop_id)
+        .into_iter()
+        .filter(|journey| {
+            journey
+                .is_last_stop(stop_id, true)
+                .is_ok_and(|is_last| !is_last)
+        })
+        .filter_map(|journey| journey.departure_at_of(stop_id, date).ok())
+        .filter(|departure_at| departure_at.date() == date && window.contains(&departure_at.time()))
+        .collect();
+    departures.sort_unstable();
+    departures.dedup();
+    HeadwayStats::from_departures(&departures)
+}
+
+/// A departure from a stop, as shown on a departure board.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DepartureBoardEntry {
+    pub journey_id: i32,
+    pub departure_at: NaiveDateTime,
+    #[schema(value_type = String)]
+    pub transport: Transport,
+    /// The designation of the transport type, e.g. IC or B.
+    pub designation: String,
+    /// The terminus of the journey.
+    pub destination_stop_id: i32,
+    pub destination_name: String,
+}
+
+/// Returns the next n departures from the stop at or after from, until the end of the next day,
+/// including those of the journeys of the previous day still running after midnight. Unlike the
+/// departures explored by the routing, the journeys going to the same terminus are all kept. The
+/// journeys which can't be boarded at the stop, see BoardingRestrictions, are left out.
+/// There is no departure on a date outside the timetable period.
+pub fn departure_board(
+    hrdf: &Hrdf,
+    stop_id: i32,
+    from: NaiveDateTime,
+    n: usize,
+    restrictions: Option<&BoardingRestrictions>,
+) -> Vec<DepartureBoardEntry> {
+    if !is_date_within_timetable(hrdf, from.date()) {
+        return Vec::new();
+    }
+    let data_storage = hrdf.data_storage();
+    let end_of_next_day = NaiveDateTime::new(add_1_day(add_1_day(from.date())), NaiveTime::MIN);
+
+    let mut departures = departures_within_window(
+        data_storage,
+        stop_id,
+        from,
+        end_of_next_day - from,
+        NaiveTime::MIN,
+        restrictions,
+    );
+    departures.sort_by_key(|&(journey, departure_at)| (departure_at, journey.id()));
+
+    departures
+        .into_iter()
+        .filter_map(|(journey, departure_at)| {
+            let transport_type = journey.transport_type(data_storage).ok()?;
+            let destination = journey.route().last()?.stop(data_storage).ok()?;
+            Some(DepartureBoardEntry {
+                journey_id: journey.id(),
+                departure_at,
+                transport: Transport::from(transport_type),
+                designation: String::from(transport_type.designation()),
+                destination_stop_id: destination.id(),
+                destination_name: String::from(destination.name()),
+            })
+        })
+        .take(n)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_log::test;
+
+    use crate::utils::create_date_time;
+
+    use super::*;
+
+    #[test]
+    fn test_headway_stats_from_departures() {
+        let departures = [
+            create_date_time(2025, 6, 2, 8, 0),
+            create_date_time(2025, 6, 2, 8, 10),
+            create_date_time(2025, 6, 2, 8, 40),
+        ];
+        assert_eq!(
+            HeadwayStats::from_departures(&departures),
+            HeadwayStats {
+                count: 3,
+                min: Some(Duration::minutes(10)),
+                max: Some(Duration::minutes(30)),
+                mean: Some(Duration::minutes(20)),
+            }
+        );
+
+        // A single departure has no headway.
+        assert_eq!(
+            HeadwayStats::from_departures(&departures[..1]),
+            HeadwayStats {
+                count: 1,
+                min: None,
+                max: None,
+                mean: None,
+            }
+        );
+    }
+}
@@ -1,7 +1,14 @@
+use std::num::NonZeroUsize;
+
 use chrono::{Days, Duration, NaiveDate, NaiveDateTime, NaiveTime};
 
 // TODO: ...
 
+/// The prefix of the log messages of a labelled computation, e.g. "[request 12] ".
+pub fn log_prefix(label: Option<&str>) -> String {
+    label.map_or_else(String::new, |label| format!("[{label}] "))
+}
+
 pub fn add_1_day(date: NaiveDate) -> NaiveDate {
     date.checked_add_days(Days::new(1)).unwrap()
 }
@@ -42,10 +49,62 @@ pub fn inner_threads(num_threads: usize, in_parallel: bool) -> usize {
     }
 }
 
+/// Same as `inner_threads`, but `num_threads == 0` is resolved to the number of threads the
+/// machine can run in parallel, so that a nested region does not oversubscribe the global pool.
+pub fn available_inner_threads(num_threads: usize, in_parallel: bool) -> usize {
+    let num_threads = if num_threads == 0 {
+        available_threads()
+    } else {
+        num_threads
+    };
+    inner_threads(num_threads, in_parallel)
+}
+
+/// The number of threads that can run in parallel, 1 if it cannot be determined.
+pub fn available_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// The small timetable of tests/fixtures/hrdf, running every day of June 2025:
+/// - IC 101 and 105 Alpha 08:00/09:00 - Bravo - Charlie, IC 102 Charlie 08:30 - Bravo - Alpha.
+///   Bravo is board-only towards Charlie and alight-only towards Alpha.
+/// - Bus 201 and 203 Charlie 08:18/09:18 - Delta - Echo, a footpath links Echo and Foxtrot.
+/// - R 301 Alpha 08:03 - Golf - Juliett (not an exchange point) - Delta, S 302 Alpha 08:10 -
+///   Bravo.
+/// - Bus 401 Alpha 09:00 - Hotel - India - Alpha - Hotel, a circular line.
+/// - SN 501 Alpha 23:50 - Golf 00:10 - Delta 00:40, running past midnight.
+#[cfg(test)]
+pub fn fixture_hrdf() -> hrdf_parser::Hrdf {
+    crate::hrdf_from_directory(
+        hrdf_parser::Version::V_5_40_41_2_0_7,
+        &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hrdf"),
+    )
+    .unwrap()
+}
+
+/// The board-only and alight-only stops of fixture_hrdf.
+#[cfg(test)]
+pub fn fixture_boarding_restrictions() -> crate::BoardingRestrictions {
+    crate::BoardingRestrictions::from_fplan(
+        &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hrdf/FPLAN"),
+    )
+    .unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_fixture_hrdf() {
+        let hrdf = fixture_hrdf();
+        let data_storage = hrdf.data_storage();
+        assert_eq!(data_storage.stops().entries().len(), 10);
+        assert_eq!(data_storage.journeys().entries().len(), 9);
+        assert_eq!(data_storage.stop_connections().entries().len(), 2);
+        assert_eq!(data_storage.default_exchange_time(), (3, 6));
+    }
+
     #[test]
     fn test_add_1_day() {
         let date = create_date(2026, 1, 31);
@@ -143,4 +202,14 @@ mod tests {
         assert_eq!(inner_threads(4, false), 4);
         assert_eq!(inner_threads(1, false), 1);
     }
+
+    #[test]
+    fn test_available_inner_threads_resolves_the_global_pool() {
+        let available = std::thread::available_parallelism().unwrap().get();
+        assert_eq!(available_threads(), available);
+        assert_eq!(available_inner_threads(0, false), available);
+        assert_eq!(available_inner_threads(0, true), 1);
+        assert_eq!(available_inner_threads(4, false), 4);
+        assert_eq!(available_inner_threads(4, true), 1);
+    }
 }